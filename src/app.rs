@@ -1,60 +1,169 @@
 use serde::{Deserialize, Serialize};
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
 use tui_textarea::TextArea;
 
+use crate::assertions::TestResult;
+use crate::auth::{Auth, AuthField, AuthKind};
+use crate::collection::{CollectionItem, CollectionStore, SavedRequest, TreeRow};
+use crate::config::Config;
+use crate::cookies::{parse_set_cookie, unix_now, CookieJars, SharedJar};
+use crate::environment::EnvironmentStore;
+use crate::export::{render_snippet, SnippetFormat};
+use crate::history::HistoryStore;
+use crate::jwt::{self, FoundJwt};
 use crate::key_value::KeyValueEntries;
+use crate::network::{make_request, ApiResponse, ClientPool};
+use crate::request::{BodyMode, RequestSpec, TEXT_CONTENT_TYPES};
+use crate::scripts::{self, ScriptStage};
+use crate::settings::{effective_settings, RequestSettings};
+use crate::timing::Timing;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RequestHistoryEntry {
-    pub method: HttpMethod,
-    pub url: String,
-    pub headers: KeyValueEntries,
-    pub params: KeyValueEntries,
-    pub auth: KeyValueEntries,
-    pub body: String,
+    /// Tells entries apart while the app runs; not stored
+    #[serde(skip)]
+    pub id: u64,
+    #[serde(flatten)]
+    pub request: RequestSpec,
     pub timestamp: u64,
+    /// Timing of the response, once one arrived
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timing: Option<Timing>,
 }
 
 impl RequestHistoryEntry {
-    pub fn new(
-        method: HttpMethod,
-        url: String,
-        headers: KeyValueEntries,
-        params: KeyValueEntries,
-        auth: KeyValueEntries,
-        body: String,
-    ) -> Self {
+    pub fn new(request: RequestSpec) -> Self {
         let timestamp = SystemTime::now()
             .duration_since(UNIX_EPOCH)
             .unwrap_or_default()
             .as_secs();
         Self {
-            method,
-            url,
-            headers,
-            params,
-            auth,
-            body,
+            id: 0,
+            request,
             timestamp,
+            timing: None,
         }
     }
 }
 
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
 pub enum HttpMethod {
     GET,
     POST,
     PUT,
     DELETE,
     PATCH,
+    HEAD,
+    OPTIONS,
+    TRACE,
+    CONNECT,
+    /// Any other method token, e.g. `PURGE` or `PROPFIND`
+    Custom(String),
+}
+
+impl HttpMethod {
+    /// Methods cycled through with Left/Right in the Method pane
+    pub const STANDARD: [HttpMethod; 9] = [
+        HttpMethod::GET,
+        HttpMethod::POST,
+        HttpMethod::PUT,
+        HttpMethod::DELETE,
+        HttpMethod::PATCH,
+        HttpMethod::HEAD,
+        HttpMethod::OPTIONS,
+        HttpMethod::TRACE,
+        HttpMethod::CONNECT,
+    ];
+
+    pub fn to_reqwest(&self) -> anyhow::Result<reqwest::Method> {
+        reqwest::Method::from_bytes(self.to_string().as_bytes())
+            .map_err(|_| anyhow::anyhow!("Invalid HTTP method '{}'", self))
+    }
 }
 
 impl std::fmt::Display for HttpMethod {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{:?}", self)
+        match self {
+            HttpMethod::Custom(method) => write!(f, "{}", method),
+            other => write!(f, "{:?}", other),
+        }
     }
 }
 
+impl std::str::FromStr for HttpMethod {
+    type Err = anyhow::Error;
+
+    /// Standard methods match case-insensitively; anything else must be a
+    /// valid HTTP token and is kept exactly as typed
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if let Some(method) = Self::STANDARD
+            .iter()
+            .find(|m| m.to_string().eq_ignore_ascii_case(s))
+        {
+            return Ok(method.clone());
+        }
+        let is_token = !s.is_empty()
+            && s.chars()
+                .all(|c| c.is_ascii_alphanumeric() || "!#$%&'*+-.^_`|~".contains(c));
+        if !is_token {
+            anyhow::bail!("Invalid HTTP method '{}'", s);
+        }
+        Ok(HttpMethod::Custom(s.to_string()))
+    }
+}
+
+impl TryFrom<String> for HttpMethod {
+    type Error = anyhow::Error;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<HttpMethod> for String {
+    fn from(method: HttpMethod) -> Self {
+        method.to_string()
+    }
+}
+
+/// A response (or error) from the request task, tagged with its request id
+pub type ResponseMessage = (u64, Result<ApiResponse, String>);
+
+/// A request currently being sent
+#[derive(Debug)]
+pub struct InFlight {
+    pub id: u64,
+    pub started: Instant,
+    /// The request as sent, whose tests and scripts check the response
+    request: RequestSpec,
+    /// The request before its pre-request script ran and variables were
+    /// substituted, which a retry starts from
+    original: RequestSpec,
+    /// History entry the response's timing is stored in
+    history_id: u64,
+    /// 1, plus one for each time the post-response script asked to retry
+    attempt: u32,
+    handle: JoinHandle<()>,
+}
+
+/// A request to send again once `at` is reached, as the post-response
+/// script asked
+#[derive(Debug)]
+pub struct PendingRetry {
+    pub at: Instant,
+    /// The request before its pre-request script ran
+    original: RequestSpec,
+    history_id: u64,
+    attempt: u32,
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum InputMode {
     Normal,
@@ -63,6 +172,7 @@ pub enum InputMode {
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum FocusedPane {
+    Collections,
     Method,
     Url,
     RequestDetails,
@@ -70,14 +180,80 @@ pub enum FocusedPane {
     Response, // Maybe for scrolling
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResponseTab {
+    Body,
+    Headers,
+    Cookies,
+    Timing,
+    Tests,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum RequestTab {
     Params,
     Headers,
     Authorization,
+    Settings,
+    Tests,
+    Captures,
+    Scripts,
+}
+
+/// Popup editing one of the request's scripts
+#[derive(Debug, Clone)]
+pub struct ScriptEditor {
+    pub stage: ScriptStage,
+    pub textarea: TextArea<'static>,
 }
 
+/// Popup showing the current request as a code snippet
 #[derive(Debug, Clone)]
+pub struct ExportView {
+    pub format: SnippetFormat,
+    pub text: String,
+    pub scroll: u16,
+}
+
+/// Popup decoding the JWTs found in the response
+#[derive(Debug, Clone)]
+pub struct JwtView {
+    pub tokens: Vec<FoundJwt>,
+    pub selected: usize,
+    pub scroll: u16,
+}
+
+/// What a pending name prompt in the collections sidebar will do
+#[derive(Debug, Clone, PartialEq)]
+pub enum CollectionAction {
+    SaveRequest,
+    NewFolder,
+    Rename,
+    ConfirmDelete,
+}
+
+#[derive(Debug, Clone)]
+pub struct CollectionPrompt {
+    pub action: CollectionAction,
+    pub input: String,
+}
+
+/// Popup listing the cookies of the active environment
+#[derive(Debug, Clone, Default)]
+pub struct CookieView {
+    pub selected: usize,
+    pub edit: Option<CookieEdit>,
+}
+
+/// Text being typed in the cookie popup
+#[derive(Debug, Clone)]
+pub struct CookieEdit {
+    /// Index of the cookie whose value is edited; `None` when adding a cookie
+    pub target: Option<usize>,
+    pub input: String,
+}
+
+#[derive(Debug)]
 pub struct App {
     pub running: bool,
     pub input_mode: InputMode,
@@ -86,100 +262,467 @@ pub struct App {
     // Request Data
     pub method: HttpMethod,
     pub url_input: String,
+    /// Method name being typed in the Method pane
+    pub method_input: Option<String>,
     pub active_request_tab: RequestTab,
     pub headers: KeyValueEntries,
     pub params: KeyValueEntries,
-    pub authorization: KeyValueEntries,
+    pub auth: Auth,
+    /// Index into `auth.fields()` of the focused Auth tab field
+    pub auth_field: usize,
+    pub settings: KeyValueEntries,
+    pub tests: KeyValueEntries,
+    pub captures: KeyValueEntries,
+    pub pre_request_script: String,
+    pub post_response_script: String,
+    /// Script selected in the Scripts tab
+    pub script_stage: ScriptStage,
+    pub body_mode: BodyMode,
     pub body_input: TextArea<'static>,
+    /// Fields for the form body modes
+    pub form: KeyValueEntries,
+    /// File sent in `BodyMode::Binary`
+    pub body_file: String,
+    pub text_content_type: String,
 
     // Response Data (Placeholder for now)
     pub response_text: Option<String>,
     pub response_status: Option<u16>,
     pub response_scroll: u16,
+    pub response: Option<ApiResponse>,
+    pub active_response_tab: ResponseTab,
+    /// Outcome of the Tests tab assertions for the last response, plus
+    /// any captures that failed
+    pub test_results: Vec<TestResult>,
+    /// Lines printed by the scripts of the last send
+    pub script_log: Vec<String>,
+    pub in_flight: Option<InFlight>,
+    pub pending_retry: Option<PendingRetry>,
+    /// Id given to the most recently sent request
+    pub last_request_id: u64,
+    /// Id given to the most recent history entry
+    pub last_history_id: u64,
 
     // Request History
     pub history: Vec<RequestHistoryEntry>,
     pub history_index: Option<usize>,
+    pub history_store: HistoryStore,
+
+    /// Client settings from the config file, overridden per request
+    pub request_defaults: BTreeMap<String, String>,
+
+    // Saved Request Collections
+    pub collections: CollectionStore,
+    pub collection_selected: usize,
+    pub collection_prompt: Option<CollectionPrompt>,
+
+    // Environments for {{variable}} substitution
+    pub environments: EnvironmentStore,
+
+    // Cookies received from responses, one jar per environment
+    pub cookie_jars: CookieJars,
+    pub cookie_view: Option<CookieView>,
+
+    /// HTTP clients reused across requests
+    pub clients: ClientPool,
 
     // JSON Validation
     pub validation_error: Option<(usize, usize, String)>, // (line, column, message)
+
+    // curl import popup, open while pasting a command
+    pub curl_import: Option<TextArea<'static>>,
+
+    // Script editor popup
+    pub script_editor: Option<ScriptEditor>,
+
+    // Code snippet export popup
+    pub export: Option<ExportView>,
+
+    // JWT decoder popup
+    pub jwt_view: Option<JwtView>,
+
+    // Transient message shown in the footer (warnings, save errors, ...)
+    pub status_message: Option<String>,
+}
+
+/// Where the app reads and writes its files
+#[derive(Debug, Clone)]
+pub struct AppPaths {
+    pub config: PathBuf,
+    pub history: PathBuf,
+    pub collection: PathBuf,
+    pub environments: PathBuf,
+    pub cookies: PathBuf,
+}
+
+impl Default for AppPaths {
+    /// The per-user config and data directories and the workspace
+    fn default() -> Self {
+        Self {
+            config: Config::path(),
+            history: HistoryStore::default_path(),
+            collection: CollectionStore::default_path(),
+            environments: EnvironmentStore::default_path(),
+            cookies: CookieJars::default_path(),
+        }
+    }
+}
+
+impl AppPaths {
+    /// Every file in `dir`
+    #[cfg(test)]
+    pub fn in_dir(dir: &std::path::Path) -> Self {
+        Self {
+            config: dir.join("config.json"),
+            history: dir.join("history.json"),
+            collection: dir.join("collection.json"),
+            environments: dir.join("environments.json"),
+            cookies: dir.join("cookies.json"),
+        }
+    }
 }
 
 impl App {
     pub fn new() -> App {
+        Self::with_paths(&AppPaths::default())
+    }
+
+    pub fn with_paths(paths: &AppPaths) -> App {
+        let mut warnings = Vec::new();
+        let config = Config::load_from(&paths.config).unwrap_or_else(|e| {
+            warnings.push(format!("{:#}", e));
+            Config::default()
+        });
+        if let Err(e) =
+            RequestSettings::parse(config.request.iter().map(|(k, v)| (k.as_str(), v.as_str())))
+        {
+            warnings.push(format!("config.json: {:#}", e));
+        }
+        let history_store = HistoryStore::new(paths.history.clone(), config.history_limit);
+        let (history, history_warning) = history_store.load();
+        warnings.extend(history_warning);
+        let collections = CollectionStore::load(paths.collection.clone()).unwrap_or_else(|e| {
+            warnings.push(format!("{:#}", e));
+            CollectionStore::detached()
+        });
+        let environments = EnvironmentStore::load(paths.environments.clone()).unwrap_or_else(|e| {
+            warnings.push(format!("{:#}", e));
+            EnvironmentStore::detached()
+        });
+
+        let cookie_jars = if config.persist_cookies {
+            CookieJars::load(paths.cookies.clone()).unwrap_or_else(|e| {
+                warnings.push(format!("{:#}", e));
+                CookieJars::in_memory()
+            })
+        } else {
+            CookieJars::in_memory()
+        };
+
         App {
             running: true,
             input_mode: InputMode::Normal,
             focused_pane: FocusedPane::Url,
             method: HttpMethod::GET,
             url_input: String::new(),
+            method_input: None,
             active_request_tab: RequestTab::Headers,
             headers: KeyValueEntries::new(),
             params: KeyValueEntries::new(),
-            authorization: KeyValueEntries::new(),
+            auth: Auth::default(),
+            auth_field: 0,
+            settings: KeyValueEntries::new(),
+            tests: KeyValueEntries::new(),
+            captures: KeyValueEntries::new(),
+            pre_request_script: String::new(),
+            post_response_script: String::new(),
+            script_stage: ScriptStage::PreRequest,
+            body_mode: BodyMode::Json,
             body_input: TextArea::default(),
+            form: KeyValueEntries::new(),
+            body_file: String::new(),
+            text_content_type: TEXT_CONTENT_TYPES[0].to_string(),
             response_text: None,
             response_status: None,
             response_scroll: 0,
-            history: Vec::new(),
+            response: None,
+            active_response_tab: ResponseTab::Body,
+            test_results: Vec::new(),
+            script_log: Vec::new(),
+            in_flight: None,
+            pending_retry: None,
+            last_request_id: 0,
+            last_history_id: 0,
+            history,
             history_index: None,
+            history_store,
+            request_defaults: config.request,
+            collections,
+            collection_selected: 0,
+            collection_prompt: None,
+            environments,
+            cookie_jars,
+            cookie_view: None,
+            clients: ClientPool::default(),
             validation_error: None,
+            curl_import: None,
+            script_editor: None,
+            export: None,
+            jwt_view: None,
+            status_message: (!warnings.is_empty()).then(|| warnings.join("; ")),
         }
     }
 
     pub fn next_method(&mut self) {
-        self.method = match self.method {
-            HttpMethod::GET => HttpMethod::POST,
-            HttpMethod::POST => HttpMethod::PUT,
-            HttpMethod::PUT => HttpMethod::DELETE,
-            HttpMethod::DELETE => HttpMethod::PATCH,
-            HttpMethod::PATCH => HttpMethod::GET,
+        let idx = HttpMethod::STANDARD.iter().position(|m| *m == self.method);
+        self.method = match idx {
+            Some(i) => HttpMethod::STANDARD[(i + 1) % HttpMethod::STANDARD.len()].clone(),
+            None => HttpMethod::GET,
         };
     }
 
     pub fn prev_method(&mut self) {
-        self.method = match self.method {
-            HttpMethod::GET => HttpMethod::PATCH,
-            HttpMethod::POST => HttpMethod::GET,
-            HttpMethod::PUT => HttpMethod::POST,
-            HttpMethod::DELETE => HttpMethod::PUT,
-            HttpMethod::PATCH => HttpMethod::DELETE,
+        let len = HttpMethod::STANDARD.len();
+        let idx = HttpMethod::STANDARD.iter().position(|m| *m == self.method);
+        self.method = match idx {
+            Some(i) => HttpMethod::STANDARD[(i + len - 1) % len].clone(),
+            None => HttpMethod::GET,
         };
     }
 
+    pub fn begin_method_edit(&mut self) {
+        self.method_input = Some(self.method.to_string());
+        self.input_mode = InputMode::Editing;
+    }
+
+    /// Leave method editing, applying the typed method if `apply` is set
+    pub fn finish_method_edit(&mut self, apply: bool) {
+        if let Some(input) = self.method_input.take() {
+            if apply {
+                match input.parse() {
+                    Ok(method) => self.method = method,
+                    Err(e) => self.status_message = Some(e.to_string()),
+                }
+            }
+        }
+        self.input_mode = InputMode::Normal;
+    }
+
     pub fn quit(&mut self) {
         self.running = false;
     }
 
-    /// Check if the focus is on the RequestDetails pane (tabbed Headers/Params/Auth)
+    /// Check if the focus is on the RequestDetails pane (tabbed Headers/Params/Auth/...)
     pub fn is_in_request_details(&self) -> bool {
         self.focused_pane == FocusedPane::RequestDetails
     }
 
-    /// Cycle to next request tab
+    pub fn is_request_tab_active(&self, tab: &RequestTab) -> bool {
+        &self.active_request_tab == tab
+    }
+
+    /// Cycle to the next tab of the focused pane
     pub fn next_tab(&mut self) {
+        match self.focused_pane {
+            FocusedPane::RequestDetails => self.next_request_tab(),
+            FocusedPane::Response => self.next_response_tab(),
+            _ => {}
+        }
+    }
+
+    /// Cycle to the previous tab of the focused pane
+    pub fn prev_tab(&mut self) {
+        match self.focused_pane {
+            FocusedPane::RequestDetails => self.prev_request_tab(),
+            FocusedPane::Response => self.prev_response_tab(),
+            _ => {}
+        }
+    }
+
+    /// Cycle to next request tab
+    pub fn next_request_tab(&mut self) {
         self.active_request_tab = match self.active_request_tab {
             RequestTab::Headers => RequestTab::Params,
             RequestTab::Params => RequestTab::Authorization,
-            RequestTab::Authorization => RequestTab::Headers,
+            RequestTab::Authorization => RequestTab::Settings,
+            RequestTab::Settings => RequestTab::Tests,
+            RequestTab::Tests => RequestTab::Captures,
+            RequestTab::Captures => RequestTab::Scripts,
+            RequestTab::Scripts => RequestTab::Headers,
         };
     }
 
     /// Cycle to previous request tab
-    pub fn prev_tab(&mut self) {
+    pub fn prev_request_tab(&mut self) {
         self.active_request_tab = match self.active_request_tab {
-            RequestTab::Headers => RequestTab::Authorization,
+            RequestTab::Headers => RequestTab::Scripts,
             RequestTab::Params => RequestTab::Headers,
             RequestTab::Authorization => RequestTab::Params,
+            RequestTab::Settings => RequestTab::Authorization,
+            RequestTab::Tests => RequestTab::Settings,
+            RequestTab::Captures => RequestTab::Tests,
+            RequestTab::Scripts => RequestTab::Captures,
         };
     }
 
-    /// Get mutable reference to the active tab's key-value entries
-    pub fn get_active_tab_mut(&mut self) -> &mut KeyValueEntries {
+    /// Cycle to next response tab
+    pub fn next_response_tab(&mut self) {
+        self.active_response_tab = match self.active_response_tab {
+            ResponseTab::Body => ResponseTab::Headers,
+            ResponseTab::Headers => ResponseTab::Cookies,
+            ResponseTab::Cookies => ResponseTab::Timing,
+            ResponseTab::Timing => ResponseTab::Tests,
+            ResponseTab::Tests => ResponseTab::Body,
+        };
+        self.response_scroll = 0;
+    }
+
+    /// Cycle to previous response tab
+    pub fn prev_response_tab(&mut self) {
+        self.active_response_tab = match self.active_response_tab {
+            ResponseTab::Body => ResponseTab::Tests,
+            ResponseTab::Headers => ResponseTab::Body,
+            ResponseTab::Cookies => ResponseTab::Headers,
+            ResponseTab::Timing => ResponseTab::Cookies,
+            ResponseTab::Tests => ResponseTab::Timing,
+        };
+        self.response_scroll = 0;
+    }
+
+    /// Whether the focused pane is a key-value table
+    pub fn focuses_key_values(&self) -> bool {
+        match self.focused_pane {
+            FocusedPane::RequestDetails => !matches!(
+                self.active_request_tab,
+                RequestTab::Authorization | RequestTab::Scripts
+            ),
+            FocusedPane::Body => self.body_mode.uses_form(),
+            _ => false,
+        }
+    }
+
+    /// Get mutable reference to the key-value entries being edited: the
+    /// form fields when the body pane is focused, else the active tab's.
+    /// `None` on the Auth and Scripts tabs, which have their own fields.
+    pub fn get_active_tab_mut(&mut self) -> Option<&mut KeyValueEntries> {
+        if self.focused_pane == FocusedPane::Body {
+            return Some(&mut self.form);
+        }
+        self.get_active_request_entries()
+    }
+
+    /// The active request tab's key-value entries, `None` on the Auth and
+    /// Scripts tabs
+    pub fn get_active_request_entries(&mut self) -> Option<&mut KeyValueEntries> {
         match self.active_request_tab {
-            RequestTab::Headers => &mut self.headers,
-            RequestTab::Params => &mut self.params,
-            RequestTab::Authorization => &mut self.authorization,
+            RequestTab::Headers => Some(&mut self.headers),
+            RequestTab::Params => Some(&mut self.params),
+            RequestTab::Authorization | RequestTab::Scripts => None,
+            RequestTab::Settings => Some(&mut self.settings),
+            RequestTab::Tests => Some(&mut self.tests),
+            RequestTab::Captures => Some(&mut self.captures),
+        }
+    }
+
+    /// Whether the focused pane is the Scripts tab
+    pub fn focuses_scripts(&self) -> bool {
+        self.is_in_request_details() && self.is_request_tab_active(&RequestTab::Scripts)
+    }
+
+    pub fn script(&self, stage: ScriptStage) -> &str {
+        match stage {
+            ScriptStage::PreRequest => &self.pre_request_script,
+            ScriptStage::PostResponse => &self.post_response_script,
+        }
+    }
+
+    /// Edit the script selected in the Scripts tab in a popup
+    pub fn open_script_editor(&mut self) {
+        let stage = self.script_stage;
+        let lines = self.script(stage).lines().map(str::to_string).collect();
+        self.script_editor = Some(ScriptEditor {
+            stage,
+            textarea: TextArea::new(lines),
+        });
+        self.input_mode = InputMode::Editing;
+    }
+
+    /// Close the script popup, keeping what was typed
+    pub fn close_script_editor(&mut self) {
+        if let Some(editor) = self.script_editor.take() {
+            let text = editor.textarea.lines().join("\n");
+            match editor.stage {
+                ScriptStage::PreRequest => self.pre_request_script = text,
+                ScriptStage::PostResponse => self.post_response_script = text,
+            }
+        }
+        self.input_mode = InputMode::Normal;
+    }
+
+    /// Whether the focused pane is the Auth tab
+    pub fn focuses_auth(&self) -> bool {
+        self.is_in_request_details() && self.is_request_tab_active(&RequestTab::Authorization)
+    }
+
+    pub fn focused_auth_field(&self) -> Option<AuthField> {
+        self.auth.fields().get(self.auth_field).copied()
+    }
+
+    pub fn next_auth_kind(&mut self) {
+        self.auth.kind = self.auth.kind.next();
+        self.auth_field = 0;
+    }
+
+    pub fn next_auth_field(&mut self) {
+        let len = self.auth.fields().len();
+        if len > 0 {
+            self.auth_field = (self.auth_field + 1) % len;
+        }
+    }
+
+    pub fn prev_auth_field(&mut self) {
+        let len = self.auth.fields().len();
+        if len > 0 {
+            self.auth_field = (self.auth_field + len - 1) % len;
+        }
+    }
+
+    /// Edit the focused auth field, or flip it if it is a choice
+    pub fn begin_auth_edit(&mut self) {
+        let Some(field) = self.focused_auth_field() else {
+            return;
+        };
+        if self.auth.value_mut(field).is_some() {
+            self.input_mode = InputMode::Editing;
+        } else {
+            self.auth.toggle(field);
+        }
+    }
+
+    /// The text of the auth field being edited
+    pub fn auth_input_mut(&mut self) -> Option<&mut String> {
+        let field = self.focused_auth_field()?;
+        self.auth.value_mut(field)
+    }
+
+    /// The current auth with environment variables filled in, as it is sent
+    fn resolved_auth(&self) -> Auth {
+        self.environments
+            .resolve(&self.current_request())
+            .map(|request| request.auth)
+            .unwrap_or_else(|_| self.auth.clone())
+    }
+
+    /// What the OAuth 2.0 token cache holds for the current auth
+    pub fn oauth_token_status(&self) -> Option<String> {
+        self.clients.tokens().status(&self.resolved_auth())
+    }
+
+    /// Drop the cached OAuth 2.0 token so the next send fetches a new one
+    pub fn forget_oauth_token(&mut self) {
+        if self.auth.kind == AuthKind::OAuth2 {
+            self.clients.tokens().forget(&self.resolved_auth());
+            self.status_message = Some("OAuth2 token cleared".to_string());
         }
     }
 
@@ -193,68 +736,301 @@ impl App {
         self.body_input = TextArea::new(text.lines().map(String::from).collect());
     }
 
-    /// Validate the body as JSON and update validation_error field
+    /// Validate the body for the selected mode and update validation_error field
     pub fn validate_body(&mut self) {
-        let body_text = self.get_body_text();
+        self.validation_error = match self.body_mode {
+            BodyMode::Json | BodyMode::Xml if self.get_body_text().trim().is_empty() => None,
+            BodyMode::Json => serde_json::from_str::<serde_json::Value>(&self.get_body_text())
+                .err()
+                .map(|e| (e.line(), e.column(), e.to_string())),
+            BodyMode::Xml => roxmltree::Document::parse(&self.get_body_text())
+                .err()
+                .map(|e| {
+                    let pos = e.pos();
+                    (pos.row as usize, pos.col as usize, e.to_string())
+                }),
+            BodyMode::Binary
+                if !self.body_file.trim().is_empty()
+                    && !std::path::Path::new(self.body_file.trim()).is_file() =>
+            {
+                Some((0, 0, "File not found".to_string()))
+            }
+            BodyMode::Text | BodyMode::FormUrlEncoded | BodyMode::Multipart | BodyMode::Binary => {
+                None
+            }
+        };
+    }
 
-        // Empty body is considered valid (no JSON to validate)
-        if body_text.trim().is_empty() {
-            self.validation_error = None;
-            return;
+    /// Get a formatted validation status message for display
+    pub fn get_validation_status(&self) -> String {
+        let label = match self.body_mode {
+            BodyMode::Text => format!("Text: {}", self.text_content_type),
+            mode => mode.label().to_string(),
+        };
+        match &self.validation_error {
+            None if matches!(self.body_mode, BodyMode::Json | BodyMode::Xml)
+                && !self.get_body_text().trim().is_empty() =>
+            {
+                format!("Body ({}) ✓", label)
+            }
+            None => format!("Body ({})", label),
+            Some((0, 0, message)) => format!("Body ({}) - {}", label, message),
+            Some((line, col, _)) => {
+                format!("Body ({}) - Error at line {}, col {}", label, line, col)
+            }
         }
+    }
 
-        match serde_json::from_str::<serde_json::Value>(&body_text) {
-            Ok(_) => {
-                self.validation_error = None;
-            }
+    /// Cycle the body mode, revalidating the body for the new mode
+    pub fn next_body_mode(&mut self) {
+        self.body_mode = self.body_mode.next();
+        self.validate_body();
+    }
+
+    /// Cycle the preset content types used for raw text bodies
+    pub fn next_text_content_type(&mut self) {
+        let idx = TEXT_CONTENT_TYPES
+            .iter()
+            .position(|t| *t == self.text_content_type)
+            .map_or(0, |i| (i + 1) % TEXT_CONTENT_TYPES.len());
+        self.text_content_type = TEXT_CONTENT_TYPES[idx].to_string();
+    }
+
+    /// Snapshot of the request currently in the editor
+    pub fn current_request(&self) -> RequestSpec {
+        RequestSpec {
+            method: self.method.clone(),
+            url: self.url_input.clone(),
+            headers: self.headers.clone(),
+            params: self.params.clone(),
+            auth: self.auth.clone(),
+            settings: self.settings.clone(),
+            tests: self.tests.clone(),
+            captures: self.captures.clone(),
+            pre_request_script: self.pre_request_script.clone(),
+            post_response_script: self.post_response_script.clone(),
+            body_mode: self.body_mode,
+            body: self.get_body_text(),
+            form: self.form.clone(),
+            body_file: self.body_file.clone(),
+            text_content_type: self.text_content_type.clone(),
+        }
+    }
+
+    /// Replace the editor contents with a stored request
+    pub fn load_request(&mut self, request: RequestSpec) {
+        self.method = request.method;
+        self.url_input = request.url;
+        self.headers = request.headers;
+        self.params = request.params;
+        self.auth = request.auth;
+        self.auth_field = 0;
+        self.settings = request.settings;
+        self.tests = request.tests;
+        self.captures = request.captures;
+        self.pre_request_script = request.pre_request_script;
+        self.post_response_script = request.post_response_script;
+        self.body_mode = request.body_mode;
+        self.set_body_text(&request.body);
+        self.form = request.form;
+        self.body_file = request.body_file;
+        self.text_content_type = request.text_content_type;
+        self.validate_body();
+    }
+
+    /// Client settings for a request: config defaults, then the active
+    /// environment's settings, then the request's Settings tab
+    pub fn effective_settings(&self, request: &RequestSpec) -> anyhow::Result<RequestSettings> {
+        let environment = self.environments.active().map(|env| &env.settings);
+        effective_settings(&self.request_defaults, environment, request)
+    }
+
+    /// Whether certificate checks are turned off for the current request
+    pub fn insecure_tls(&self) -> bool {
+        self.effective_settings(&self.current_request())
+            .is_ok_and(|settings| settings.insecure == Some(true))
+    }
+
+    /// The request to send: `request` as changed by the pre-request
+    /// script, with variables substituted. The script's output is added to
+    /// the log of this send, which the first attempt starts afresh.
+    fn outgoing_request(
+        &mut self,
+        mut request: RequestSpec,
+        attempt: u32,
+    ) -> anyhow::Result<RequestSpec> {
+        let log = scripts::pre_request(&mut request, &mut self.environments)?;
+        if attempt == 1 {
+            self.script_log.clear();
+        }
+        self.script_log.extend(log);
+        self.environments.resolve(&request)
+    }
+
+    /// Run the pre-request script of a request from the editor, resolve its
+    /// `{{variables}}` from the active environment and send it in the
+    /// background, replacing any request still running. Its timing is
+    /// added to the history entry `history_id`.
+    pub fn send_request(
+        &mut self,
+        request: RequestSpec,
+        history_id: u64,
+        tx: mpsc::Sender<ResponseMessage>,
+    ) {
+        self.pending_retry = None;
+        self.start_request(request, history_id, 1, tx);
+    }
+
+    /// Send a request again if the post-response script asked to and its
+    /// delay is over
+    pub fn send_due_retry(&mut self, tx: &mpsc::Sender<ResponseMessage>) {
+        if let Some(retry) = self.pending_retry.take_if(|r| r.at <= Instant::now()) {
+            self.start_request(retry.original, retry.history_id, retry.attempt, tx.clone());
+        }
+    }
+
+    fn start_request(
+        &mut self,
+        original: RequestSpec,
+        history_id: u64,
+        attempt: u32,
+        tx: mpsc::Sender<ResponseMessage>,
+    ) {
+        let request = match self.outgoing_request(original.clone(), attempt) {
+            Ok(request) => request,
             Err(e) => {
-                let line = e.line();
-                let column = e.column();
-                let message = e.to_string();
-                self.validation_error = Some((line, column, message));
+                self.response_status = None;
+                self.response = None;
+                self.response_text = Some(format!("Error: {}", e));
+                return;
             }
+        };
+        if let Some(previous) = self.in_flight.take() {
+            previous.handle.abort();
         }
+        self.last_request_id += 1;
+        let id = self.last_request_id;
+        let jar_name = self.cookie_jar_name();
+        let jar = self.cookie_jars.jar(&jar_name);
+        let client = self
+            .effective_settings(&request)
+            .and_then(|settings| self.clients.client(&settings, Some((&jar_name, jar))));
+        let sent = request.clone();
+        let handle = tokio::spawn(async move {
+            let result = async { make_request(&client?, &sent).await }
+                .await
+                .map_err(|e| format!("{:#}", e));
+            let _ = tx.send((id, result)).await;
+        });
+        self.in_flight = Some(InFlight {
+            id,
+            started: Instant::now(),
+            request,
+            original,
+            history_id,
+            attempt,
+            handle,
+        });
+        self.response_status = None;
+        self.response_text = None;
+        self.response = None;
+        self.test_results.clear();
+        self.response_scroll = 0;
     }
 
-    /// Get a formatted validation status message for display
-    pub fn get_validation_status(&self) -> String {
-        match &self.validation_error {
-            None => {
-                if self.get_body_text().trim().is_empty() {
-                    "Body (JSON)".to_string()
-                } else {
-                    "Body (JSON) ✓".to_string()
+    /// Abort the running request, or a retry waiting to be sent
+    pub fn cancel_request(&mut self) {
+        if self.pending_retry.take().is_some() {
+            self.status_message = Some("Retry cancelled".to_string());
+        }
+        if let Some(in_flight) = self.in_flight.take() {
+            in_flight.handle.abort();
+            self.response_text = Some(format!(
+                "Request cancelled after {:.1}s",
+                in_flight.started.elapsed().as_secs_f64()
+            ));
+        }
+    }
+
+    /// Show the result of a request, ignoring results from requests that
+    /// were cancelled or superseded
+    pub fn finish_request(&mut self, (id, result): ResponseMessage) {
+        let Some(in_flight) = self.in_flight.take_if(|r| r.id == id) else {
+            return;
+        };
+        if let Err(e) = self.cookie_jars.save() {
+            self.status_message = Some(format!("{:#}", e));
+        }
+        match result {
+            Ok(resp) => {
+                // The request was added to history when it was sent, unless
+                // it has been trimmed since
+                let entry = self
+                    .history
+                    .iter_mut()
+                    .find(|entry| entry.id == in_flight.history_id);
+                if let Some(entry) = entry {
+                    entry.timing = Some(resp.timing.clone());
+                    if let Err(e) = self.history_store.save(&self.history) {
+                        self.status_message = Some(format!("{:#}", e));
+                    }
                 }
+                let checks =
+                    scripts::after_response(&in_flight.request, &resp, &mut self.environments);
+                self.test_results = checks.tests;
+                self.script_log.extend(checks.log);
+                if let Some(error) = checks.error {
+                    self.status_message = Some(format!("Post-response script: {}", error));
+                }
+                if let Some(delay) = checks.retry {
+                    if in_flight.attempt < scripts::MAX_ATTEMPTS {
+                        self.status_message = Some(format!(
+                            "Retrying in {:.1}s (attempt {} of {}), Esc cancels",
+                            delay.as_secs_f64(),
+                            in_flight.attempt + 1,
+                            scripts::MAX_ATTEMPTS
+                        ));
+                        self.pending_retry = Some(PendingRetry {
+                            at: Instant::now() + delay,
+                            original: in_flight.original,
+                            history_id: in_flight.history_id,
+                            attempt: in_flight.attempt + 1,
+                        });
+                    } else {
+                        self.test_results.push(scripts::retries_exhausted());
+                    }
+                }
+                self.response_status = Some(resp.status);
+                self.response_text = Some(resp.body.clone());
+                self.response = Some(resp);
             }
-            Some((line, col, _)) => {
-                format!("Body (Error at line {}, col {})", line, col)
+            Err(err_msg) => {
+                self.response_status = None;
+                self.response = None;
+                self.response_text = Some(format!("Error: {}", err_msg));
             }
         }
     }
 
-    /// Save current request to history
-    pub fn save_to_history(&mut self) {
-        let entry = RequestHistoryEntry::new(
-            self.method.clone(),
-            self.url_input.clone(),
-            self.headers.clone(),
-            self.params.clone(),
-            self.authorization.clone(),
-            self.get_body_text(),
-        );
+    /// Save current request to history, returning the new entry's id
+    pub fn save_to_history(&mut self) -> u64 {
+        self.last_history_id += 1;
+        let mut entry = RequestHistoryEntry::new(self.current_request());
+        entry.id = self.last_history_id;
         self.history.push(entry);
+        self.history_store.trim(&mut self.history);
         self.history_index = None; // Reset index after saving
+
+        if let Err(e) = self.history_store.save(&self.history) {
+            self.status_message = Some(format!("{:#}", e));
+        }
+        self.last_history_id
     }
 
     /// Load a specific history entry by index
     pub fn load_from_history(&mut self, index: usize) {
         if let Some(entry) = self.history.get(index).cloned() {
-            self.method = entry.method;
-            self.url_input = entry.url;
-            self.headers = entry.headers;
-            self.params = entry.params;
-            self.authorization = entry.auth;
-            self.set_body_text(&entry.body);
+            self.load_request(entry.request);
             self.history_index = Some(index);
         }
     }
@@ -292,31 +1068,552 @@ impl App {
         }
     }
 
-    pub fn next_request_tab(&mut self) {
-        self.active_request_tab = match self.active_request_tab {
-            RequestTab::Params => RequestTab::Headers,
-            RequestTab::Headers => RequestTab::Authorization,
-            RequestTab::Authorization => RequestTab::Params,
+    /// Switch to the next environment (or back to none)
+    pub fn cycle_environment(&mut self) {
+        if self.environments.environments.is_empty() {
+            self.status_message = Some(match self.environments.path() {
+                Some(path) => format!("No environments defined in {}", path.display()),
+                None => "No environments loaded".to_string(),
+            });
+            return;
+        }
+        self.environments.cycle();
+        self.status_message = None;
+    }
+
+    /// Name of the cookie jar used by the active environment
+    pub fn cookie_jar_name(&self) -> String {
+        self.environments.cookie_jar_name()
+    }
+
+    pub fn active_jar(&mut self) -> SharedJar {
+        let name = self.cookie_jar_name();
+        self.cookie_jars.jar(&name)
+    }
+
+    pub fn open_cookies(&mut self) {
+        self.cookie_view = Some(CookieView::default());
+        self.input_mode = InputMode::Editing;
+    }
+
+    pub fn close_cookies(&mut self) {
+        self.cookie_view = None;
+        self.input_mode = InputMode::Normal;
+    }
+
+    pub fn cookie_select_next(&mut self) {
+        let len = self.active_jar().lock().cookies.len();
+        if let Some(view) = self.cookie_view.as_mut() {
+            view.selected = (view.selected + 1).min(len.saturating_sub(1));
+        }
+    }
+
+    pub fn cookie_select_prev(&mut self) {
+        if let Some(view) = self.cookie_view.as_mut() {
+            view.selected = view.selected.saturating_sub(1);
+        }
+    }
+
+    /// Start editing the selected cookie's value, or typing a new cookie
+    pub fn begin_cookie_edit(&mut self, adding: bool) {
+        let jar = self.active_jar();
+        let jar = jar.lock();
+        let Some(view) = self.cookie_view.as_mut() else {
+            return;
+        };
+        view.edit = if adding {
+            let domain = reqwest::Url::parse(&self.url_input)
+                .ok()
+                .and_then(|url| url.host_str().map(str::to_string));
+            Some(CookieEdit {
+                target: None,
+                input: match domain {
+                    Some(domain) => format!("name=value; Domain={}; Path=/", domain),
+                    None => "name=value; Domain=; Path=/".to_string(),
+                },
+            })
+        } else {
+            jar.cookies.get(view.selected).map(|cookie| CookieEdit {
+                target: Some(view.selected),
+                input: cookie.value.clone(),
+            })
         };
     }
 
-    pub fn prev_request_tab(&mut self) {
-        self.active_request_tab = match self.active_request_tab {
-            RequestTab::Params => RequestTab::Authorization,
-            RequestTab::Headers => RequestTab::Params,
-            RequestTab::Authorization => RequestTab::Headers,
+    /// Apply the text typed in the cookie popup
+    pub fn submit_cookie_edit(&mut self) {
+        let jar = self.active_jar();
+        let Some(edit) = self.cookie_view.as_mut().and_then(|view| view.edit.take()) else {
+            return;
         };
+        let mut jar = jar.lock();
+        if let Some(idx) = edit.target {
+            if let Some(cookie) = jar.cookies.get_mut(idx) {
+                cookie.value = edit.input;
+            }
+        } else {
+            let Some(cookie) = parse_set_cookie(&edit.input) else {
+                self.status_message = Some("Expected name=value; Domain=...; Path=...".to_string());
+                return;
+            };
+            let Some(domain) = cookie.domain.clone().filter(|d| !d.is_empty()) else {
+                self.status_message = Some("Cookie needs a Domain".to_string());
+                return;
+            };
+            match reqwest::Url::parse(&format!("https://{}/", domain)) {
+                Ok(url) => jar.store(&url, cookie, unix_now()),
+                Err(e) => {
+                    self.status_message = Some(format!("Invalid domain '{}': {}", domain, e));
+                    return;
+                }
+            }
+        }
+        drop(jar);
+        self.save_cookies();
     }
 
-    pub fn is_request_tab_active(&self, tab: &RequestTab) -> bool {
-        &self.active_request_tab == tab
+    pub fn delete_selected_cookie(&mut self) {
+        let jar = self.active_jar();
+        if let Some(view) = self.cookie_view.as_mut() {
+            let mut jar = jar.lock();
+            if view.selected < jar.cookies.len() {
+                jar.cookies.remove(view.selected);
+                view.selected = view.selected.min(jar.cookies.len().saturating_sub(1));
+            }
+        }
+        self.save_cookies();
     }
 
-    pub fn get_active_request_entries(&mut self) -> &mut KeyValueEntries {
-        match self.active_request_tab {
-            RequestTab::Params => &mut self.params,
-            RequestTab::Headers => &mut self.headers,
-            RequestTab::Authorization => &mut self.authorization,
+    /// Remove every cookie in the active environment's jar
+    pub fn clear_cookies(&mut self) {
+        self.active_jar().lock().cookies.clear();
+        if let Some(view) = self.cookie_view.as_mut() {
+            view.selected = 0;
+        }
+        self.save_cookies();
+    }
+
+    fn save_cookies(&mut self) {
+        if let Err(e) = self.cookie_jars.save() {
+            self.status_message = Some(format!("{:#}", e));
+        }
+    }
+
+    /// Open the popup for pasting a curl command
+    pub fn open_curl_import(&mut self) {
+        self.curl_import = Some(TextArea::default());
+        self.input_mode = InputMode::Editing;
+    }
+
+    pub fn close_curl_import(&mut self) {
+        self.curl_import = None;
+        self.input_mode = InputMode::Normal;
+    }
+
+    /// Replace the editor contents with the pasted curl command, keeping the
+    /// popup open on parse errors
+    pub fn import_curl(&mut self) {
+        let Some(textarea) = &self.curl_import else {
+            return;
+        };
+        match crate::curl::parse_curl(&textarea.lines().join("\n")) {
+            Ok(request) => {
+                self.load_request(request);
+                self.history_index = None;
+                self.close_curl_import();
+                self.status_message = Some("Imported curl command".to_string());
+            }
+            Err(e) => self.status_message = Some(format!("{:#}", e)),
+        }
+    }
+
+    /// Handle text pasted into the terminal
+    pub fn paste(&mut self, text: &str) {
+        if let Some(editor) = self.script_editor.as_mut() {
+            editor.textarea.insert_str(text);
+        } else if let Some(textarea) = self.curl_import.as_mut() {
+            textarea.insert_str(text);
+        } else if self.input_mode == InputMode::Editing {
+            match self.focused_pane {
+                FocusedPane::Url => self.url_input.push_str(text.trim()),
+                FocusedPane::Body if self.body_mode == BodyMode::Binary => {
+                    self.body_file.push_str(text.trim());
+                    self.validate_body();
+                }
+                FocusedPane::Body if !self.body_mode.uses_form() => {
+                    self.body_input.insert_str(text);
+                    self.validate_body();
+                }
+                _ => {}
+            }
+        } else if text.trim_start().starts_with("curl ") {
+            // Pasting a curl command anywhere opens the import popup with it
+            self.open_curl_import();
+            self.paste(text);
+        }
+    }
+
+    /// Open the export popup for the current request, with variables from
+    /// the active environment substituted where possible
+    pub fn open_export(&mut self, format: SnippetFormat) {
+        let request = self.current_request();
+        let resolved = match self.environments.resolve(&request) {
+            Ok(resolved) => resolved,
+            Err(e) => {
+                self.status_message = Some(format!("{:#} (exported unresolved)", e));
+                request
+            }
+        };
+        let prepared = match crate::network::prepare_request(&resolved) {
+            Ok(prepared) => prepared,
+            Err(e) => {
+                self.status_message = Some(format!("{:#}", e));
+                return;
+            }
+        };
+        let text = render_snippet(&prepared, format);
+        self.export = Some(ExportView {
+            format,
+            text,
+            scroll: 0,
+        });
+        self.input_mode = InputMode::Editing;
+    }
+
+    pub fn close_export(&mut self) {
+        self.export = None;
+        self.input_mode = InputMode::Normal;
+    }
+
+    /// Write the snippet shown in the export popup to the current directory
+    pub fn write_export(&mut self) {
+        let Some(export) = &self.export else {
+            return;
+        };
+        let file_name = export.format.file_name();
+        self.status_message = Some(match std::fs::write(file_name, &export.text) {
+            Ok(()) => format!("Wrote {}", file_name),
+            Err(e) => format!("Failed to write {}: {}", file_name, e),
+        });
+    }
+
+    /// Decode the JWTs in the response's headers and body
+    pub fn open_jwt_view(&mut self) {
+        let tokens = self
+            .response
+            .as_ref()
+            .map(jwt::find_in_response)
+            .unwrap_or_default();
+        if tokens.is_empty() {
+            self.status_message = Some("No JWT found in the response".to_string());
+            return;
+        }
+        self.jwt_view = Some(JwtView {
+            tokens,
+            selected: 0,
+            scroll: 0,
+        });
+        self.input_mode = InputMode::Editing;
+    }
+
+    pub fn close_jwt_view(&mut self) {
+        self.jwt_view = None;
+        self.input_mode = InputMode::Normal;
+    }
+
+    /// Show the next (or previous) token found in the response
+    pub fn cycle_jwt(&mut self, forward: bool) {
+        if let Some(view) = self.jwt_view.as_mut() {
+            let len = view.tokens.len();
+            view.selected = if forward {
+                (view.selected + 1) % len
+            } else {
+                (view.selected + len - 1) % len
+            };
+            view.scroll = 0;
+        }
+    }
+
+    /// Rows of the collections sidebar
+    pub fn collection_rows(&self) -> Vec<TreeRow> {
+        self.collections.root.rows()
+    }
+
+    fn selected_collection_row(&self) -> Option<TreeRow> {
+        self.collection_rows()
+            .into_iter()
+            .nth(self.collection_selected)
+    }
+
+    /// Folder new items go into: the selected folder, or the parent of the
+    /// selected request
+    fn target_folder_path(&self) -> Vec<usize> {
+        match self.selected_collection_row() {
+            Some(row) if row.method.is_none() => row.path,
+            Some(mut row) => {
+                row.path.pop();
+                row.path
+            }
+            None => Vec::new(),
+        }
+    }
+
+    fn select_collection_path(&mut self, path: &[usize]) {
+        if let Some(idx) = self.collection_rows().iter().position(|r| r.path == path) {
+            self.collection_selected = idx;
+        }
+    }
+
+    pub fn collection_select_prev(&mut self) {
+        self.collection_selected = self.collection_selected.saturating_sub(1);
+    }
+
+    pub fn collection_select_next(&mut self) {
+        if self.collection_selected + 1 < self.collection_rows().len() {
+            self.collection_selected += 1;
+        }
+    }
+
+    /// Load the selected saved request into the editor
+    pub fn open_selected_request(&mut self) {
+        let Some(row) = self.selected_collection_row() else {
+            return;
+        };
+        if let Some(CollectionItem::Request(saved)) = self.collections.root.get(&row.path) {
+            let request = saved.request.clone();
+            self.load_request(request);
+            self.history_index = None;
+            self.status_message = Some(format!("Opened '{}'", row.name));
+        }
+    }
+
+    /// Open the name prompt for a collection action
+    pub fn begin_collection_prompt(&mut self, action: CollectionAction) {
+        let input = match action {
+            CollectionAction::SaveRequest | CollectionAction::Rename => self
+                .selected_collection_row()
+                .filter(|row| action == CollectionAction::Rename || row.method.is_some())
+                .map(|row| row.name)
+                .unwrap_or_default(),
+            CollectionAction::NewFolder | CollectionAction::ConfirmDelete => String::new(),
+        };
+        if matches!(
+            action,
+            CollectionAction::Rename | CollectionAction::ConfirmDelete
+        ) && self.selected_collection_row().is_none()
+        {
+            return;
+        }
+        self.collection_prompt = Some(CollectionPrompt { action, input });
+        self.focused_pane = FocusedPane::Collections;
+        self.input_mode = InputMode::Editing;
+    }
+
+    pub fn cancel_collection_prompt(&mut self) {
+        self.collection_prompt = None;
+        self.input_mode = InputMode::Normal;
+    }
+
+    /// Apply the pending collection prompt and persist the collection
+    pub fn submit_collection_prompt(&mut self) {
+        let Some(prompt) = self.collection_prompt.take() else {
+            return;
+        };
+        self.input_mode = InputMode::Normal;
+
+        let name = prompt.input.trim().to_string();
+        if name.is_empty() && prompt.action != CollectionAction::ConfirmDelete {
+            return;
         }
+
+        let target = self.target_folder_path();
+        let selected = self.selected_collection_row().map(|row| row.path);
+        let current = self.current_request();
+        let root = &mut self.collections.root;
+        let result = match prompt.action {
+            CollectionAction::SaveRequest => {
+                let saved = SavedRequest {
+                    name,
+                    request: current,
+                };
+                root.save_request(&target, saved).map(Some)
+            }
+            CollectionAction::NewFolder => root.add_folder(&target, &name).map(Some),
+            CollectionAction::Rename => match &selected {
+                Some(path) => root.rename(path, &name).map(|_| Some(path.clone())),
+                None => Ok(None),
+            },
+            CollectionAction::ConfirmDelete => match &selected {
+                Some(path) => root.remove(path).map(|_| None),
+                None => Ok(None),
+            },
+        };
+
+        match result {
+            Ok(new_selection) => {
+                if let Some(path) = new_selection {
+                    self.select_collection_path(&path);
+                }
+                self.clamp_collection_selection();
+                self.persist_collections();
+            }
+            Err(e) => self.status_message = Some(format!("{:#}", e)),
+        }
+    }
+
+    /// Insert a copy of the selected item right below it
+    pub fn duplicate_selected(&mut self) {
+        let Some(row) = self.selected_collection_row() else {
+            return;
+        };
+        match self.collections.root.duplicate(&row.path) {
+            Ok(path) => {
+                self.select_collection_path(&path);
+                self.persist_collections();
+            }
+            Err(e) => self.status_message = Some(format!("{:#}", e)),
+        }
+    }
+
+    fn clamp_collection_selection(&mut self) {
+        let len = self.collection_rows().len();
+        if self.collection_selected >= len {
+            self.collection_selected = len.saturating_sub(1);
+        }
+    }
+
+    fn persist_collections(&mut self) {
+        if let Err(e) = self.collections.save() {
+            self.status_message = Some(format!("{:#}", e));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_http_method_parsing_and_serde() {
+        assert_eq!(
+            "options".parse::<HttpMethod>().unwrap(),
+            HttpMethod::OPTIONS
+        );
+        assert_eq!(
+            "PROPFIND".parse::<HttpMethod>().unwrap(),
+            HttpMethod::Custom("PROPFIND".to_string())
+        );
+        assert!("BAD METHOD".parse::<HttpMethod>().is_err());
+        assert!("".parse::<HttpMethod>().is_err());
+
+        // Stored as a plain string, so older files still load
+        let json = serde_json::to_string(&HttpMethod::Custom("PURGE".to_string())).unwrap();
+        assert_eq!(json, "\"PURGE\"");
+        let method: HttpMethod = serde_json::from_str("\"GET\"").unwrap();
+        assert_eq!(method, HttpMethod::GET);
+        assert!(serde_json::from_str::<HttpMethod>("\"a b\"").is_err());
+
+        let purge = HttpMethod::Custom("PURGE".to_string())
+            .to_reqwest()
+            .unwrap();
+        assert_eq!(purge.as_str(), "PURGE");
+    }
+
+    #[tokio::test]
+    async fn test_stale_and_cancelled_responses_are_discarded() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut app = App::with_paths(&AppPaths::in_dir(dir.path()));
+        let (tx, _rx) = mpsc::channel(4);
+        let request = RequestSpec {
+            url: "http://127.0.0.1:9/".to_string(),
+            ..RequestSpec::default()
+        };
+        app.send_request(request.clone(), 0, tx.clone());
+        app.send_request(request.clone(), 0, tx.clone());
+        assert_eq!(app.in_flight.as_ref().map(|r| r.id), Some(2));
+
+        // The first request was superseded, so its result is dropped
+        app.finish_request((1, Err("first".to_string())));
+        assert!(app.in_flight.is_some());
+        assert_eq!(app.response_text, None);
+
+        app.finish_request((2, Err("second".to_string())));
+        assert!(app.in_flight.is_none());
+        assert_eq!(app.response_text.as_deref(), Some("Error: second"));
+
+        app.send_request(request, 0, tx);
+        app.cancel_request();
+        assert!(app.in_flight.is_none());
+        app.finish_request((3, Err("late".to_string())));
+        assert!(app.response_text.unwrap().starts_with("Request cancelled"));
+    }
+
+    #[tokio::test]
+    async fn test_retry_reruns_pre_request_script() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut app = App::with_paths(&AppPaths::in_dir(dir.path()));
+        app.environments = EnvironmentStore::with_active(dir.path(), &[("n", "1")]);
+        let (tx, _rx) = mpsc::channel(4);
+
+        let request = RequestSpec {
+            url: "http://127.0.0.1:9/{{n}}".to_string(),
+            pre_request_script: r#"print("pre " + get_var("n"));"#.to_string(),
+            ..RequestSpec::default()
+        };
+        app.send_request(request.clone(), 0, tx.clone());
+        assert_eq!(
+            app.in_flight.as_ref().unwrap().request.url,
+            "http://127.0.0.1:9/1"
+        );
+
+        // The post-response script set a variable and asked for a retry
+        app.in_flight.take().unwrap().handle.abort();
+        app.environments
+            .set_variables(&[("n".to_string(), "2".to_string())])
+            .unwrap();
+        app.pending_retry = Some(PendingRetry {
+            at: Instant::now(),
+            original: request,
+            history_id: 0,
+            attempt: 2,
+        });
+        app.send_due_retry(&tx);
+        let in_flight = app.in_flight.as_ref().unwrap();
+        assert_eq!(in_flight.request.url, "http://127.0.0.1:9/2");
+        assert_eq!(in_flight.attempt, 2);
+        assert_eq!(app.script_log, ["pre 1", "pre 2"]);
+    }
+
+    #[tokio::test]
+    async fn test_timing_is_stored_in_the_sent_history_entry() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut app = App::with_paths(&AppPaths::in_dir(dir.path()));
+        let (tx, _rx) = mpsc::channel(4);
+
+        app.url_input = "http://127.0.0.1:9/sent".to_string();
+        let history_id = app.save_to_history();
+        let request = app.current_request();
+        app.send_request(request, history_id, tx);
+        // A newer entry is added while the request is running
+        app.url_input = "http://127.0.0.1:9/newer".to_string();
+        app.save_to_history();
+
+        let timing = Timing {
+            total: std::time::Duration::from_millis(5),
+            ..Timing::default()
+        };
+        let response = ApiResponse {
+            status: 200,
+            version: "HTTP/1.1".to_string(),
+            url: "http://127.0.0.1:9/sent".to_string(),
+            headers: Vec::new(),
+            body: String::new(),
+            raw_body: Vec::new(),
+            size: 0,
+            timing: timing.clone(),
+        };
+        app.finish_request((app.last_request_id, Ok(response)));
+        assert_eq!(app.history[0].timing, Some(timing));
+        assert_eq!(app.history[1].timing, None);
     }
 }
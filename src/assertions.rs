@@ -0,0 +1,342 @@
+use anyhow::{anyhow, bail, Result};
+use regex::Regex;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::key_value::KeyValueEntries;
+use crate::network::ApiResponse;
+
+/// Shown under the Tests tab
+pub const HINT: &str = "Keys: status, header <name>, $.json.path, body, size (bytes), time (ms). \
+     Values: 200, == x, != x, < n, <= n, > n, >= n, contains x, matches <regex>, exists, !exists";
+
+/// What part of the response an assertion looks at
+#[derive(Debug, Clone, PartialEq)]
+enum Target {
+    Status,
+    Header(String),
+    JsonPath(String),
+    Body,
+    Size,
+    /// Total response time in milliseconds
+    Time,
+}
+
+#[derive(Debug, Clone)]
+enum Check {
+    Exists,
+    Missing,
+    Equals(String),
+    NotEquals(String),
+    Compare(Ordering, f64),
+    Contains(String),
+    Matches(Regex),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Ordering {
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+impl Ordering {
+    fn holds(self, actual: f64, expected: f64) -> bool {
+        match self {
+            Ordering::Less => actual < expected,
+            Ordering::LessOrEqual => actual <= expected,
+            Ordering::Greater => actual > expected,
+            Ordering::GreaterOrEqual => actual >= expected,
+        }
+    }
+}
+
+/// One row of the Tests tab, parsed
+#[derive(Debug, Clone)]
+pub struct Assertion {
+    target: Target,
+    check: Check,
+}
+
+impl Assertion {
+    /// Parse a Tests row: the key names what to check and the value how,
+    /// e.g. `$.user.id` and `== 7`. A bare value means `==`.
+    pub fn parse(key: &str, value: &str) -> Result<Self> {
+        let key = key.trim();
+        let target = match key.split_once(char::is_whitespace) {
+            _ if key.starts_with('$') => {
+                serde_json_path::JsonPath::parse(key)
+                    .map_err(|e| anyhow!("invalid JSONPath: {}", e))?;
+                Target::JsonPath(key.to_string())
+            }
+            Some((word, name)) if word.eq_ignore_ascii_case("header") => {
+                Target::Header(name.trim().to_string())
+            }
+            None if key.eq_ignore_ascii_case("status") => Target::Status,
+            None if key.eq_ignore_ascii_case("body") => Target::Body,
+            None if key.eq_ignore_ascii_case("size") => Target::Size,
+            None if key.eq_ignore_ascii_case("time") => Target::Time,
+            _ => bail!(
+                "unknown target '{}' (expected status, header <name>, $.path, body, size or time)",
+                key
+            ),
+        };
+
+        let value = value.trim();
+        let number = |text: &str| parse_number(text.trim(), &target);
+        let check = if value.is_empty() || value.eq_ignore_ascii_case("exists") {
+            Check::Exists
+        } else if value.eq_ignore_ascii_case("!exists") {
+            Check::Missing
+        } else if let Some(rest) = value.strip_prefix("==") {
+            Check::Equals(rest.trim().to_string())
+        } else if let Some(rest) = value.strip_prefix("!=") {
+            Check::NotEquals(rest.trim().to_string())
+        } else if let Some(rest) = value.strip_prefix("<=") {
+            Check::Compare(Ordering::LessOrEqual, number(rest)?)
+        } else if let Some(rest) = value.strip_prefix(">=") {
+            Check::Compare(Ordering::GreaterOrEqual, number(rest)?)
+        } else if let Some(rest) = value.strip_prefix('<') {
+            Check::Compare(Ordering::Less, number(rest)?)
+        } else if let Some(rest) = value.strip_prefix('>') {
+            Check::Compare(Ordering::Greater, number(rest)?)
+        } else if let Some(rest) = value.strip_prefix("contains ") {
+            Check::Contains(rest.trim().to_string())
+        } else if let Some(rest) = value.strip_prefix("matches ") {
+            Check::Matches(Regex::new(rest.trim()).map_err(|e| anyhow!("invalid regex: {}", e))?)
+        } else {
+            Check::Equals(value.to_string())
+        };
+        Ok(Self { target, check })
+    }
+
+    /// Check the response, returning what was found as the message
+    pub fn evaluate(&self, resp: &ApiResponse) -> (bool, String) {
+        let actual = match self.actual(resp) {
+            Ok(actual) => actual,
+            Err(e) => return (false, format!("{:#}", e)),
+        };
+        let passed = match &self.check {
+            Check::Exists => !actual.is_empty(),
+            Check::Missing => actual.is_empty(),
+            Check::Equals(expected) => actual.iter().any(|v| equals(v, expected)),
+            Check::NotEquals(expected) => {
+                !actual.is_empty() && actual.iter().all(|v| !equals(v, expected))
+            }
+            Check::Compare(ordering, expected) => {
+                !actual.is_empty()
+                    && actual
+                        .iter()
+                        .all(|v| as_number(v).is_some_and(|n| ordering.holds(n, *expected)))
+            }
+            Check::Contains(expected) => actual.iter().any(|v| contains(v, expected)),
+            Check::Matches(regex) => actual.iter().any(|v| regex.is_match(&as_text(v))),
+        };
+        let found = match actual.as_slice() {
+            [] => "not found".to_string(),
+            [one] => format!("got {}", truncate(&one.to_string())),
+            many => format!("got {}", truncate(&Value::Array(many.to_vec()).to_string())),
+        };
+        (passed, found)
+    }
+
+    /// The values the assertion checks; several for repeated headers or
+    /// JSONPath queries matching many nodes
+    fn actual(&self, resp: &ApiResponse) -> Result<Vec<Value>> {
+        Ok(match &self.target {
+            Target::Status => vec![Value::from(resp.status)],
+            Target::Header(name) => resp
+                .header_values(name)
+                .map(|v| Value::String(v.to_string()))
+                .collect(),
+            Target::JsonPath(path) => resp.json_path(path)?,
+            Target::Body => vec![Value::String(
+                String::from_utf8_lossy(&resp.raw_body).into_owned(),
+            )],
+            Target::Size => vec![Value::from(resp.size)],
+            Target::Time => vec![Value::from(resp.timing.total.as_millis() as u64)],
+        })
+    }
+}
+
+/// The outcome of one assertion
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TestResult {
+    /// The row as typed, e.g. `status 200`
+    pub name: String,
+    pub passed: bool,
+    /// What was found, or why the row could not be checked
+    pub message: String,
+}
+
+/// Check every enabled row of a Tests table against a response
+pub fn run(tests: &KeyValueEntries, resp: &ApiResponse) -> Vec<TestResult> {
+    tests
+        .entries
+        .iter()
+        .filter(|e| e.enabled && !e.key.trim().is_empty())
+        .map(|entry| {
+            let name = format!("{} {}", entry.key.trim(), entry.value.trim())
+                .trim_end()
+                .to_string();
+            let (passed, message) = match Assertion::parse(&entry.key, &entry.value) {
+                Ok(assertion) => assertion.evaluate(resp),
+                Err(e) => (false, format!("{:#}", e)),
+            };
+            TestResult {
+                name,
+                passed,
+                message,
+            }
+        })
+        .collect()
+}
+
+/// `500`, or for times also `500ms` and `1.5s`
+fn parse_number(text: &str, target: &Target) -> Result<f64> {
+    let (number, scale) = match (target, text) {
+        (Target::Time, t) if t.ends_with("ms") => (&t[..t.len() - 2], 1.0),
+        (Target::Time, t) if t.ends_with('s') => (&t[..t.len() - 1], 1000.0),
+        (_, t) => (t, 1.0),
+    };
+    number
+        .trim()
+        .parse::<f64>()
+        .map(|n| n * scale)
+        .map_err(|_| anyhow!("'{}' is not a number", text))
+}
+
+/// Compare with the expected text read as JSON (`7`, `true`, `"a"`) or,
+/// failing that, as a plain string
+fn equals(actual: &Value, expected: &str) -> bool {
+    match serde_json::from_str::<Value>(expected) {
+        Ok(Value::Number(n)) => {
+            as_number(actual).is_some_and(|a| n.as_f64().is_some_and(|n| a == n))
+        }
+        Ok(expected_value) if *actual == expected_value => true,
+        _ => actual.as_str() == Some(expected),
+    }
+}
+
+/// Substring of a string, element of an array or key of an object
+fn contains(actual: &Value, expected: &str) -> bool {
+    match actual {
+        Value::String(s) => s.contains(expected),
+        Value::Array(items) => items.iter().any(|item| equals(item, expected)),
+        Value::Object(map) => map.contains_key(expected),
+        other => other.to_string().contains(expected),
+    }
+}
+
+fn as_number(value: &Value) -> Option<f64> {
+    match value {
+        Value::Number(n) => n.as_f64(),
+        Value::String(s) => s.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+/// Strings without their quotes, anything else as JSON
+fn as_text(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+fn truncate(text: &str) -> String {
+    const MAX: usize = 80;
+    match text.char_indices().nth(MAX) {
+        Some((idx, _)) => format!("{}…", &text[..idx]),
+        None => text.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn response() -> ApiResponse {
+        let raw = br#"{"user": {"id": 7, "name": "Ada", "roles": ["admin", "dev"]}}"#.to_vec();
+        let mut resp = ApiResponse {
+            status: 201,
+            version: "HTTP/1.1".to_string(),
+            url: "http://localhost/users".to_string(),
+            headers: vec![(
+                "Content-Type".to_string(),
+                "application/json; charset=utf-8".to_string(),
+            )],
+            body: String::from_utf8_lossy(&raw).into_owned(),
+            size: raw.len(),
+            raw_body: raw,
+            timing: Default::default(),
+        };
+        resp.timing.total = Duration::from_millis(120);
+        resp
+    }
+
+    fn check(key: &str, value: &str) -> bool {
+        let mut tests = KeyValueEntries::new();
+        tests.add_entry(key.to_string(), value.to_string());
+        let results = run(&tests, &response());
+        results[0].passed
+    }
+
+    #[test]
+    fn test_passing_assertions() {
+        assert!(check("status", "201"));
+        assert!(check("status", ">= 200"));
+        assert!(check("header content-type", "matches ^application/json"));
+        assert!(check("header Content-Type", "exists"));
+        assert!(check("header X-Missing", "!exists"));
+        assert!(check("$.user.id", "== 7"));
+        assert!(check("$.user.name", "Ada"));
+        assert!(check("$.user.name", "== \"Ada\""));
+        assert!(check("$.user.roles", "contains admin"));
+        assert!(check("$.user.roles[*]", "matches ^d"));
+        assert!(check("$.user", "contains roles"));
+        assert!(check("body", "contains \"Ada\""));
+        assert!(check("size", "< 1024"));
+        assert!(check("time", "< 0.5s"));
+        assert!(check("time", "<= 120ms"));
+    }
+
+    #[test]
+    fn test_failing_assertions_report_what_was_found() {
+        assert!(!check("status", "200"));
+        assert!(!check("$.user.id", "!= 7"));
+        assert!(!check("$.user.missing", "exists"));
+        assert!(!check("time", "< 100"));
+
+        let mut tests = KeyValueEntries::new();
+        tests.add_entry("status".to_string(), "== 200".to_string());
+        tests.add_entry("$.user.email".to_string(), "== a@b.c".to_string());
+        tests.add_entry("cookie".to_string(), "x".to_string());
+        tests.add_entry("$.user.id".to_string(), "matches (".to_string());
+        tests.add_entry("ignored".to_string(), "".to_string());
+        tests.toggle_enabled(4);
+        let results = run(&tests, &response());
+        assert_eq!(results.len(), 4);
+        assert_eq!(results[0].name, "status == 200");
+        assert_eq!(results[0].message, "got 201");
+        assert_eq!(results[1].message, "not found");
+        assert!(results[2].message.starts_with("unknown target 'cookie'"));
+        assert!(results[3].message.starts_with("invalid regex"));
+        assert!(results.iter().all(|r| !r.passed));
+    }
+
+    #[test]
+    fn test_json_path_on_non_json_body() {
+        let mut resp = response();
+        resp.raw_body = b"<html/>".to_vec();
+        let assertion = Assertion::parse("$.id", "1").unwrap();
+        let (passed, message) = assertion.evaluate(&resp);
+        assert!(!passed);
+        assert_eq!(
+            message,
+            "Body is not JSON: expected value at line 1 column 1"
+        );
+    }
+}
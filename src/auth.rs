@@ -0,0 +1,753 @@
+use anyhow::{bail, Result};
+use base64::{engine::general_purpose, Engine as _};
+use md5::Md5;
+use serde::{Deserialize, Deserializer, Serialize};
+use sha2::{Digest as _, Sha256};
+
+use crate::jwt::JwtConfig;
+use crate::key_value::KeyValueEntries;
+use crate::oauth::{OAuth2Config, OAuthGrant, DEFAULT_REDIRECT_URI};
+use crate::signing::{self, HmacConfig};
+use crate::sigv4::AwsSigV4Config;
+
+/// How a request authenticates
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuthKind {
+    #[default]
+    None,
+    Basic,
+    Bearer,
+    /// A key sent in a header or query parameter
+    ApiKey,
+    /// HTTP Digest: the first response's challenge is answered with a
+    /// second request
+    Digest,
+    /// A bearer token fetched, cached and refreshed from a token endpoint
+    #[serde(rename = "oauth2")]
+    OAuth2,
+    /// AWS Signature Version 4, computed over the request as it is sent
+    #[serde(rename = "aws_sigv4")]
+    AwsSigV4,
+    /// An HMAC over a canonical string, e.g. for partner webhooks
+    Hmac,
+    /// A JWT signed from a claims object, sent as a bearer token
+    Jwt,
+}
+
+impl AuthKind {
+    pub const ALL: [AuthKind; 9] = [
+        AuthKind::None,
+        AuthKind::Basic,
+        AuthKind::Bearer,
+        AuthKind::ApiKey,
+        AuthKind::Digest,
+        AuthKind::OAuth2,
+        AuthKind::AwsSigV4,
+        AuthKind::Hmac,
+        AuthKind::Jwt,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            AuthKind::None => "None",
+            AuthKind::Basic => "Basic",
+            AuthKind::Bearer => "Bearer token",
+            AuthKind::ApiKey => "API key",
+            AuthKind::Digest => "Digest",
+            AuthKind::OAuth2 => "OAuth 2.0",
+            AuthKind::AwsSigV4 => "AWS Signature v4",
+            AuthKind::Hmac => "HMAC signature",
+            AuthKind::Jwt => "JWT",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        let idx = Self::ALL.iter().position(|k| *k == self).unwrap_or(0);
+        Self::ALL[(idx + 1) % Self::ALL.len()]
+    }
+}
+
+/// Where an API key is sent
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ApiKeyLocation {
+    #[default]
+    Header,
+    Query,
+}
+
+/// An editable field of the Auth tab
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuthField {
+    Username,
+    Password,
+    Token,
+    KeyName,
+    KeyValue,
+    KeyLocation,
+    Grant,
+    TokenUrl,
+    AuthUrl,
+    ClientId,
+    ClientSecret,
+    Scope,
+    RefreshToken,
+    RedirectUri,
+    AccessKey,
+    SecretKey,
+    SessionToken,
+    Region,
+    Service,
+    Algorithm,
+    Secret,
+    Template,
+    SignatureHeader,
+    SignaturePrefix,
+    Encoding,
+    TimestampHeader,
+    JwtAlgorithm,
+    JwtSecret,
+    KeyFile,
+    KeyId,
+    Claims,
+}
+
+impl AuthField {
+    pub fn label(self) -> &'static str {
+        match self {
+            AuthField::Username => "Username",
+            AuthField::Password => "Password",
+            AuthField::Token => "Token",
+            AuthField::KeyName => "Key name",
+            AuthField::KeyValue => "Key value",
+            AuthField::KeyLocation => "Send in",
+            AuthField::Grant => "Grant type",
+            AuthField::TokenUrl => "Token URL",
+            AuthField::AuthUrl => "Auth URL",
+            AuthField::ClientId => "Client ID",
+            AuthField::ClientSecret => "Client secret",
+            AuthField::Scope => "Scope",
+            AuthField::RefreshToken => "Refresh token",
+            AuthField::RedirectUri => "Redirect URI",
+            AuthField::AccessKey => "Access key",
+            AuthField::SecretKey => "Secret key",
+            AuthField::SessionToken => "Session token",
+            AuthField::Region => "Region",
+            AuthField::Service => "Service",
+            AuthField::Algorithm => "Algorithm",
+            AuthField::Secret => "Secret",
+            AuthField::Template => "Signed string",
+            AuthField::SignatureHeader => "Header",
+            AuthField::SignaturePrefix => "Value prefix",
+            AuthField::Encoding => "Encoding",
+            AuthField::TimestampHeader => "Timestamp header",
+            AuthField::JwtAlgorithm => "Algorithm",
+            AuthField::JwtSecret => "Secret",
+            AuthField::KeyFile => "Private key file",
+            AuthField::KeyId => "Key ID (kid)",
+            AuthField::Claims => "Claims (JSON)",
+        }
+    }
+
+    /// Masked in the UI unless being edited
+    pub fn is_secret(self) -> bool {
+        matches!(
+            self,
+            AuthField::Password
+                | AuthField::ClientSecret
+                | AuthField::RefreshToken
+                | AuthField::SecretKey
+                | AuthField::SessionToken
+                | AuthField::Secret
+                | AuthField::JwtSecret
+        )
+    }
+}
+
+/// The authorization settings of a request. Fields of the other kinds are
+/// kept, like the body of other body modes, so switching back loses nothing.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Auth {
+    pub kind: AuthKind,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub username: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub password: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub token: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub key_name: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub key_value: String,
+    pub key_location: ApiKeyLocation,
+    #[serde(skip_serializing_if = "OAuth2Config::is_default")]
+    pub oauth2: OAuth2Config,
+    #[serde(skip_serializing_if = "AwsSigV4Config::is_default")]
+    pub aws: AwsSigV4Config,
+    #[serde(skip_serializing_if = "HmacConfig::is_default")]
+    pub hmac: HmacConfig,
+    #[serde(skip_serializing_if = "JwtConfig::is_default")]
+    pub jwt: JwtConfig,
+}
+
+impl Auth {
+    /// Fields shown for the current kind
+    pub fn fields(&self) -> &'static [AuthField] {
+        match self.kind {
+            AuthKind::None => &[],
+            AuthKind::Basic | AuthKind::Digest => &[AuthField::Username, AuthField::Password],
+            AuthKind::Bearer => &[AuthField::Token],
+            AuthKind::ApiKey => &[
+                AuthField::KeyName,
+                AuthField::KeyValue,
+                AuthField::KeyLocation,
+            ],
+            AuthKind::OAuth2 => match self.oauth2.grant {
+                OAuthGrant::ClientCredentials => &[
+                    AuthField::Grant,
+                    AuthField::TokenUrl,
+                    AuthField::ClientId,
+                    AuthField::ClientSecret,
+                    AuthField::Scope,
+                ],
+                OAuthGrant::Password => &[
+                    AuthField::Grant,
+                    AuthField::TokenUrl,
+                    AuthField::ClientId,
+                    AuthField::ClientSecret,
+                    AuthField::Scope,
+                    AuthField::Username,
+                    AuthField::Password,
+                ],
+                OAuthGrant::RefreshToken => &[
+                    AuthField::Grant,
+                    AuthField::TokenUrl,
+                    AuthField::ClientId,
+                    AuthField::ClientSecret,
+                    AuthField::Scope,
+                    AuthField::RefreshToken,
+                ],
+                OAuthGrant::AuthorizationCode => &[
+                    AuthField::Grant,
+                    AuthField::AuthUrl,
+                    AuthField::TokenUrl,
+                    AuthField::ClientId,
+                    AuthField::ClientSecret,
+                    AuthField::Scope,
+                    AuthField::RedirectUri,
+                ],
+            },
+            AuthKind::AwsSigV4 => &[
+                AuthField::AccessKey,
+                AuthField::SecretKey,
+                AuthField::SessionToken,
+                AuthField::Region,
+                AuthField::Service,
+            ],
+            AuthKind::Hmac => &[
+                AuthField::Algorithm,
+                AuthField::Secret,
+                AuthField::Template,
+                AuthField::SignatureHeader,
+                AuthField::SignaturePrefix,
+                AuthField::Encoding,
+                AuthField::TimestampHeader,
+            ],
+            AuthKind::Jwt if self.jwt.algorithm.uses_key_file() => &[
+                AuthField::JwtAlgorithm,
+                AuthField::KeyFile,
+                AuthField::KeyId,
+                AuthField::Claims,
+            ],
+            AuthKind::Jwt => &[
+                AuthField::JwtAlgorithm,
+                AuthField::JwtSecret,
+                AuthField::KeyId,
+                AuthField::Claims,
+            ],
+        }
+    }
+
+    pub fn value(&self, field: AuthField) -> &str {
+        match field {
+            AuthField::Username => &self.username,
+            AuthField::Password => &self.password,
+            AuthField::Token => &self.token,
+            AuthField::KeyName => &self.key_name,
+            AuthField::KeyValue => &self.key_value,
+            AuthField::KeyLocation => match self.key_location {
+                ApiKeyLocation::Header => "header",
+                ApiKeyLocation::Query => "query parameter",
+            },
+            AuthField::Grant => self.oauth2.grant.label(),
+            AuthField::TokenUrl => &self.oauth2.token_url,
+            AuthField::AuthUrl => &self.oauth2.auth_url,
+            AuthField::ClientId => &self.oauth2.client_id,
+            AuthField::ClientSecret => &self.oauth2.client_secret,
+            AuthField::Scope => &self.oauth2.scope,
+            AuthField::RefreshToken => &self.oauth2.refresh_token,
+            AuthField::RedirectUri if self.oauth2.redirect_uri.is_empty() => DEFAULT_REDIRECT_URI,
+            AuthField::RedirectUri => &self.oauth2.redirect_uri,
+            AuthField::AccessKey => &self.aws.access_key,
+            AuthField::SecretKey => &self.aws.secret_key,
+            AuthField::SessionToken => &self.aws.session_token,
+            AuthField::Region => &self.aws.region,
+            AuthField::Service => &self.aws.service,
+            AuthField::Algorithm => self.hmac.algorithm.label(),
+            AuthField::Secret => &self.hmac.secret,
+            AuthField::Template if self.hmac.template.is_empty() => signing::DEFAULT_TEMPLATE,
+            AuthField::Template => &self.hmac.template,
+            AuthField::SignatureHeader => &self.hmac.header,
+            AuthField::SignaturePrefix => &self.hmac.prefix,
+            AuthField::Encoding => self.hmac.encoding.label(),
+            AuthField::TimestampHeader => &self.hmac.timestamp_header,
+            AuthField::JwtAlgorithm => self.jwt.algorithm.label(),
+            AuthField::JwtSecret => &self.jwt.secret,
+            AuthField::KeyFile => &self.jwt.key_file,
+            AuthField::KeyId => &self.jwt.key_id,
+            AuthField::Claims => &self.jwt.claims,
+        }
+    }
+
+    /// The text of a field, `None` for fields that are toggled instead
+    pub fn value_mut(&mut self, field: AuthField) -> Option<&mut String> {
+        match field {
+            AuthField::Username => Some(&mut self.username),
+            AuthField::Password => Some(&mut self.password),
+            AuthField::Token => Some(&mut self.token),
+            AuthField::KeyName => Some(&mut self.key_name),
+            AuthField::KeyValue => Some(&mut self.key_value),
+            AuthField::TokenUrl => Some(&mut self.oauth2.token_url),
+            AuthField::AuthUrl => Some(&mut self.oauth2.auth_url),
+            AuthField::ClientId => Some(&mut self.oauth2.client_id),
+            AuthField::ClientSecret => Some(&mut self.oauth2.client_secret),
+            AuthField::Scope => Some(&mut self.oauth2.scope),
+            AuthField::RefreshToken => Some(&mut self.oauth2.refresh_token),
+            AuthField::RedirectUri => Some(&mut self.oauth2.redirect_uri),
+            AuthField::AccessKey => Some(&mut self.aws.access_key),
+            AuthField::SecretKey => Some(&mut self.aws.secret_key),
+            AuthField::SessionToken => Some(&mut self.aws.session_token),
+            AuthField::Region => Some(&mut self.aws.region),
+            AuthField::Service => Some(&mut self.aws.service),
+            AuthField::Secret => Some(&mut self.hmac.secret),
+            AuthField::Template => Some(&mut self.hmac.template),
+            AuthField::SignatureHeader => Some(&mut self.hmac.header),
+            AuthField::SignaturePrefix => Some(&mut self.hmac.prefix),
+            AuthField::TimestampHeader => Some(&mut self.hmac.timestamp_header),
+            AuthField::JwtSecret => Some(&mut self.jwt.secret),
+            AuthField::KeyFile => Some(&mut self.jwt.key_file),
+            AuthField::KeyId => Some(&mut self.jwt.key_id),
+            AuthField::Claims => Some(&mut self.jwt.claims),
+            AuthField::KeyLocation
+            | AuthField::Grant
+            | AuthField::Algorithm
+            | AuthField::Encoding
+            | AuthField::JwtAlgorithm => None,
+        }
+    }
+
+    pub fn toggle(&mut self, field: AuthField) {
+        match field {
+            AuthField::KeyLocation => {
+                self.key_location = match self.key_location {
+                    ApiKeyLocation::Header => ApiKeyLocation::Query,
+                    ApiKeyLocation::Query => ApiKeyLocation::Header,
+                };
+            }
+            AuthField::Grant => self.oauth2.grant = self.oauth2.grant.next(),
+            AuthField::Algorithm => self.hmac.algorithm = self.hmac.algorithm.next(),
+            AuthField::Encoding => self.hmac.encoding = self.hmac.encoding.next(),
+            AuthField::JwtAlgorithm => self.jwt.algorithm = self.jwt.algorithm.next(),
+            _ => {}
+        }
+    }
+
+    /// The header this auth adds up front; Digest has none until challenged,
+    /// JWTs are signed by `prepare_request` and OAuth 2.0 tokens and
+    /// signatures are added when sending
+    pub fn header(&self) -> Option<(String, String)> {
+        match self.kind {
+            AuthKind::Basic => {
+                let credentials = format!("{}:{}", self.username, self.password);
+                let encoded = general_purpose::STANDARD.encode(credentials.as_bytes());
+                Some(("Authorization".to_string(), format!("Basic {}", encoded)))
+            }
+            AuthKind::Bearer if !self.token.trim().is_empty() => Some((
+                "Authorization".to_string(),
+                format!("Bearer {}", self.token.trim()),
+            )),
+            AuthKind::ApiKey
+                if self.key_location == ApiKeyLocation::Header
+                    && !self.key_name.trim().is_empty() =>
+            {
+                Some((self.key_name.trim().to_string(), self.key_value.clone()))
+            }
+            _ => None,
+        }
+    }
+
+    /// The query parameter this auth adds to the URL
+    pub fn query_param(&self) -> Option<(String, String)> {
+        (self.kind == AuthKind::ApiKey
+            && self.key_location == ApiKeyLocation::Query
+            && !self.key_name.trim().is_empty())
+        .then(|| (self.key_name.trim().to_string(), self.key_value.clone()))
+    }
+
+    /// Convert the old free-form auth table, keeping what it used to send:
+    /// `username`/`password` as Basic, `Bearer` or `Authorization` as the
+    /// Authorization header and `API-Key`/`X-API-Key` as that header
+    pub fn from_legacy(entries: &KeyValueEntries) -> Self {
+        let enabled = |name: &str| {
+            entries
+                .entries
+                .iter()
+                .find(|e| e.enabled && e.key.trim().eq_ignore_ascii_case(name))
+                .map(|e| e.value.trim().to_string())
+        };
+
+        if let (Some(username), Some(password)) = (enabled("username"), enabled("password")) {
+            return Auth {
+                kind: AuthKind::Basic,
+                username,
+                password,
+                ..Auth::default()
+            };
+        }
+        if let Some(value) = enabled("Bearer").or_else(|| enabled("Authorization")) {
+            // The old table sent the value as-is, so it may already carry
+            // the scheme
+            let token = match value.split_once(' ') {
+                Some((scheme, token)) if scheme.eq_ignore_ascii_case("bearer") => token,
+                Some(_) => {
+                    return Auth {
+                        kind: AuthKind::ApiKey,
+                        key_name: "Authorization".to_string(),
+                        key_value: value,
+                        ..Auth::default()
+                    }
+                }
+                None => value.as_str(),
+            };
+            return Auth {
+                kind: AuthKind::Bearer,
+                token: token.trim().to_string(),
+                ..Auth::default()
+            };
+        }
+        for name in ["X-API-Key", "API-Key"] {
+            if let Some(value) = enabled(name) {
+                return Auth {
+                    kind: AuthKind::ApiKey,
+                    key_name: name.to_string(),
+                    key_value: value,
+                    ..Auth::default()
+                };
+            }
+        }
+        Auth::default()
+    }
+}
+
+/// Read either the current auth object or the old key-value table
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Auth, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Stored {
+        Legacy(KeyValueEntries),
+        Typed(Box<Auth>),
+    }
+
+    Ok(match Stored::deserialize(deserializer)? {
+        Stored::Legacy(entries) => Auth::from_legacy(&entries),
+        Stored::Typed(auth) => *auth,
+    })
+}
+
+/// The parameters of a `WWW-Authenticate: Digest` challenge
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct DigestChallenge {
+    pub realm: String,
+    pub nonce: String,
+    pub opaque: Option<String>,
+    pub algorithm: Option<String>,
+    pub qop: Option<String>,
+}
+
+/// Parse a `WWW-Authenticate` value; `None` unless it is a Digest challenge
+pub fn parse_digest_challenge(header: &str) -> Option<DigestChallenge> {
+    let (scheme, params) = header.trim().split_once(' ')?;
+    if !scheme.eq_ignore_ascii_case("digest") {
+        return None;
+    }
+
+    let mut challenge = DigestChallenge::default();
+    let mut rest = params.trim();
+    while !rest.is_empty() {
+        let (key, after) = rest.split_once('=')?;
+        let after = after.trim_start();
+        let (value, after) = match after.strip_prefix('"') {
+            Some(quoted) => unquote(quoted)?,
+            None => {
+                let (value, after) = after.split_at(after.find(',').unwrap_or(after.len()));
+                (value.to_string(), after)
+            }
+        };
+        match key.trim().to_ascii_lowercase().as_str() {
+            "realm" => challenge.realm = value,
+            "nonce" => challenge.nonce = value,
+            "opaque" => challenge.opaque = Some(value),
+            "algorithm" => challenge.algorithm = Some(value),
+            "qop" => challenge.qop = Some(value),
+            _ => {}
+        }
+        rest = after.trim_start().trim_start_matches(',').trim_start();
+    }
+    (!challenge.nonce.is_empty()).then_some(challenge)
+}
+
+/// Read a quoted-string whose opening quote was already consumed,
+/// returning its unescaped value and the text after the closing quote
+fn unquote(quoted: &str) -> Option<(String, &str)> {
+    let mut value = String::new();
+    let mut chars = quoted.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return Some((value, &quoted[i + 1..])),
+            '\\' => value.push(chars.next()?.1),
+            c => value.push(c),
+        }
+    }
+    None
+}
+
+/// A random client nonce for Digest auth
+pub fn new_cnonce() -> String {
+    let mut bytes = [0u8; 16];
+    // Uniqueness is all that matters here, so a failure is not fatal
+    let _ = getrandom::fill(&mut bytes);
+    hex::encode(bytes)
+}
+
+/// The `Authorization` header answering a Digest challenge (RFC 7616)
+pub fn digest_authorization(
+    challenge: &DigestChallenge,
+    username: &str,
+    password: &str,
+    method: &str,
+    uri: &str,
+    cnonce: &str,
+) -> Result<String> {
+    let algorithm = challenge.algorithm.as_deref().unwrap_or("MD5");
+    let (hash, session): (fn(&str) -> String, bool) = match algorithm.to_ascii_uppercase().as_str()
+    {
+        "MD5" => (md5_hex, false),
+        "MD5-SESS" => (md5_hex, true),
+        "SHA-256" => (sha256_hex, false),
+        "SHA-256-SESS" => (sha256_hex, true),
+        other => bail!("Unsupported Digest algorithm '{}'", other),
+    };
+    let qop = match &challenge.qop {
+        Some(offered) if offered.split(',').any(|q| q.trim() == "auth") => Some("auth"),
+        Some(offered) => bail!("Unsupported Digest qop '{}'", offered),
+        None => None,
+    };
+    let nc = "00000001";
+
+    let mut ha1 = hash(&format!("{}:{}:{}", username, challenge.realm, password));
+    if session {
+        ha1 = hash(&format!("{}:{}:{}", ha1, challenge.nonce, cnonce));
+    }
+    let ha2 = hash(&format!("{}:{}", method, uri));
+    let response = match qop {
+        Some(qop) => hash(&format!(
+            "{}:{}:{}:{}:{}:{}",
+            ha1, challenge.nonce, nc, cnonce, qop, ha2
+        )),
+        None => hash(&format!("{}:{}:{}", ha1, challenge.nonce, ha2)),
+    };
+
+    let mut header = format!(
+        r#"Digest username={}, realm={}, nonce={}, uri={}, algorithm={}, response="{}""#,
+        quote(username),
+        quote(&challenge.realm),
+        quote(&challenge.nonce),
+        quote(uri),
+        algorithm,
+        response
+    );
+    if let Some(qop) = qop {
+        header.push_str(&format!(
+            r#", qop={}, nc={}, cnonce={}"#,
+            qop,
+            nc,
+            quote(cnonce)
+        ));
+    }
+    if let Some(opaque) = &challenge.opaque {
+        header.push_str(&format!(", opaque={}", quote(opaque)));
+    }
+    Ok(header)
+}
+
+fn sha256_hex(text: &str) -> String {
+    hex::encode(Sha256::digest(text.as_bytes()))
+}
+
+fn md5_hex(text: &str) -> String {
+    hex::encode(Md5::digest(text.as_bytes()))
+}
+
+/// `value` as a quoted-string (RFC 9110 section 5.6.4)
+fn quote(value: &str) -> String {
+    let escaped = value.replace('\\', "\\\\").replace('"', "\\\"");
+    format!("\"{}\"", escaped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_headers_per_kind() {
+        let mut auth = Auth {
+            kind: AuthKind::Basic,
+            username: "Aladdin".to_string(),
+            password: "open sesame".to_string(),
+            token: "abc".to_string(),
+            key_name: "X-API-Key".to_string(),
+            key_value: "k".to_string(),
+            ..Auth::default()
+        };
+        let header = |auth: &Auth| auth.header().map(|(_, v)| v);
+        assert_eq!(
+            header(&auth).as_deref(),
+            Some("Basic QWxhZGRpbjpvcGVuIHNlc2FtZQ==")
+        );
+        auth.kind = AuthKind::Bearer;
+        assert_eq!(header(&auth).as_deref(), Some("Bearer abc"));
+        auth.kind = AuthKind::ApiKey;
+        assert_eq!(
+            auth.header(),
+            Some(("X-API-Key".to_string(), "k".to_string()))
+        );
+        assert_eq!(auth.query_param(), None);
+        auth.toggle(AuthField::KeyLocation);
+        assert_eq!(auth.header(), None);
+        assert_eq!(
+            auth.query_param(),
+            Some(("X-API-Key".to_string(), "k".to_string()))
+        );
+        auth.kind = AuthKind::Digest;
+        assert_eq!(auth.header(), None);
+    }
+
+    #[test]
+    fn test_migrates_legacy_auth_table() {
+        let legacy = |pairs: &[(&str, &str)]| {
+            let mut entries = KeyValueEntries::new();
+            for (key, value) in pairs {
+                entries.add_entry(key.to_string(), value.to_string());
+            }
+            let json = serde_json::to_string(&entries).unwrap();
+            let mut de = serde_json::Deserializer::from_str(&json);
+            deserialize(&mut de).unwrap()
+        };
+
+        let basic = legacy(&[("username", "u"), ("password", "p")]);
+        assert_eq!(
+            (basic.kind, basic.username.as_str()),
+            (AuthKind::Basic, "u")
+        );
+        // A raw token gains the Bearer prefix it was always missing
+        let bearer = legacy(&[("Bearer", "abc")]);
+        assert_eq!(
+            (bearer.kind, bearer.token.as_str()),
+            (AuthKind::Bearer, "abc")
+        );
+        let prefixed = legacy(&[("Authorization", "Bearer abc")]);
+        assert_eq!(prefixed.token, "abc");
+        let other = legacy(&[("Authorization", "Token xyz")]);
+        assert_eq!(
+            other.header(),
+            Some(("Authorization".to_string(), "Token xyz".to_string()))
+        );
+        let key = legacy(&[("X-API-Key", "k")]);
+        assert_eq!(
+            key.header(),
+            Some(("X-API-Key".to_string(), "k".to_string()))
+        );
+        assert_eq!(legacy(&[]), Auth::default());
+
+        // The current format is read as-is
+        let json = r#"{"kind": "bearer", "token": "t"}"#;
+        let mut de = serde_json::Deserializer::from_str(json);
+        assert_eq!(deserialize(&mut de).unwrap().token, "t");
+    }
+
+    #[test]
+    fn test_md5() {
+        assert_eq!(md5_hex(""), "d41d8cd98f00b204e9800998ecf8427e");
+        assert_eq!(
+            md5_hex("The quick brown fox jumps over the lazy dog"),
+            "9e107d9d372bb6826bd81d3542a419d6"
+        );
+        assert_eq!(
+            md5_hex(&"a".repeat(100)),
+            "36a92cc94a9e0fa21f625f8bfb007adf"
+        );
+    }
+
+    #[test]
+    fn test_digest_rfc_examples() {
+        // RFC 2617 section 3.5
+        let header = r#"Digest realm="testrealm@host.com", qop="auth,auth-int", nonce="dcd98b7102dd2f0e8b11d0f600bfb0c093", opaque="5ccc069c403ebaf9f0171e9517f40e41""#;
+        let challenge = parse_digest_challenge(header).unwrap();
+        assert_eq!(challenge.realm, "testrealm@host.com");
+        let authorization = digest_authorization(
+            &challenge,
+            "Mufasa",
+            "Circle Of Life",
+            "GET",
+            "/dir/index.html",
+            "0a4f113b",
+        )
+        .unwrap();
+        assert!(authorization.contains(r#"response="6629fae49393a05397450978507c4ef1""#));
+        assert!(authorization.contains(r#"opaque="5ccc069c403ebaf9f0171e9517f40e41""#));
+
+        // RFC 7616 section 3.9.1
+        let header = r#"Digest realm="http-auth@example.org", qop="auth, auth-int", algorithm=SHA-256, nonce="7ypf/xlj9XXwfDPEoM4URrv/xwf94BcCAzFZH4GiTo0v", opaque="FQhe/qaU925kfnzjCev0ciny7QMkPqMAFRtzCUYo5tdS""#;
+        let challenge = parse_digest_challenge(header).unwrap();
+        let authorization = digest_authorization(
+            &challenge,
+            "Mufasa",
+            "Circle of Life",
+            "GET",
+            "/dir/index.html",
+            "f2/wE4q74E6zIJEtWaHKaf5wv/H5QzzpXusqGemxURZJ",
+        )
+        .unwrap();
+        assert!(authorization.contains(
+            r#"response="753927fa0e85d155564e2e272a28d1802ca10daf4496794697cf8db5856cb6c1""#
+        ));
+
+        assert!(parse_digest_challenge(r#"Basic realm="x""#).is_none());
+    }
+
+    #[test]
+    fn test_digest_quoted_strings() {
+        let header = r#"Digest realm="say \"hi\" \\ bye", nonce="n""#;
+        let challenge = parse_digest_challenge(header).unwrap();
+        assert_eq!(challenge.realm, r#"say "hi" \ bye"#);
+        assert_eq!(challenge.nonce, "n");
+
+        let authorization =
+            digest_authorization(&challenge, r#"a"b\c"#, "p", "GET", "/", "c").unwrap();
+        assert!(authorization
+            .starts_with(r#"Digest username="a\"b\\c", realm="say \"hi\" \\ bye", nonce="n""#));
+        assert!(parse_digest_challenge(r#"Digest nonce="open"#).is_none());
+    }
+}
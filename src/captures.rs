@@ -0,0 +1,199 @@
+use anyhow::{anyhow, bail, Result};
+use regex::Regex;
+use serde_json::Value;
+
+use crate::assertions::TestResult;
+use crate::environment::EnvironmentStore;
+use crate::key_value::KeyValueEntries;
+use crate::network::ApiResponse;
+
+/// Shown under the Captures tab
+pub const HINT: &str = "Key: variable to set in the active environment. \
+     Value: $.json.path, header <name>, cookie <name> or regex <pattern> \
+     (on the body; the first group if it has one)";
+
+/// Where in the response a captured value comes from
+#[derive(Debug, Clone)]
+pub enum Extractor {
+    JsonPath(String),
+    Header(String),
+    Cookie(String),
+    Regex(Regex),
+}
+
+impl Extractor {
+    pub fn parse(text: &str) -> Result<Self> {
+        let text = text.trim();
+        if text.starts_with('$') {
+            serde_json_path::JsonPath::parse(text)
+                .map_err(|e| anyhow!("invalid JSONPath: {}", e))?;
+            return Ok(Extractor::JsonPath(text.to_string()));
+        }
+        let (kind, arg) = text
+            .split_once(char::is_whitespace)
+            .map(|(kind, arg)| (kind.to_ascii_lowercase(), arg.trim()))
+            .unwrap_or_default();
+        Ok(match kind.as_str() {
+            "header" => Extractor::Header(arg.to_string()),
+            "cookie" => Extractor::Cookie(arg.to_string()),
+            "regex" => {
+                Extractor::Regex(Regex::new(arg).map_err(|e| anyhow!("invalid regex: {}", e))?)
+            }
+            _ => bail!(
+                "'{}' is not an extractor (expected $.path, header <name>, cookie <name> or regex <pattern>)",
+                text
+            ),
+        })
+    }
+
+    /// The first value found; JSON strings lose their quotes, other JSON
+    /// values are kept as JSON text
+    pub fn extract(&self, resp: &ApiResponse) -> Result<Option<String>> {
+        Ok(match self {
+            Extractor::JsonPath(path) => {
+                resp.json_path(path)?.into_iter().next().map(|v| match v {
+                    Value::String(s) => s,
+                    other => other.to_string(),
+                })
+            }
+            Extractor::Header(name) => resp.header_values(name).next().map(str::to_string),
+            Extractor::Cookie(name) => resp
+                .cookies()
+                .into_iter()
+                .find(|cookie| cookie.name == *name)
+                .map(|cookie| cookie.value),
+            Extractor::Regex(regex) => {
+                let body = String::from_utf8_lossy(&resp.raw_body);
+                regex.captures(&body).and_then(|caps| {
+                    caps.get(1)
+                        .or_else(|| caps.get(0))
+                        .map(|m| m.as_str().to_string())
+                })
+            }
+        })
+    }
+}
+
+/// Run every enabled capture on a response and store the values found in
+/// the active environment. Returns a failed test for each capture that
+/// found nothing, so a broken chain shows up with the request's tests.
+pub fn apply(
+    environments: &mut EnvironmentStore,
+    captures: &KeyValueEntries,
+    resp: &ApiResponse,
+) -> Vec<TestResult> {
+    let mut values = Vec::new();
+    let mut failures = Vec::new();
+    for entry in captures
+        .entries
+        .iter()
+        .filter(|e| e.enabled && !e.key.trim().is_empty())
+    {
+        let variable = entry.key.trim();
+        let found = Extractor::parse(&entry.value).and_then(|extractor| extractor.extract(resp));
+        let message = match found {
+            Ok(Some(value)) => {
+                values.push((variable.to_string(), value));
+                continue;
+            }
+            Ok(None) => format!("{} not found", entry.value.trim()),
+            Err(e) => format!("{:#}", e),
+        };
+        failures.push(TestResult {
+            name: format!("capture {}", variable),
+            passed: false,
+            message,
+        });
+    }
+
+    if !values.is_empty() {
+        if let Err(e) = environments.set_variables(&values) {
+            let names: Vec<_> = values.iter().map(|(name, _)| name.as_str()).collect();
+            failures.push(TestResult {
+                name: format!("capture {}", names.join(", ")),
+                passed: false,
+                message: format!("{:#}", e),
+            });
+        }
+    }
+    failures
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response() -> ApiResponse {
+        let raw =
+            br#"{"token": "abc.def", "user": {"id": 7}, "note": "order #1234 placed"}"#.to_vec();
+        ApiResponse {
+            status: 200,
+            version: "HTTP/1.1".to_string(),
+            url: "http://localhost/login".to_string(),
+            headers: vec![
+                ("Location".to_string(), "/users/7".to_string()),
+                (
+                    "Set-Cookie".to_string(),
+                    "session=s3cr3t; Path=/; HttpOnly".to_string(),
+                ),
+            ],
+            body: String::from_utf8_lossy(&raw).into_owned(),
+            size: raw.len(),
+            raw_body: raw,
+            timing: Default::default(),
+        }
+    }
+
+    fn extract(text: &str) -> Option<String> {
+        Extractor::parse(text)
+            .unwrap()
+            .extract(&response())
+            .unwrap()
+    }
+
+    #[test]
+    fn test_extractors() {
+        assert_eq!(extract("$.token").as_deref(), Some("abc.def"));
+        assert_eq!(extract("$.user.id").as_deref(), Some("7"));
+        assert_eq!(extract("$.user").as_deref(), Some(r#"{"id":7}"#));
+        assert_eq!(extract("$.missing"), None);
+        assert_eq!(extract("header location").as_deref(), Some("/users/7"));
+        assert_eq!(extract("cookie session").as_deref(), Some("s3cr3t"));
+        assert_eq!(extract("cookie other"), None);
+        assert_eq!(extract(r"regex order #(\d+)").as_deref(), Some("1234"));
+        assert_eq!(extract(r"regex #\d+").as_deref(), Some("#1234"));
+        assert!(Extractor::parse("token").is_err());
+        assert!(Extractor::parse("regex (").is_err());
+    }
+
+    #[test]
+    fn test_apply_stores_values_in_active_environment() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("environments.json");
+        let mut store = EnvironmentStore::with_active(dir.path(), &[("token", "old")]);
+        store.environments[0].variables.toggle_enabled(0);
+        store.active = None;
+
+        let mut captures = KeyValueEntries::new();
+        captures.add_entry("token".to_string(), "$.token".to_string());
+        captures.add_entry("user_id".to_string(), "$.user.id".to_string());
+        captures.add_entry("missing".to_string(), "header X-Nope".to_string());
+
+        // Nowhere to store the values without an active environment
+        let failures = apply(&mut store, &captures, &response());
+        assert_eq!(failures.len(), 2);
+        assert_eq!(failures[0].message, "header X-Nope not found");
+        assert_eq!(failures[1].name, "capture token, user_id");
+
+        store.select("local").unwrap();
+        let failures = apply(&mut store, &captures, &response());
+        assert_eq!(failures.len(), 1);
+        let variables = store.active().unwrap().variable_map();
+        assert_eq!(variables["token"], "abc.def");
+        assert_eq!(variables["user_id"], "7");
+
+        let saved = EnvironmentStore::load(path).unwrap();
+        assert_eq!(saved.environments[0].variables.entries.len(), 2);
+        assert_eq!(saved.environments[0].variable_map()["token"], "abc.def");
+    }
+}
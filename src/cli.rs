@@ -0,0 +1,485 @@
+use anyhow::{anyhow, Result};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use reqwest::StatusCode;
+use serde_json::{json, Value};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use crate::assertions::TestResult;
+use crate::collection::{unique_name, CollectionItem, CollectionStore};
+use crate::environment::EnvironmentStore;
+use crate::network::ApiResponse;
+use crate::postman;
+use crate::request::RequestSpec;
+use crate::runner::run_folder;
+use crate::storage;
+use crate::workspace::{Exchange, Workspace};
+
+/// The request could not be sent or no response arrived
+pub const EXIT_SEND_FAILED: i32 = 1;
+/// Bad arguments, an unknown request or environment, or an unreadable file
+pub const EXIT_USAGE: i32 = 2;
+/// An assertion in the request's Tests tab failed, or in `run` any test
+/// of any request
+pub const EXIT_TESTS_FAILED: i32 = 3;
+/// The response status was not 2xx and `--fail` was given, as with curl
+pub const EXIT_HTTP_ERROR: i32 = 22;
+
+/// A terminal HTTP client
+#[derive(Debug, Parser)]
+#[command(version, about)]
+pub struct Cli {
+    /// Start with the request described by a curl command line
+    #[arg(long, value_name = "COMMAND")]
+    pub curl: Option<String>,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Send a saved request without the UI, print the response and check
+    /// its tests
+    Send(SendArgs),
+    /// Send every request in the collection, or in one folder of it, in
+    /// order and report on their tests
+    Run(RunArgs),
+    /// Add Postman collection and environment exports to the workspace
+    Import(ImportArgs),
+}
+
+#[derive(Debug, Args)]
+pub struct SendArgs {
+    /// A saved request as `folder/name` (or just its name, if unique), or
+    /// a JSON file holding a request
+    #[arg(value_name = "NAME_OR_FILE")]
+    pub request: String,
+    /// Environment to take `{{variables}}` and settings from
+    #[arg(long, short)]
+    pub env: Option<String>,
+    /// How the response is printed
+    #[arg(long, short, value_enum, default_value_t = OutputFormat::Pretty)]
+    pub format: OutputFormat,
+    /// Exit with status 22 when the response status is not 2xx
+    #[arg(long)]
+    pub fail: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct RunArgs {
+    /// Folder to run as `parent/child`; the whole collection if omitted
+    #[arg(value_name = "FOLDER")]
+    pub folder: Option<String>,
+    /// Environment to take `{{variables}}` and settings from
+    #[arg(long, short)]
+    pub env: Option<String>,
+    /// Also write a JUnit XML report to this file
+    #[arg(long, value_name = "FILE")]
+    pub junit: Option<PathBuf>,
+    /// Also write a JSON report to this file
+    #[arg(long, value_name = "FILE")]
+    pub json: Option<PathBuf>,
+}
+
+#[derive(Debug, Args)]
+pub struct ImportArgs {
+    /// Postman v2.1 collection or environment files, in any order
+    #[arg(value_name = "FILE", required = true)]
+    pub files: Vec<PathBuf>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// Status line, headers and the body exactly as received
+    Raw,
+    /// Status line, headers and the body with JSON indented
+    Pretty,
+    /// One JSON object with the status, headers, body and timing
+    Json,
+}
+
+/// Read a request from a JSON file, or else look it up in the collection
+fn load_request(name_or_file: &str) -> Result<RequestSpec> {
+    let path = Path::new(name_or_file);
+    if path.is_file() {
+        return storage::read_json(path)?
+            .ok_or_else(|| anyhow!("{} does not exist", path.display()));
+    }
+    let collections = CollectionStore::load(CollectionStore::default_path())?;
+    Ok(collections.root.find_request(name_or_file)?.request.clone())
+}
+
+/// Run `send` and return the process exit status
+pub async fn send(args: SendArgs) -> i32 {
+    let prepared = load_request(&args.request).and_then(|original| {
+        let mut workspace = Workspace::load(args.env.as_deref())?;
+        let (request, client) = workspace.prepare(&original)?;
+        Ok((workspace, original, request, client))
+    });
+    let (mut workspace, original, request, client) = match prepared {
+        Ok(prepared) => prepared,
+        Err(e) => {
+            eprintln!("Error: {:#}", e);
+            return EXIT_USAGE;
+        }
+    };
+
+    let Exchange {
+        response, checks, ..
+    } = match workspace.exchange(&original, request, client).await {
+        Ok(exchanged) => exchanged,
+        Err(e) => {
+            eprintln!("Error: {:#}", e);
+            return EXIT_SEND_FAILED;
+        }
+    };
+    if let Err(e) = workspace.save_cookies() {
+        eprintln!("Warning: {:#}", e);
+    }
+    let results = checks.tests;
+    if let Err(e) = write_response(&mut io::stdout().lock(), &response, &results, args.format) {
+        // A closed pipe (e.g. `| head`) is not worth a message
+        if e.kind() != io::ErrorKind::BrokenPipe {
+            eprintln!("Error: {}", e);
+        }
+    }
+
+    // Results go to stderr so stdout stays just the response
+    for result in &results {
+        let mark = if result.passed { "✓" } else { "✗" };
+        match result.message.as_str() {
+            "" => eprintln!("{} {}", mark, result.name),
+            message => eprintln!("{} {}  {}", mark, result.name, message),
+        }
+    }
+
+    if results.iter().any(|r| !r.passed) {
+        EXIT_TESTS_FAILED
+    } else if args.fail && !(200..300).contains(&response.status) {
+        EXIT_HTTP_ERROR
+    } else {
+        0
+    }
+}
+
+/// Run `run` and return the process exit status
+pub async fn run(args: RunArgs) -> i32 {
+    let loaded = CollectionStore::load(CollectionStore::default_path()).and_then(|collections| {
+        let workspace = Workspace::load(args.env.as_deref())?;
+        Ok((workspace, collections))
+    });
+    let (mut workspace, collections) = match loaded {
+        Ok(loaded) => loaded,
+        Err(e) => {
+            eprintln!("Error: {:#}", e);
+            return EXIT_USAGE;
+        }
+    };
+    let suite = args.folder.as_deref().unwrap_or_default();
+    let folder = match collections.root.find_folder(suite) {
+        Ok(folder) => folder,
+        Err(e) => {
+            eprintln!("Error: {:#}", e);
+            return EXIT_USAGE;
+        }
+    };
+    let suite = match suite.trim_matches('/') {
+        "" => folder.name.as_str(),
+        path => path,
+    };
+
+    let report = run_folder(&mut workspace, suite, folder).await;
+    if let Err(e) = workspace.save_cookies() {
+        eprintln!("Warning: {:#}", e);
+    }
+    print!("{}", report.summary());
+
+    let reports = [
+        (&args.junit, report.junit_xml()),
+        (&args.json, format!("{:#}\n", report.to_json())),
+    ];
+    for (path, contents) in reports {
+        if let Some(path) = path {
+            if let Err(e) = std::fs::write(path, contents) {
+                eprintln!("Error: writing {}: {}", path.display(), e);
+                return EXIT_USAGE;
+            }
+        }
+    }
+
+    if report.errors() > 0 {
+        EXIT_SEND_FAILED
+    } else if report.failed() > 0 {
+        EXIT_TESTS_FAILED
+    } else {
+        0
+    }
+}
+
+/// Run `import` and return the process exit status. Nothing is saved
+/// unless every file converts.
+pub fn import(args: ImportArgs) -> i32 {
+    let imported = CollectionStore::load(CollectionStore::default_path()).and_then(|collections| {
+        let environments = EnvironmentStore::load(EnvironmentStore::default_path())?;
+        import_files(&args.files, collections, environments)
+    });
+    match imported {
+        Ok(warnings) => {
+            for warning in warnings {
+                eprintln!("Warning: {}", warning);
+            }
+            0
+        }
+        Err(e) => {
+            eprintln!("Error: {:#}", e);
+            EXIT_USAGE
+        }
+    }
+}
+
+/// Convert each file and add it to the collection and environments,
+/// renaming anything whose name is already taken. Returns the warnings.
+fn import_files(
+    files: &[PathBuf],
+    mut collections: CollectionStore,
+    mut environments: EnvironmentStore,
+) -> Result<Vec<String>> {
+    let mut warnings = Vec::new();
+    let (mut imported_folders, mut imported_environments) = (Vec::new(), Vec::new());
+    for path in files {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| anyhow!("reading {}: {}", path.display(), e))?;
+        let imported =
+            postman::import(&text).map_err(|e| e.context(format!("{}", path.display())))?;
+
+        if let Some(mut folder) = imported.folder {
+            let items = &collections.root.items;
+            folder.name = unique_name(&folder.name, |name| items.iter().any(|i| i.name() == name));
+            imported_folders.push(format!(
+                "Imported collection '{}' ({} requests)",
+                folder.name,
+                folder.requests().len()
+            ));
+            collections.root.items.push(CollectionItem::Folder(folder));
+        }
+        for mut environment in imported.environments {
+            let taken = &environments.environments;
+            environment.name = unique_name(&environment.name, |name| {
+                taken.iter().any(|e| e.name == name)
+            });
+            imported_environments.push(format!(
+                "Imported environment '{}' ({} variables)",
+                environment.name,
+                environment.variables.entries.len()
+            ));
+            environments.environments.push(environment);
+        }
+        warnings.extend(
+            imported
+                .warnings
+                .into_iter()
+                .map(|warning| format!("{}: {}", path.display(), warning)),
+        );
+    }
+
+    // The two files are written one after the other, so report what was
+    // saved before a failure
+    if !imported_folders.is_empty() {
+        collections.save()?;
+        for line in &imported_folders {
+            println!("{}", line);
+        }
+    }
+    if !imported_environments.is_empty() {
+        environments.save().map_err(|e| {
+            if imported_folders.is_empty() {
+                e
+            } else {
+                e.context("The collection was imported, but not its environments")
+            }
+        })?;
+        for line in &imported_environments {
+            println!("{}", line);
+        }
+    }
+    Ok(warnings)
+}
+
+/// Print a response in the chosen format; test results are only part of
+/// the JSON format
+pub fn write_response(
+    out: &mut impl Write,
+    resp: &ApiResponse,
+    tests: &[TestResult],
+    format: OutputFormat,
+) -> io::Result<()> {
+    if format == OutputFormat::Json {
+        serde_json::to_writer_pretty(&mut *out, &envelope(resp, tests))?;
+        return writeln!(out);
+    }
+
+    let reason = StatusCode::from_u16(resp.status)
+        .ok()
+        .and_then(|status| status.canonical_reason())
+        .unwrap_or_default();
+    writeln!(out, "{} {} {}", resp.version, resp.status, reason)?;
+    for (name, value) in &resp.headers {
+        writeln!(out, "{}: {}", name, value)?;
+    }
+    writeln!(out)?;
+    match format {
+        OutputFormat::Raw => out.write_all(&resp.raw_body),
+        _ => writeln!(out, "{}", resp.body),
+    }
+}
+
+/// The JSON form of a response; a JSON body is embedded as-is, any other
+/// body as a string
+fn envelope(resp: &ApiResponse, tests: &[TestResult]) -> Value {
+    let body = serde_json::from_slice::<Value>(&resp.raw_body)
+        .unwrap_or_else(|_| Value::String(String::from_utf8_lossy(&resp.raw_body).into_owned()));
+    let headers: Vec<_> = resp
+        .headers
+        .iter()
+        .map(|(name, value)| json!({ "name": name, "value": value }))
+        .collect();
+    json!({
+        "status": resp.status,
+        "version": resp.version,
+        "url": resp.url,
+        "headers": headers,
+        "body": body,
+        "size": resp.size,
+        "time_ms": resp.timing.total.as_millis() as u64,
+        "tests": tests,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response() -> ApiResponse {
+        let raw = br#"{"id":1}"#.to_vec();
+        ApiResponse {
+            status: 404,
+            version: "HTTP/1.1".to_string(),
+            url: "http://localhost/users/1".to_string(),
+            headers: vec![
+                ("content-type".to_string(), "application/json".to_string()),
+                ("set-cookie".to_string(), "a=1".to_string()),
+                ("set-cookie".to_string(), "b=2".to_string()),
+            ],
+            body: "{\n  \"id\": 1\n}".to_string(),
+            size: raw.len(),
+            raw_body: raw,
+            timing: Default::default(),
+        }
+    }
+
+    fn render(format: OutputFormat) -> String {
+        let mut out = Vec::new();
+        let tests = [TestResult {
+            name: "status 200".to_string(),
+            passed: false,
+            message: "got 404".to_string(),
+        }];
+        write_response(&mut out, &response(), &tests, format).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_output_formats() {
+        let head = "HTTP/1.1 404 Not Found\ncontent-type: application/json\n\
+                    set-cookie: a=1\nset-cookie: b=2\n\n";
+        assert_eq!(render(OutputFormat::Raw), format!("{}{{\"id\":1}}", head));
+        assert_eq!(
+            render(OutputFormat::Pretty),
+            format!("{}{{\n  \"id\": 1\n}}\n", head)
+        );
+
+        let json: Value = serde_json::from_str(&render(OutputFormat::Json)).unwrap();
+        assert_eq!(json["status"], 404);
+        assert_eq!(json["body"]["id"], 1);
+        assert_eq!(json["headers"][2]["value"], "b=2");
+        assert_eq!(json["tests"][0]["passed"], false);
+    }
+
+    #[test]
+    fn test_parses_send_command() {
+        let cli = Cli::try_parse_from([
+            "api-client",
+            "send",
+            "auth/login",
+            "--env",
+            "staging",
+            "-f",
+            "json",
+            "--fail",
+        ])
+        .unwrap();
+        let Some(Command::Send(args)) = cli.command else {
+            panic!("expected send");
+        };
+        assert_eq!(args.request, "auth/login");
+        assert_eq!(args.env.as_deref(), Some("staging"));
+        assert_eq!(args.format, OutputFormat::Json);
+        assert!(args.fail);
+    }
+
+    #[test]
+    fn test_parses_run_command() {
+        let cli =
+            Cli::try_parse_from(["api-client", "run", "smoke", "--junit", "out.xml"]).unwrap();
+        let Some(Command::Run(args)) = cli.command else {
+            panic!("expected run");
+        };
+        assert_eq!(args.folder.as_deref(), Some("smoke"));
+        assert_eq!(args.junit, Some(PathBuf::from("out.xml")));
+        assert_eq!(args.json, None);
+
+        let cli = Cli::try_parse_from(["api-client", "run", "-e", "ci"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Some(Command::Run(RunArgs { folder: None, .. }))
+        ));
+    }
+
+    #[test]
+    fn test_parses_import_command() {
+        let cli = Cli::try_parse_from(["api-client", "import", "api.json", "dev.json"]).unwrap();
+        let Some(Command::Import(args)) = cli.command else {
+            panic!("expected import");
+        };
+        assert_eq!(
+            args.files,
+            [PathBuf::from("api.json"), PathBuf::from("dev.json")]
+        );
+        assert!(Cli::try_parse_from(["api-client", "import"]).is_err());
+    }
+
+    #[test]
+    fn test_import_reports_a_half_saved_import() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("api.json");
+        std::fs::write(
+            &file,
+            r#"{
+                "info": {"name": "API", "schema": "https://schema.getpostman.com/json/collection/v2.1.0/collection.json"},
+                "item": [{"name": "ping", "request": {"method": "GET", "url": "{{base}}/ping"}}],
+                "variable": [{"key": "base", "value": "https://api.test"}]
+            }"#,
+        )
+        .unwrap();
+        let collections = CollectionStore::load(dir.path().join("collection.json")).unwrap();
+        let environments = EnvironmentStore::load(dir.path().join("environments.json")).unwrap();
+        // Nothing can be written where a directory is in the way
+        std::fs::create_dir(dir.path().join("environments.json")).unwrap();
+
+        let err = import_files(&[file], collections, environments).unwrap_err();
+        assert!(format!("{:#}", err)
+            .starts_with("The collection was imported, but not its environments: "));
+        let saved = CollectionStore::load(dir.path().join("collection.json")).unwrap();
+        assert_eq!(saved.root.items[0].name(), "API");
+    }
+}
@@ -0,0 +1,434 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+use crate::app::HttpMethod;
+use crate::request::RequestSpec;
+use crate::storage;
+
+/// A named request stored in a collection
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SavedRequest {
+    pub name: String,
+    #[serde(flatten)]
+    pub request: RequestSpec,
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct Folder {
+    pub name: String,
+    #[serde(default)]
+    pub items: Vec<CollectionItem>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum CollectionItem {
+    Folder(Folder),
+    Request(Box<SavedRequest>),
+}
+
+impl CollectionItem {
+    pub fn name(&self) -> &str {
+        match self {
+            CollectionItem::Folder(folder) => &folder.name,
+            CollectionItem::Request(saved) => &saved.name,
+        }
+    }
+
+    pub fn set_name(&mut self, name: String) {
+        match self {
+            CollectionItem::Folder(folder) => folder.name = name,
+            CollectionItem::Request(saved) => saved.name = name,
+        }
+    }
+}
+
+/// One line of the flattened collection tree, as shown in the sidebar
+#[derive(Debug, Clone, PartialEq)]
+pub struct TreeRow {
+    pub depth: usize,
+    /// Child indices from the root folder down to this item
+    pub path: Vec<usize>,
+    pub name: String,
+    /// `None` for folders
+    pub method: Option<HttpMethod>,
+}
+
+impl Folder {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            items: Vec::new(),
+        }
+    }
+
+    /// Flatten the tree depth-first, folders before their contents
+    pub fn rows(&self) -> Vec<TreeRow> {
+        let mut rows = Vec::new();
+        self.collect_rows(0, &mut Vec::new(), &mut rows);
+        rows
+    }
+
+    fn collect_rows(&self, depth: usize, path: &mut Vec<usize>, rows: &mut Vec<TreeRow>) {
+        for (idx, item) in self.items.iter().enumerate() {
+            path.push(idx);
+            rows.push(TreeRow {
+                depth,
+                path: path.clone(),
+                name: item.name().to_string(),
+                method: match item {
+                    CollectionItem::Folder(_) => None,
+                    CollectionItem::Request(saved) => Some(saved.request.method.clone()),
+                },
+            });
+            if let CollectionItem::Folder(folder) = item {
+                folder.collect_rows(depth + 1, path, rows);
+            }
+            path.pop();
+        }
+    }
+
+    pub fn get(&self, path: &[usize]) -> Option<&CollectionItem> {
+        let (first, rest) = path.split_first()?;
+        let item = self.items.get(*first)?;
+        match (item, rest.is_empty()) {
+            (_, true) => Some(item),
+            (CollectionItem::Folder(folder), false) => folder.get(rest),
+            (CollectionItem::Request(_), false) => None,
+        }
+    }
+
+    /// The request at a `/`-separated path of names such as `auth/login`.
+    /// A bare name also finds a request in any folder, if only one has it.
+    pub fn find_request(&self, path: &str) -> Result<&SavedRequest> {
+        let mut folder = self;
+        let mut names = path.split('/').filter(|name| !name.is_empty()).peekable();
+        while let Some(name) = names.next() {
+            let item = folder.items.iter().find(|item| match item {
+                CollectionItem::Folder(_) => names.peek().is_some() && item.name() == name,
+                CollectionItem::Request(_) => names.peek().is_none() && item.name() == name,
+            });
+            match item {
+                Some(CollectionItem::Folder(inner)) => folder = inner,
+                Some(CollectionItem::Request(saved)) => return Ok(saved),
+                None => break,
+            }
+        }
+
+        let matches: Vec<_> = self
+            .rows()
+            .into_iter()
+            .filter(|row| row.method.is_some() && row.name == path)
+            .collect();
+        match matches.as_slice() {
+            [row] => match self.get(&row.path) {
+                Some(CollectionItem::Request(saved)) => Ok(saved),
+                _ => unreachable!("rows only list existing items"),
+            },
+            [] => Err(anyhow!("No saved request named '{}'", path)),
+            _ => Err(anyhow!(
+                "{} saved requests are named '{}'; give the folder too, e.g. folder/{}",
+                matches.len(),
+                path,
+                path
+            )),
+        }
+    }
+
+    /// The folder at a `/`-separated path of names; an empty path is this
+    /// folder itself
+    pub fn find_folder(&self, path: &str) -> Result<&Folder> {
+        let mut folder = self;
+        for name in path.split('/').filter(|name| !name.is_empty()) {
+            folder = folder
+                .items
+                .iter()
+                .find_map(|item| match item {
+                    CollectionItem::Folder(inner) if inner.name == name => Some(inner),
+                    _ => None,
+                })
+                .ok_or_else(|| anyhow!("No folder named '{}' in '{}'", name, folder.name))?;
+        }
+        Ok(folder)
+    }
+
+    /// Every request in the tree in sidebar order, with its `/`-separated path
+    pub fn requests(&self) -> Vec<(String, &SavedRequest)> {
+        let mut requests = Vec::new();
+        self.collect_requests("", &mut requests);
+        requests
+    }
+
+    fn collect_requests<'a>(&'a self, prefix: &str, out: &mut Vec<(String, &'a SavedRequest)>) {
+        for item in &self.items {
+            let path = format!("{}{}", prefix, item.name());
+            match item {
+                CollectionItem::Folder(folder) => {
+                    folder.collect_requests(&format!("{}/", path), out)
+                }
+                CollectionItem::Request(saved) => out.push((path, saved)),
+            }
+        }
+    }
+
+    /// The folder at `path`, where an empty path is this folder itself
+    pub fn folder_mut(&mut self, path: &[usize]) -> Option<&mut Folder> {
+        match path.split_first() {
+            None => Some(self),
+            Some((first, rest)) => match self.items.get_mut(*first)? {
+                CollectionItem::Folder(folder) => folder.folder_mut(rest),
+                CollectionItem::Request(_) => None,
+            },
+        }
+    }
+
+    /// Save a request into the folder at `folder_path`, replacing any request
+    /// with the same name there. Returns the path of the saved item.
+    pub fn save_request(
+        &mut self,
+        folder_path: &[usize],
+        saved: SavedRequest,
+    ) -> Result<Vec<usize>> {
+        let folder = self
+            .folder_mut(folder_path)
+            .ok_or_else(|| anyhow!("Target folder no longer exists"))?;
+        let existing = folder
+            .items
+            .iter()
+            .position(|item| matches!(item, CollectionItem::Request(r) if r.name == saved.name));
+        let idx = match existing {
+            Some(idx) => {
+                folder.items[idx] = CollectionItem::Request(Box::new(saved));
+                idx
+            }
+            None => {
+                folder.items.push(CollectionItem::Request(Box::new(saved)));
+                folder.items.len() - 1
+            }
+        };
+        let mut path = folder_path.to_vec();
+        path.push(idx);
+        Ok(path)
+    }
+
+    pub fn add_folder(&mut self, folder_path: &[usize], name: &str) -> Result<Vec<usize>> {
+        let folder = self
+            .folder_mut(folder_path)
+            .ok_or_else(|| anyhow!("Target folder no longer exists"))?;
+        folder.items.push(CollectionItem::Folder(Folder::new(name)));
+        let mut path = folder_path.to_vec();
+        path.push(folder.items.len() - 1);
+        Ok(path)
+    }
+
+    pub fn rename(&mut self, path: &[usize], name: &str) -> Result<()> {
+        let (parent, idx) = self.parent_of(path)?;
+        parent.items[idx].set_name(name.to_string());
+        Ok(())
+    }
+
+    /// Insert a copy of the item right after it. Returns the copy's path.
+    pub fn duplicate(&mut self, path: &[usize]) -> Result<Vec<usize>> {
+        let (parent, idx) = self.parent_of(path)?;
+        let mut copy = parent.items[idx].clone();
+        copy.set_name(format!("{} copy", copy.name()));
+        parent.items.insert(idx + 1, copy);
+        let mut new_path = path.to_vec();
+        *new_path.last_mut().unwrap() += 1;
+        Ok(new_path)
+    }
+
+    pub fn remove(&mut self, path: &[usize]) -> Result<CollectionItem> {
+        let (parent, idx) = self.parent_of(path)?;
+        Ok(parent.items.remove(idx))
+    }
+
+    fn parent_of(&mut self, path: &[usize]) -> Result<(&mut Folder, usize)> {
+        let (idx, parent_path) = path
+            .split_last()
+            .ok_or_else(|| anyhow!("No item selected"))?;
+        let parent = self
+            .folder_mut(parent_path)
+            .filter(|parent| *idx < parent.items.len())
+            .ok_or_else(|| anyhow!("Item no longer exists"))?;
+        Ok((parent, *idx))
+    }
+}
+
+/// `name`, or `name (2)` and so on if that is taken
+pub fn unique_name(name: &str, taken: impl Fn(&str) -> bool) -> String {
+    let mut unique = name.to_string();
+    let mut n = 2;
+    while taken(&unique) {
+        unique = format!("{} ({})", name, n);
+        n += 1;
+    }
+    unique
+}
+
+/// The collection file backing the sidebar
+#[derive(Debug, Clone)]
+pub struct CollectionStore {
+    /// `None` when the file could not be read, so it is never overwritten
+    path: Option<PathBuf>,
+    pub root: Folder,
+}
+
+impl CollectionStore {
+    /// `collection.json` in the workspace directory
+    pub fn default_path() -> PathBuf {
+        storage::workspace_dir().join("collection.json")
+    }
+
+    /// Load a collection file, starting an empty collection if it does not exist
+    pub fn load(path: PathBuf) -> Result<Self> {
+        let root = storage::read_json(&path)?.unwrap_or_else(|| Folder::new("Collection"));
+        Ok(Self {
+            path: Some(path),
+            root,
+        })
+    }
+
+    /// An empty collection that refuses to save, used after a failed load
+    pub fn detached() -> Self {
+        Self {
+            path: None,
+            root: Folder::new("Collection"),
+        }
+    }
+
+    pub fn save(&self) -> Result<()> {
+        match &self.path {
+            Some(path) => storage::write_json(path, &self.root),
+            None => Err(anyhow!(
+                "Collection file failed to load; not saving to avoid overwriting it"
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn saved(name: &str, url: &str) -> SavedRequest {
+        SavedRequest {
+            name: name.to_string(),
+            request: RequestSpec {
+                url: url.to_string(),
+                ..RequestSpec::default()
+            },
+        }
+    }
+
+    #[test]
+    fn test_tree_operations() {
+        let mut root = Folder::new("root");
+        let users = root.add_folder(&[], "users").unwrap();
+        root.save_request(&users, saved("list", "/users")).unwrap();
+        let get = root.save_request(&users, saved("get", "/users/1")).unwrap();
+        root.save_request(&[], saved("health", "/health")).unwrap();
+
+        let names: Vec<_> = root.rows().into_iter().map(|r| (r.depth, r.name)).collect();
+        assert_eq!(
+            names,
+            [
+                (0, "users".to_string()),
+                (1, "list".to_string()),
+                (1, "get".to_string()),
+                (0, "health".to_string()),
+            ]
+        );
+
+        let copy = root.duplicate(&get).unwrap();
+        root.rename(&copy, "get-again").unwrap();
+        match root.get(&copy) {
+            Some(CollectionItem::Request(saved)) => {
+                assert_eq!(saved.name, "get-again");
+                assert_eq!(saved.request.url, "/users/1");
+            }
+            other => panic!("expected a request, got {:?}", other),
+        }
+
+        root.remove(&users).unwrap();
+        assert_eq!(root.rows().len(), 1);
+    }
+
+    #[test]
+    fn test_find_request_by_path_or_unique_name() {
+        let mut root = Folder::new("root");
+        let auth = root.add_folder(&[], "auth").unwrap();
+        root.save_request(&auth, saved("login", "/login")).unwrap();
+        root.save_request(&auth, saved("health", "/auth/health"))
+            .unwrap();
+        root.save_request(&[], saved("health", "/health")).unwrap();
+
+        assert_eq!(
+            root.find_request("auth/login").unwrap().request.url,
+            "/login"
+        );
+        assert_eq!(root.find_request("login").unwrap().request.url, "/login");
+        assert_eq!(root.find_request("health").unwrap().request.url, "/health");
+        assert_eq!(
+            root.find_request("auth/health").unwrap().request.url,
+            "/auth/health"
+        );
+        assert!(root.find_request("auth/missing").is_err());
+    }
+
+    #[test]
+    fn test_find_folder_and_list_requests() {
+        let mut root = Folder::new("root");
+        let auth = root.add_folder(&[], "auth").unwrap();
+        let tokens = root.add_folder(&auth, "tokens").unwrap();
+        root.save_request(&tokens, saved("refresh", "/refresh"))
+            .unwrap();
+        root.save_request(&auth, saved("login", "/login")).unwrap();
+        root.save_request(&[], saved("health", "/health")).unwrap();
+
+        let paths: Vec<_> = root.requests().into_iter().map(|(path, _)| path).collect();
+        assert_eq!(paths, ["auth/tokens/refresh", "auth/login", "health"]);
+
+        let auth = root.find_folder("auth").unwrap();
+        assert_eq!(auth.requests().len(), 2);
+        assert_eq!(root.find_folder("auth/tokens/").unwrap().name, "tokens");
+        assert_eq!(root.find_folder("").unwrap().name, "root");
+        assert!(root.find_folder("auth/login").is_err());
+    }
+
+    #[test]
+    fn test_save_replaces_request_with_same_name() {
+        let mut root = Folder::new("root");
+        root.save_request(&[], saved("a", "/old")).unwrap();
+        root.save_request(&[], saved("a", "/new")).unwrap();
+        assert_eq!(root.items.len(), 1);
+        assert!(matches!(&root.items[0], CollectionItem::Request(r) if r.request.url == "/new"));
+    }
+
+    #[test]
+    fn test_file_format_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("collection.json");
+        let mut store = CollectionStore::load(path.clone()).unwrap();
+        let folder = store.root.add_folder(&[], "auth").unwrap();
+        store
+            .root
+            .save_request(&folder, saved("login", "/login"))
+            .unwrap();
+        store.save().unwrap();
+
+        let text = std::fs::read_to_string(&path).unwrap();
+        assert!(text.contains("\"type\": \"folder\""));
+        assert!(!text.contains("focused_index"));
+        let reloaded = CollectionStore::load(path).unwrap();
+        assert_eq!(reloaded.root, store.root);
+    }
+
+    #[test]
+    fn test_unique_name() {
+        let taken = ["API", "API (2)"];
+        assert_eq!(unique_name("API", |name| taken.contains(&name)), "API (3)");
+        assert_eq!(unique_name("Other", |name| taken.contains(&name)), "Other");
+    }
+}
@@ -0,0 +1,47 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::storage;
+
+/// User configuration, read from `config.json` in the config directory
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Maximum number of requests kept in the on-disk history
+    pub history_limit: usize,
+    /// Default client settings for every request, e.g. `{"timeout": "30s"}`.
+    /// Keys are the same as in a request's Settings tab.
+    pub request: BTreeMap<String, String>,
+    /// Save cookie jars to `cookies.json` in the data directory so they
+    /// survive restarts
+    pub persist_cookies: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            history_limit: 500,
+            request: BTreeMap::new(),
+            persist_cookies: false,
+        }
+    }
+}
+
+impl Config {
+    pub fn path() -> PathBuf {
+        storage::config_dir().join("config.json")
+    }
+
+    /// Load the config file, falling back to defaults when it does not exist
+    pub fn load() -> Result<Self> {
+        Self::load_from(&Self::path())
+    }
+
+    /// Load a config file at `path`, falling back to defaults when it does
+    /// not exist
+    pub fn load_from(path: &Path) -> Result<Self> {
+        Ok(storage::read_json(path)?.unwrap_or_default())
+    }
+}
@@ -0,0 +1,385 @@
+use anyhow::Result;
+use reqwest::header::HeaderValue;
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::storage;
+
+/// A cookie from a `Set-Cookie` response header
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SetCookie {
+    pub name: String,
+    pub value: String,
+    pub domain: Option<String>,
+    pub path: Option<String>,
+    pub expires: Option<String>,
+    pub max_age: Option<i64>,
+    pub secure: bool,
+    pub http_only: bool,
+    pub same_site: Option<String>,
+}
+
+/// Parse a `Set-Cookie` header value; `None` if it has no `name=value` pair
+pub fn parse_set_cookie(header: &str) -> Option<SetCookie> {
+    let mut parts = header.split(';');
+    let (name, value) = parts.next()?.split_once('=')?;
+    let name = name.trim();
+    if name.is_empty() {
+        return None;
+    }
+
+    let mut cookie = SetCookie {
+        name: name.to_string(),
+        value: value.trim().trim_matches('"').to_string(),
+        ..SetCookie::default()
+    };
+    for attr in parts {
+        let (key, val) = match attr.split_once('=') {
+            Some((key, val)) => (key.trim(), Some(val.trim().to_string())),
+            None => (attr.trim(), None),
+        };
+        match key.to_ascii_lowercase().as_str() {
+            "domain" => cookie.domain = val.map(|d| d.trim_start_matches('.').to_string()),
+            "path" => cookie.path = val,
+            "expires" => cookie.expires = val,
+            "max-age" => cookie.max_age = val.and_then(|v| v.parse().ok()),
+            "secure" => cookie.secure = true,
+            "httponly" => cookie.http_only = true,
+            "samesite" => cookie.same_site = val,
+            _ => {}
+        }
+    }
+    Some(cookie)
+}
+
+/// A cookie held in a jar
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StoredCookie {
+    pub name: String,
+    pub value: String,
+    pub domain: String,
+    /// Without a `Domain` attribute a cookie is only sent to the exact host
+    #[serde(default)]
+    pub host_only: bool,
+    pub path: String,
+    /// Unix time after which the cookie is dropped; `None` for session cookies
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires: Option<u64>,
+    #[serde(default)]
+    pub secure: bool,
+    #[serde(default)]
+    pub http_only: bool,
+}
+
+impl StoredCookie {
+    fn matches(&self, url: &Url, now: u64) -> bool {
+        let host = url.host_str().unwrap_or_default();
+        let domain_ok = if self.host_only {
+            host.eq_ignore_ascii_case(&self.domain)
+        } else {
+            domain_match(host, &self.domain)
+        };
+        domain_ok
+            && path_match(url.path(), &self.path)
+            && (!self.secure || url.scheme() == "https")
+            && self.expires.is_none_or(|expires| expires > now)
+    }
+}
+
+/// Cookies collected from responses, following the storage rules of RFC 6265
+/// (without the public suffix list)
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CookieJar {
+    pub cookies: Vec<StoredCookie>,
+}
+
+impl CookieJar {
+    /// Store a cookie set by a response from `url`, replacing any cookie with
+    /// the same name, domain and path. Expired cookies delete that cookie.
+    pub fn store(&mut self, url: &Url, cookie: SetCookie, now: u64) {
+        let host = url.host_str().unwrap_or_default().to_ascii_lowercase();
+        let (domain, host_only) = match cookie.domain.map(|d| d.to_ascii_lowercase()) {
+            Some(domain) if !domain.is_empty() => {
+                if !domain_match(&host, &domain) {
+                    return;
+                }
+                (domain, false)
+            }
+            _ => (host, true),
+        };
+        let path = match cookie.path {
+            Some(path) if path.starts_with('/') => path,
+            _ => default_path(url.path()),
+        };
+        let expires = match (cookie.max_age, cookie.expires) {
+            (Some(max_age), _) => Some(now.saturating_add_signed(max_age)),
+            (None, Some(expires)) => httpdate::parse_http_date(&expires)
+                .ok()
+                .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                .map(|d| d.as_secs()),
+            (None, None) => None,
+        };
+
+        self.cookies
+            .retain(|c| !(c.name == cookie.name && c.domain == domain && c.path == path));
+        if expires.is_some_and(|expires| expires <= now) {
+            return;
+        }
+        self.cookies.push(StoredCookie {
+            name: cookie.name,
+            value: cookie.value,
+            domain,
+            host_only,
+            path,
+            expires,
+            secure: cookie.secure,
+            http_only: cookie.http_only,
+        });
+    }
+
+    /// The `Cookie` header value for a request to `url`, longest paths first
+    pub fn header_for(&self, url: &Url, now: u64) -> Option<String> {
+        let mut matching: Vec<_> = self
+            .cookies
+            .iter()
+            .filter(|c| c.matches(url, now))
+            .collect();
+        if matching.is_empty() {
+            return None;
+        }
+        matching.sort_by_key(|c| std::cmp::Reverse(c.path.len()));
+        Some(
+            matching
+                .iter()
+                .map(|c| format!("{}={}", c.name, c.value))
+                .collect::<Vec<_>>()
+                .join("; "),
+        )
+    }
+
+    /// Drop cookies that have expired
+    pub fn purge_expired(&mut self, now: u64) {
+        self.cookies
+            .retain(|c| c.expires.is_none_or(|expires| expires > now));
+    }
+}
+
+/// A jar shared between the UI and in-flight requests, usable as reqwest's
+/// cookie store so cookies set during redirects are kept too
+#[derive(Debug, Clone, Default)]
+pub struct SharedJar(Arc<Mutex<CookieJar>>);
+
+impl SharedJar {
+    pub fn lock(&self) -> MutexGuard<'_, CookieJar> {
+        self.0
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl reqwest::cookie::CookieStore for SharedJar {
+    fn set_cookies(&self, cookie_headers: &mut dyn Iterator<Item = &HeaderValue>, url: &Url) {
+        let now = unix_now();
+        let mut jar = self.lock();
+        for header in cookie_headers {
+            if let Some(cookie) = header.to_str().ok().and_then(parse_set_cookie) {
+                jar.store(url, cookie, now);
+            }
+        }
+    }
+
+    fn cookies(&self, url: &Url) -> Option<HeaderValue> {
+        self.lock()
+            .header_for(url, unix_now())
+            .and_then(|header| HeaderValue::from_str(&header).ok())
+    }
+}
+
+/// One cookie jar per environment, optionally saved to disk
+#[derive(Debug, Default)]
+pub struct CookieJars {
+    /// Where the jars are saved; `None` keeps them in memory only
+    path: Option<PathBuf>,
+    jars: BTreeMap<String, SharedJar>,
+}
+
+impl CookieJars {
+    /// `cookies.json` in the per-user data directory
+    pub fn default_path() -> PathBuf {
+        storage::data_dir().join("cookies.json")
+    }
+
+    /// Jars that live only as long as the app
+    pub fn in_memory() -> Self {
+        Self::default()
+    }
+
+    /// Load jars from `path` and save them back there after each change
+    pub fn load(path: PathBuf) -> Result<Self> {
+        let stored: BTreeMap<String, CookieJar> = storage::read_json(&path)?.unwrap_or_default();
+        let now = unix_now();
+        let jars = stored
+            .into_iter()
+            .map(|(name, mut jar)| {
+                jar.purge_expired(now);
+                (name, SharedJar(Arc::new(Mutex::new(jar))))
+            })
+            .collect();
+        Ok(Self {
+            path: Some(path),
+            jars,
+        })
+    }
+
+    pub fn get(&self, environment: &str) -> Option<&SharedJar> {
+        self.jars.get(environment)
+    }
+
+    /// The jar for an environment, created empty on first use
+    pub fn jar(&mut self, environment: &str) -> SharedJar {
+        self.jars
+            .entry(environment.to_string())
+            .or_default()
+            .clone()
+    }
+
+    /// Save every jar if persistence is enabled
+    pub fn save(&self) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let jars: BTreeMap<&String, CookieJar> = self
+            .jars
+            .iter()
+            .map(|(name, jar)| (name, jar.lock().clone()))
+            .filter(|(_, jar)| !jar.cookies.is_empty())
+            .collect();
+        storage::write_json(path, &jars)
+    }
+}
+
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+/// `host` is `domain` or a subdomain of it
+fn domain_match(host: &str, domain: &str) -> bool {
+    let host = host.to_ascii_lowercase();
+    host == domain || host.ends_with(&format!(".{}", domain))
+}
+
+fn path_match(request_path: &str, cookie_path: &str) -> bool {
+    request_path == cookie_path
+        || (request_path.starts_with(cookie_path)
+            && (cookie_path.ends_with('/') || request_path[cookie_path.len()..].starts_with('/')))
+}
+
+/// The directory of the request path, used when a cookie has no `Path`
+fn default_path(request_path: &str) -> String {
+    match request_path.rfind('/') {
+        Some(0) | None => "/".to_string(),
+        Some(idx) => request_path[..idx].to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_set_cookie() {
+        let cookie = parse_set_cookie(
+            "session=abc123; Path=/; Domain=.example.com; Max-Age=3600; Secure; HttpOnly; SameSite=Lax",
+        )
+        .unwrap();
+        assert_eq!(cookie.name, "session");
+        assert_eq!(cookie.value, "abc123");
+        assert_eq!(cookie.domain.as_deref(), Some("example.com"));
+        assert_eq!(cookie.path.as_deref(), Some("/"));
+        assert_eq!(cookie.max_age, Some(3600));
+        assert!(cookie.secure && cookie.http_only);
+        assert_eq!(cookie.same_site.as_deref(), Some("Lax"));
+
+        assert!(parse_set_cookie("garbage").is_none());
+    }
+
+    fn url(s: &str) -> Url {
+        Url::parse(s).unwrap()
+    }
+
+    fn set(jar: &mut CookieJar, from: &str, header: &str, now: u64) {
+        jar.store(&url(from), parse_set_cookie(header).unwrap(), now);
+    }
+
+    #[test]
+    fn test_jar_matching_rules() {
+        let mut jar = CookieJar::default();
+        set(
+            &mut jar,
+            "https://api.example.com/login",
+            "session=1; Domain=example.com; Path=/; Secure",
+            0,
+        );
+        set(
+            &mut jar,
+            "https://api.example.com/v1/users",
+            "prefs=dark",
+            0,
+        );
+        set(
+            &mut jar,
+            "https://api.example.com/",
+            "evil=1; Domain=other.com",
+            0,
+        );
+
+        // Domain cookies reach subdomains; host-only cookies do not
+        assert_eq!(
+            jar.header_for(&url("https://api.example.com/v1/users/7"), 0)
+                .as_deref(),
+            Some("prefs=dark; session=1")
+        );
+        assert_eq!(
+            jar.header_for(&url("https://www.example.com/"), 0)
+                .as_deref(),
+            Some("session=1")
+        );
+        // Secure cookies need https, and paths must match on a boundary
+        assert_eq!(
+            jar.header_for(&url("http://api.example.com/v1/users"), 0)
+                .as_deref(),
+            Some("prefs=dark")
+        );
+        assert_eq!(jar.header_for(&url("http://api.example.com/v1x"), 0), None);
+        assert_eq!(jar.header_for(&url("https://other.com/"), 0), None);
+    }
+
+    #[test]
+    fn test_jar_expiry_and_replacement() {
+        let mut jar = CookieJar::default();
+        set(&mut jar, "http://x.test/", "a=1; Max-Age=60", 1000);
+        set(&mut jar, "http://x.test/", "a=2; Max-Age=60", 1000);
+        assert_eq!(jar.cookies.len(), 1);
+        assert_eq!(
+            jar.header_for(&url("http://x.test/"), 1059).as_deref(),
+            Some("a=2")
+        );
+        assert_eq!(jar.header_for(&url("http://x.test/"), 1060), None);
+
+        // An expiry in the past deletes the cookie
+        set(
+            &mut jar,
+            "http://x.test/",
+            "a=; Expires=Thu, 01 Jan 1970 00:00:00 GMT",
+            1000,
+        );
+        assert!(jar.cookies.is_empty());
+    }
+}
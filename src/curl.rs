@@ -0,0 +1,663 @@
+use anyhow::{anyhow, bail, Context, Result};
+use std::collections::VecDeque;
+use std::str::FromStr;
+
+use crate::app::HttpMethod;
+use crate::auth::AuthKind;
+use crate::request::{BodyMode, RequestSpec};
+
+/// Options that take no argument and do not affect the request we build
+const IGNORED_FLAGS: &[&str] = &[
+    "-s",
+    "--silent",
+    "-S",
+    "--show-error",
+    "-L",
+    "--location",
+    "-v",
+    "--verbose",
+    "-i",
+    "--include",
+    "--compressed",
+    "-f",
+    "--fail",
+    "-g",
+    "--globoff",
+    "--http1.1",
+    "--http2",
+    "-#",
+    "--progress-bar",
+    "-N",
+    "--no-buffer",
+];
+
+/// Options whose argument does not affect the request we build
+const IGNORED_WITH_ARG: &[&str] = &["-o", "--output", "-w", "--write-out", "--retry"];
+
+/// Short options with an argument, which may be attached as in `-XPOST`
+const SHORT_WITH_VALUE: &[&str] = &[
+    "-X", "-H", "-d", "-F", "-u", "-A", "-e", "-b", "-o", "-m", "-w", "-x", "-E",
+];
+
+/// Short options without an argument that change the request we build
+const SHORT_SWITCHES: &[&str] = &["-k", "-I", "-G"];
+
+/// Parse a pasted `curl` command line into a request
+pub fn parse_curl(input: &str) -> Result<RequestSpec> {
+    let mut tokens: VecDeque<String> = tokenize(input)?.into();
+    match tokens.pop_front() {
+        Some(first) if first == "curl" => {}
+        _ => bail!("Not a curl command (expected it to start with `curl`)"),
+    }
+
+    let mut request = RequestSpec::default();
+    let mut method: Option<String> = None;
+    let mut url: Option<String> = None;
+    let mut data: Vec<String> = Vec::new();
+    let mut data_file: Option<String> = None;
+    let mut data_to_query = false;
+
+    while let Some(token) = tokens.pop_front() {
+        // Split `--opt=value` and attached short options such as `-XPOST`
+        let (flag, inline_value) = split_flag(&token);
+        // Bundled short options are handled one at a time
+        if inline_value.is_none() {
+            if let Some(flags) = split_short_flags(&flag) {
+                for flag in flags.into_iter().rev() {
+                    tokens.push_front(flag);
+                }
+                continue;
+            }
+        }
+        let mut value = |name: &str| -> Result<String> {
+            match &inline_value {
+                Some(v) => Ok(v.clone()),
+                None => tokens
+                    .pop_front()
+                    .ok_or_else(|| anyhow!("Option {} is missing its value", name)),
+            }
+        };
+
+        match flag.as_str() {
+            "-X" | "--request" => method = Some(value(&flag)?),
+            "-H" | "--header" => {
+                let header = value(&flag)?;
+                let (name, val) = header
+                    .split_once(':')
+                    .ok_or_else(|| anyhow!("Malformed header '{}'", header))?;
+                request
+                    .headers
+                    .add_entry(name.trim().to_string(), val.trim().to_string());
+            }
+            "-d" | "--data" | "--data-ascii" => {
+                let raw = value(&flag)?;
+                data.push(read_data(&raw, true)?);
+            }
+            "--data-raw" => data.push(value(&flag)?),
+            "--data-binary" => {
+                let raw = value(&flag)?;
+                // Sent as a file body, since it may not be text
+                match raw.strip_prefix('@') {
+                    Some(path) => data_file = Some(path.to_string()),
+                    None => data.push(raw),
+                }
+            }
+            "-F" | "--form" => {
+                let field = value(&flag)?;
+                let (name, val) = field
+                    .split_once('=')
+                    .ok_or_else(|| anyhow!("Malformed form field '{}'", field))?;
+                // `name=<file` sends the file's contents as a text field
+                let val = match val.strip_prefix('<') {
+                    Some(path) => std::fs::read_to_string(path)
+                        .with_context(|| format!("Failed to read form file {}", path))?,
+                    None => val.to_string(),
+                };
+                request.form.add_entry(name.to_string(), val);
+            }
+            "--data-urlencode" => data.push(urlencode_data(&value(&flag)?)),
+            "--json" => {
+                let raw = value(&flag)?;
+                data.push(read_data(&raw, false)?);
+                request
+                    .headers
+                    .add_entry("Content-Type".to_string(), "application/json".to_string());
+                request
+                    .headers
+                    .add_entry("Accept".to_string(), "application/json".to_string());
+            }
+            "-u" | "--user" => {
+                let credentials = value(&flag)?;
+                let (user, password) = credentials
+                    .split_once(':')
+                    .unwrap_or((credentials.as_str(), ""));
+                request.auth.username = user.to_string();
+                request.auth.password = password.to_string();
+                if !matches!(request.auth.kind, AuthKind::Digest | AuthKind::AwsSigV4) {
+                    request.auth.kind = AuthKind::Basic;
+                }
+            }
+            "--basic" => request.auth.kind = AuthKind::Basic,
+            "--digest" => request.auth.kind = AuthKind::Digest,
+            "--aws-sigv4" => {
+                // `provider1[:provider2[:region[:service]]]`
+                let spec = value(&flag)?;
+                let mut parts = spec.split(':').skip(2);
+                request.auth.kind = AuthKind::AwsSigV4;
+                request.auth.aws.region = parts.next().unwrap_or_default().to_string();
+                request.auth.aws.service = parts.next().unwrap_or_default().to_string();
+            }
+            "-A" | "--user-agent" => {
+                request
+                    .headers
+                    .add_entry("User-Agent".to_string(), value(&flag)?);
+            }
+            "-e" | "--referer" => {
+                request
+                    .headers
+                    .add_entry("Referer".to_string(), value(&flag)?);
+            }
+            "-b" | "--cookie" => {
+                request
+                    .headers
+                    .add_entry("Cookie".to_string(), value(&flag)?);
+            }
+            "-I" | "--head" => method = Some("HEAD".to_string()),
+            "--url" => url = Some(value(&flag)?),
+            "-m" | "--max-time" => add_setting(&mut request, "timeout", value(&flag)?),
+            "--connect-timeout" => {
+                add_setting(&mut request, "connect_timeout", value(&flag)?);
+            }
+            "--max-redirs" => add_setting(&mut request, "max_redirects", value(&flag)?),
+            "-x" | "--proxy" => add_setting(&mut request, "proxy", value(&flag)?),
+            "--noproxy" => add_setting(&mut request, "no_proxy", value(&flag)?),
+            "-k" | "--insecure" => add_setting(&mut request, "insecure", "true".to_string()),
+            "--cacert" => add_setting(&mut request, "ca_cert", value(&flag)?),
+            "-E" | "--cert" => {
+                // `cert[:password]`, as used for PKCS#12 files
+                let (cert, password) = split_cert(&value(&flag)?);
+                add_setting(&mut request, "client_cert", cert);
+                if let Some(password) = password {
+                    add_setting(&mut request, "client_cert_password", password);
+                }
+            }
+            "--key" => add_setting(&mut request, "client_key", value(&flag)?),
+            "--tlsv1.2" => add_setting(&mut request, "min_tls_version", "1.2".to_string()),
+            "--tlsv1.3" => add_setting(&mut request, "min_tls_version", "1.3".to_string()),
+            "-G" | "--get" => data_to_query = true,
+            f if IGNORED_FLAGS.contains(&f) => {}
+            f if IGNORED_WITH_ARG.contains(&f) => {
+                value(f)?;
+            }
+            f if f.starts_with('-') && f.len() > 1 => bail!("Unsupported curl option {}", f),
+            _ => {
+                if url.is_some() {
+                    bail!("Multiple URLs are not supported ('{}')", token);
+                }
+                url = Some(token);
+            }
+        }
+    }
+
+    if request.auth.kind == AuthKind::AwsSigV4 {
+        // `-u` holds the access key and secret, and a session token is
+        // passed as a header
+        request.auth.aws.access_key = std::mem::take(&mut request.auth.username);
+        request.auth.aws.secret_key = std::mem::take(&mut request.auth.password);
+        let entries = &mut request.headers.entries;
+        if let Some(idx) = entries
+            .iter()
+            .position(|e| e.key.eq_ignore_ascii_case("x-amz-security-token"))
+        {
+            request.auth.aws.session_token = entries.remove(idx).value;
+        }
+    }
+
+    let url = url.ok_or_else(|| anyhow!("No URL found in curl command"))?;
+    let (base, query) = match url.split_once('?') {
+        Some((base, query)) => (base.to_string(), Some(query.to_string())),
+        None => (url, None),
+    };
+    request.url = base;
+    if let Some(query) = query {
+        add_query_params(&mut request, &query);
+    }
+
+    let has_body = !request.form.entries.is_empty() || data_file.is_some() || !data.is_empty();
+    if !request.form.entries.is_empty() {
+        if data_file.is_some() || !data.is_empty() {
+            bail!("Cannot combine -F with -d/--data options");
+        }
+        request.body_mode = BodyMode::Multipart;
+    } else if let Some(path) = data_file {
+        if !data.is_empty() {
+            bail!("Cannot combine --data-binary @file with other data");
+        }
+        request.body_mode = BodyMode::Binary;
+        request.body_file = path;
+    } else if data_to_query {
+        for chunk in &data {
+            add_query_params(&mut request, chunk);
+        }
+    } else if !data.is_empty() {
+        request.body = data.join("&");
+        // Without a Content-Type, curl sends data as a urlencoded form
+        let content_type = request
+            .headers
+            .entries
+            .iter()
+            .find(|e| e.key.eq_ignore_ascii_case("Content-Type"))
+            .map(|e| e.value.clone())
+            .unwrap_or_else(|| "application/x-www-form-urlencoded".to_string());
+        let lower = content_type.to_ascii_lowercase();
+        if lower.contains("json") {
+            request.body_mode = BodyMode::Json;
+        } else if lower.contains("xml") {
+            request.body_mode = BodyMode::Xml;
+        } else {
+            request.body_mode = BodyMode::Text;
+            request.text_content_type = content_type;
+        }
+    }
+
+    let method = match method {
+        Some(m) => m,
+        None if has_body && !data_to_query => "POST".to_string(),
+        None => "GET".to_string(),
+    };
+    request.method = HttpMethod::from_str(&method)?;
+
+    Ok(request)
+}
+
+/// Split a shell command line into words, handling quotes, escapes and
+/// backslash-newline continuations
+fn tokenize(input: &str) -> Result<Vec<String>> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut in_token = false;
+    let mut chars = input.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                // Line continuation
+                Some('\n') => {}
+                Some('\r') if chars.peek() == Some(&'\n') => {
+                    chars.next();
+                }
+                Some(escaped) => {
+                    current.push(escaped);
+                    in_token = true;
+                }
+                None => {}
+            },
+            '\'' => {
+                in_token = true;
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(ch) => current.push(ch),
+                        None => bail!("Unterminated single quote"),
+                    }
+                }
+            }
+            '"' => {
+                in_token = true;
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(ch @ ('"' | '\\' | '$' | '`')) => current.push(ch),
+                            Some('\n') => {}
+                            Some(ch) => {
+                                current.push('\\');
+                                current.push(ch);
+                            }
+                            None => bail!("Unterminated double quote"),
+                        },
+                        Some(ch) => current.push(ch),
+                        None => bail!("Unterminated double quote"),
+                    }
+                }
+            }
+            // ANSI-C quoting, as produced by browsers' "Copy as cURL"
+            '$' if chars.peek() == Some(&'\'') => {
+                chars.next();
+                in_token = true;
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some('\\') => match chars.next() {
+                            Some('n') => current.push('\n'),
+                            Some('t') => current.push('\t'),
+                            Some('r') => current.push('\r'),
+                            Some(ch) => current.push(ch),
+                            None => bail!("Unterminated $'...' quote"),
+                        },
+                        Some(ch) => current.push(ch),
+                        None => bail!("Unterminated $'...' quote"),
+                    }
+                }
+            }
+            c if c.is_whitespace() => {
+                if in_token {
+                    tokens.push(std::mem::take(&mut current));
+                    in_token = false;
+                }
+            }
+            c => {
+                current.push(c);
+                in_token = true;
+            }
+        }
+    }
+    if in_token {
+        tokens.push(current);
+    }
+    Ok(tokens)
+}
+
+/// Split `--opt=value` and `-Xvalue` forms into option and inline value
+fn split_flag(token: &str) -> (String, Option<String>) {
+    if let Some(long) = token.strip_prefix("--") {
+        if let Some((name, value)) = long.split_once('=') {
+            return (format!("--{}", name), Some(value.to_string()));
+        }
+        return (token.to_string(), None);
+    }
+    for short in SHORT_WITH_VALUE {
+        if token.len() > 2 && token.starts_with(short) {
+            return (short.to_string(), Some(token[2..].to_string()));
+        }
+    }
+    (token.to_string(), None)
+}
+
+/// Split bundled short options such as `-sSLk` or `-sXPOST` into the
+/// options they stand for. An option with an argument ends the bundle and
+/// takes the rest of it, if any, as its value. `None` if `flag` is not a
+/// bundle of known options.
+fn split_short_flags(flag: &str) -> Option<Vec<String>> {
+    let letters = flag.strip_prefix('-')?;
+    if letters.chars().count() < 2 || letters.starts_with('-') {
+        return None;
+    }
+    let mut flags = Vec::new();
+    for (idx, c) in letters.char_indices() {
+        let short = format!("-{}", c);
+        if SHORT_WITH_VALUE.contains(&short.as_str()) {
+            flags.push(format!("{}{}", short, &letters[idx + c.len_utf8()..]));
+            return Some(flags);
+        }
+        if !IGNORED_FLAGS.contains(&short.as_str()) && !SHORT_SWITCHES.contains(&short.as_str()) {
+            return None;
+        }
+        flags.push(short);
+    }
+    Some(flags)
+}
+
+/// Split a `--cert` argument into the certificate and its password the way
+/// curl does: `\:` is a literal colon, the colon of a Windows drive such as
+/// `C:\` belongs to the path, and the password follows the last other colon
+fn split_cert(arg: &str) -> (String, Option<String>) {
+    let chars: Vec<char> = arg.chars().collect();
+    let mut cert = String::new();
+    // Length of `cert` and the index in `chars` where the password starts
+    let mut split = None;
+    let mut idx = 0;
+    while idx < chars.len() {
+        match chars[idx] {
+            '\\' if matches!(chars.get(idx + 1), Some('\\' | ':')) => {
+                idx += 1;
+                cert.push(chars[idx]);
+            }
+            ':' if idx == 1
+                && chars[0].is_ascii_alphabetic()
+                && matches!(chars.get(2), Some('\\' | '/')) =>
+            {
+                cert.push(':');
+            }
+            ':' => {
+                split = Some((cert.len(), idx + 1));
+                cert.push(':');
+            }
+            c => cert.push(c),
+        }
+        idx += 1;
+    }
+    match split {
+        Some((len, start)) => {
+            cert.truncate(len);
+            (cert, Some(chars[start..].iter().collect()))
+        }
+        None => (cert, None),
+    }
+}
+
+/// Resolve `@file` data arguments; curl strips newlines for `-d @file`
+fn read_data(raw: &str, strip_newlines: bool) -> Result<String> {
+    match raw.strip_prefix('@') {
+        Some(path) => {
+            let contents = std::fs::read_to_string(path)
+                .with_context(|| format!("Failed to read data file {}", path))?;
+            Ok(if strip_newlines {
+                contents.replace(['\r', '\n'], "")
+            } else {
+                contents
+            })
+        }
+        None => Ok(raw.to_string()),
+    }
+}
+
+/// `--data-urlencode` encodes the part after `=` (or the whole value)
+fn urlencode_data(raw: &str) -> String {
+    match raw.split_once('=') {
+        Some((name, value)) => format!("{}={}", name, urlencoding::encode(value)),
+        None => urlencoding::encode(raw).into_owned(),
+    }
+}
+
+fn add_setting(request: &mut RequestSpec, key: &str, value: String) {
+    request.settings.add_entry(key.to_string(), value);
+}
+
+fn add_query_params(request: &mut RequestSpec, query: &str) {
+    for pair in query.split('&').filter(|p| !p.is_empty()) {
+        let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+        request.params.add_entry(decode(key), decode(value));
+    }
+}
+
+fn decode(s: &str) -> String {
+    let s = s.replace('+', " ");
+    urlencoding::decode(&s).map(|d| d.into_owned()).unwrap_or(s)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pairs(entries: &crate::key_value::KeyValueEntries) -> Vec<(String, String)> {
+        entries
+            .entries
+            .iter()
+            .map(|e| (e.key.clone(), e.value.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_full_command() {
+        let cmd = r#"curl -X PUT 'https://api.example.com/users/1?verbose=true&q=a%20b' \
+  -H 'Content-Type: application/json' \
+  -H "Authorization: Bearer abc" \
+  -u alice:s3cret \
+  --data-raw '{"name": "Alice"}'"#;
+        let request = parse_curl(cmd).unwrap();
+
+        assert_eq!(request.method, HttpMethod::PUT);
+        assert_eq!(request.url, "https://api.example.com/users/1");
+        assert_eq!(
+            pairs(&request.params),
+            [
+                ("verbose".to_string(), "true".to_string()),
+                ("q".to_string(), "a b".to_string())
+            ]
+        );
+        assert_eq!(
+            pairs(&request.headers),
+            [
+                ("Content-Type".to_string(), "application/json".to_string()),
+                ("Authorization".to_string(), "Bearer abc".to_string())
+            ]
+        );
+        assert_eq!(request.auth.kind, AuthKind::Basic);
+        assert_eq!(request.auth.username, "alice");
+        assert_eq!(request.auth.password, "s3cret");
+        assert_eq!(request.body, r#"{"name": "Alice"}"#);
+    }
+
+    #[test]
+    fn test_data_implies_post_and_get_moves_it_to_query() {
+        let post = parse_curl("curl --url http://x/y -d a=1 --data b=2").unwrap();
+        assert_eq!(post.method, HttpMethod::POST);
+        assert_eq!(post.body, "a=1&b=2");
+
+        let get = parse_curl("curl -G http://x/y -d a=1 --data-urlencode 'q=hello world'").unwrap();
+        assert_eq!(get.method, HttpMethod::GET);
+        assert!(get.body.is_empty());
+        assert_eq!(
+            pairs(&get.params),
+            [
+                ("a".to_string(), "1".to_string()),
+                ("q".to_string(), "hello world".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_body_modes_from_data_options() {
+        let form = parse_curl("curl http://x -d a=1").unwrap();
+        assert_eq!(form.body_mode, BodyMode::Text);
+        assert_eq!(form.text_content_type, "application/x-www-form-urlencoded");
+
+        let xml = parse_curl("curl http://x -H 'Content-Type: text/xml' -d '<a/>'").unwrap();
+        assert_eq!(xml.body_mode, BodyMode::Xml);
+
+        let multipart = parse_curl("curl http://x -F name=Ann -F avatar=@me.png").unwrap();
+        assert_eq!(multipart.method, HttpMethod::POST);
+        assert_eq!(multipart.body_mode, BodyMode::Multipart);
+        assert_eq!(
+            pairs(&multipart.form),
+            [
+                ("name".to_string(), "Ann".to_string()),
+                ("avatar".to_string(), "@me.png".to_string())
+            ]
+        );
+
+        let binary = parse_curl("curl -X PUT http://x --data-binary @blob.bin").unwrap();
+        assert_eq!(binary.body_mode, BodyMode::Binary);
+        assert_eq!(binary.body_file, "blob.bin");
+        assert!(parse_curl("curl http://x -F a=1 -d b=2").is_err());
+    }
+
+    #[test]
+    fn test_head_and_custom_methods() {
+        assert_eq!(
+            parse_curl("curl -I http://x").unwrap().method,
+            HttpMethod::HEAD
+        );
+        assert_eq!(
+            parse_curl("curl -X PURGE http://x").unwrap().method,
+            HttpMethod::Custom("PURGE".to_string())
+        );
+        assert!(parse_curl("curl -X 'NOT OK' http://x").is_err());
+    }
+
+    #[test]
+    fn test_tokenize_quoting() {
+        let tokens = tokenize(r#"curl -XPOST "a \"b\"" 'c d'\''e' $'x\ny' f\ g"#).unwrap();
+        assert_eq!(
+            tokens,
+            ["curl", "-XPOST", "a \"b\"", "c d'e", "x\ny", "f g"]
+        );
+        assert!(tokenize("curl 'oops").is_err());
+    }
+
+    #[test]
+    fn test_rejects_unknown_options() {
+        assert!(parse_curl("curl --frobnicate http://x").is_err());
+        assert!(parse_curl("wget http://x").is_err());
+        assert!(parse_curl("curl -sSL --compressed http://x").is_ok());
+        assert!(parse_curl("curl -sZ http://x").is_err());
+    }
+
+    #[test]
+    fn test_combined_short_flags() {
+        let request = parse_curl("curl -sSkL http://x").unwrap();
+        assert_eq!(
+            pairs(&request.settings),
+            [("insecure".to_string(), "true".to_string())]
+        );
+
+        let request = parse_curl("curl -sIk http://x").unwrap();
+        assert_eq!(request.method, HttpMethod::HEAD);
+        assert_eq!(request.settings.entries.len(), 1);
+
+        let request = parse_curl("curl -kXPUT -sH 'X-A: 1' -sGd q=1 http://x").unwrap();
+        assert_eq!(request.method, HttpMethod::PUT);
+        assert!(request.body.is_empty());
+        assert_eq!(pairs(&request.params), [("q".to_string(), "1".to_string())]);
+        assert_eq!(
+            pairs(&request.headers),
+            [("X-A".to_string(), "1".to_string())]
+        );
+
+        let request = parse_curl("curl -sX PATCH http://x").unwrap();
+        assert_eq!(request.method, HttpMethod::PATCH);
+    }
+
+    #[test]
+    fn test_split_cert() {
+        let split = |arg: &str| {
+            let (cert, password) = split_cert(arg);
+            (cert, password.unwrap_or_default())
+        };
+        assert_eq!(split("me.p12"), ("me.p12".to_string(), String::new()));
+        assert_eq!(split("me.p12:pw"), ("me.p12".to_string(), "pw".to_string()));
+        assert_eq!(
+            split(r"C:\certs\c.pem:pw"),
+            (r"C:\certs\c.pem".to_string(), "pw".to_string())
+        );
+        assert_eq!(
+            split(r"C:\certs\c.pem"),
+            (r"C:\certs\c.pem".to_string(), String::new())
+        );
+        assert_eq!(
+            split(r"odd\:name.p12:pw"),
+            ("odd:name.p12".to_string(), "pw".to_string())
+        );
+        assert_eq!(split("a:b:pw"), ("a:b".to_string(), "pw".to_string()));
+    }
+
+    #[test]
+    fn test_client_options_become_settings() {
+        let request = parse_curl(
+            "curl -m 5 --connect-timeout 2 -x socks5://127.0.0.1:1080 -sk -E me.p12:pw http://x",
+        )
+        .unwrap();
+        assert_eq!(
+            pairs(&request.settings),
+            [
+                ("timeout".to_string(), "5".to_string()),
+                ("connect_timeout".to_string(), "2".to_string()),
+                ("proxy".to_string(), "socks5://127.0.0.1:1080".to_string()),
+                ("insecure".to_string(), "true".to_string()),
+                ("client_cert".to_string(), "me.p12".to_string()),
+                ("client_cert_password".to_string(), "pw".to_string())
+            ]
+        );
+    }
+}
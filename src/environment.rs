@@ -0,0 +1,320 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::path::PathBuf;
+
+use crate::key_value::KeyValueEntries;
+use crate::request::{BodyMode, RequestSpec};
+use crate::storage;
+
+/// A named set of variables referenced from requests as `{{name}}`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Environment {
+    pub name: String,
+    #[serde(default)]
+    pub variables: KeyValueEntries,
+    /// Client settings for every request sent in this environment, such as
+    /// `ca_cert` or `client_cert`; a request's Settings tab still wins
+    #[serde(default)]
+    pub settings: KeyValueEntries,
+}
+
+impl Environment {
+    /// Enabled variables as a lookup table
+    pub fn variable_map(&self) -> HashMap<String, String> {
+        self.variables.to_pairs()
+    }
+}
+
+/// The environments file, plus which environment is currently selected
+#[derive(Debug, Clone)]
+pub struct EnvironmentStore {
+    /// `None` when the file could not be read, so it is never overwritten
+    path: Option<PathBuf>,
+    pub environments: Vec<Environment>,
+    pub active: Option<usize>,
+}
+
+impl EnvironmentStore {
+    /// `environments.json` in the workspace directory
+    pub fn default_path() -> PathBuf {
+        storage::workspace_dir().join("environments.json")
+    }
+
+    pub fn load(path: PathBuf) -> Result<Self> {
+        let environments = storage::read_json(&path)?.unwrap_or_default();
+        Ok(Self {
+            path: Some(path),
+            environments,
+            active: None,
+        })
+    }
+
+    /// An empty store that refuses to save, used after a failed load
+    pub fn detached() -> Self {
+        Self {
+            path: None,
+            environments: Vec::new(),
+            active: None,
+        }
+    }
+
+    pub fn path(&self) -> Option<&PathBuf> {
+        self.path.as_ref()
+    }
+
+    pub fn active(&self) -> Option<&Environment> {
+        self.active.and_then(|idx| self.environments.get(idx))
+    }
+
+    /// Cycle through "no environment" and each defined environment
+    pub fn cycle(&mut self) {
+        self.active = match self.active {
+            None if !self.environments.is_empty() => Some(0),
+            Some(idx) if idx + 1 < self.environments.len() => Some(idx + 1),
+            _ => None,
+        };
+    }
+
+    /// Make the environment called `name` the active one
+    pub fn select(&mut self, name: &str) -> Result<()> {
+        let idx = self
+            .environments
+            .iter()
+            .position(|env| env.name == name)
+            .ok_or_else(|| {
+                let names: Vec<_> = self
+                    .environments
+                    .iter()
+                    .map(|env| env.name.as_str())
+                    .collect();
+                if names.is_empty() {
+                    anyhow!("No environment named '{}'; none are defined", name)
+                } else {
+                    anyhow!(
+                        "No environment named '{}' (defined: {})",
+                        name,
+                        names.join(", ")
+                    )
+                }
+            })?;
+        self.active = Some(idx);
+        Ok(())
+    }
+
+    /// Name of the cookie jar used by the active environment
+    pub fn cookie_jar_name(&self) -> String {
+        self.active()
+            .map(|env| env.name.clone())
+            .unwrap_or_else(|| "default".to_string())
+    }
+
+    /// Set variables in the active environment, enabling any that were
+    /// turned off, and save the file
+    pub fn set_variables(&mut self, values: &[(String, String)]) -> Result<()> {
+        let env = self
+            .active
+            .and_then(|idx| self.environments.get_mut(idx))
+            .ok_or_else(|| anyhow!("No active environment to store variables in"))?;
+        for (name, value) in values {
+            match env
+                .variables
+                .entries
+                .iter_mut()
+                .find(|entry| entry.key.trim() == name)
+            {
+                Some(entry) => {
+                    entry.value = value.clone();
+                    entry.enabled = true;
+                }
+                None => env.variables.add_entry(name.clone(), value.clone()),
+            }
+        }
+        self.save()
+    }
+
+    pub fn save(&self) -> Result<()> {
+        match &self.path {
+            Some(path) => storage::write_json(path, &self.environments),
+            None => Err(anyhow!(
+                "Environments file failed to load; not saving to avoid overwriting it"
+            )),
+        }
+    }
+
+    /// Substitute the active environment's variables into a request
+    pub fn resolve(&self, request: &RequestSpec) -> Result<RequestSpec> {
+        let variables = self
+            .active()
+            .map(Environment::variable_map)
+            .unwrap_or_default();
+        resolve_request(request, &variables)
+    }
+
+    /// A store saving to `environments.json` in `dir` with one active
+    /// environment, "local", holding `variables`
+    #[cfg(test)]
+    pub fn with_active(dir: &std::path::Path, variables: &[(&str, &str)]) -> Self {
+        let mut store = Self::load(dir.join("environments.json")).unwrap();
+        let mut entries = KeyValueEntries::new();
+        for (key, value) in variables {
+            entries.add_entry(key.to_string(), value.to_string());
+        }
+        store.environments.push(Environment {
+            name: "local".to_string(),
+            variables: entries,
+            settings: KeyValueEntries::new(),
+        });
+        store.select("local").unwrap();
+        store
+    }
+}
+
+/// Replaces `{{name}}` placeholders, remembering any names it could not resolve
+struct Substitutor<'a> {
+    variables: &'a HashMap<String, String>,
+    missing: BTreeSet<String>,
+}
+
+impl Substitutor<'_> {
+    fn apply(&mut self, text: &str) -> String {
+        let mut out = String::with_capacity(text.len());
+        let mut rest = text;
+        while let Some(start) = rest.find("{{") {
+            let Some(len) = rest[start + 2..].find("}}") else {
+                break;
+            };
+            out.push_str(&rest[..start]);
+            let name = rest[start + 2..start + 2 + len].trim();
+            match self.variables.get(name) {
+                Some(value) => out.push_str(value),
+                None => {
+                    self.missing.insert(name.to_string());
+                    out.push_str(&rest[start..start + len + 4]);
+                }
+            }
+            rest = &rest[start + len + 4..];
+        }
+        out.push_str(rest);
+        out
+    }
+
+    fn apply_entries(&mut self, entries: &KeyValueEntries) -> KeyValueEntries {
+        let mut resolved = entries.clone();
+        for entry in resolved.entries.iter_mut().filter(|entry| entry.enabled) {
+            entry.key = self.apply(&entry.key);
+            entry.value = self.apply(&entry.value);
+        }
+        resolved
+    }
+}
+
+/// Substitute `{{name}}` placeholders in the URL, every enabled key-value
+/// entry and the body. Fails listing every undefined variable.
+pub fn resolve_request(
+    request: &RequestSpec,
+    variables: &HashMap<String, String>,
+) -> Result<RequestSpec> {
+    let mut subst = Substitutor {
+        variables,
+        missing: BTreeSet::new(),
+    };
+    let mut resolved = request.clone();
+    resolved.url = subst.apply(&request.url);
+    resolved.headers = subst.apply_entries(&request.headers);
+    resolved.params = subst.apply_entries(&request.params);
+    for &field in request.auth.fields() {
+        if let Some(value) = resolved.auth.value_mut(field) {
+            *value = subst.apply(value);
+        }
+    }
+    resolved.settings = subst.apply_entries(&request.settings);
+    resolved.tests = subst.apply_entries(&request.tests);
+    // Only the part of the body that will be sent, so stale text in another
+    // mode cannot fail the request
+    match request.body_mode {
+        BodyMode::Json | BodyMode::Text | BodyMode::Xml => {
+            resolved.body = subst.apply(&request.body)
+        }
+        BodyMode::FormUrlEncoded | BodyMode::Multipart => {
+            resolved.form = subst.apply_entries(&request.form)
+        }
+        BodyMode::Binary => resolved.body_file = subst.apply(&request.body_file),
+    }
+
+    if subst.missing.is_empty() {
+        Ok(resolved)
+    } else {
+        let names: Vec<_> = subst
+            .missing
+            .iter()
+            .map(|name| format!("{{{{{}}}}}", name))
+            .collect();
+        Err(anyhow!("Undefined variable(s): {}", names.join(", ")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_resolves_all_parts_of_request() {
+        let mut request = RequestSpec {
+            url: "{{host}}/users/{{ id }}".to_string(),
+            body: r#"{"token": "{{token}}"}"#.to_string(),
+            ..RequestSpec::default()
+        };
+        request
+            .headers
+            .add_entry("X-{{header}}".to_string(), "{{token}}".to_string());
+
+        let variables = vars(&[
+            ("host", "https://staging"),
+            ("id", "7"),
+            ("token", "abc"),
+            ("header", "Token"),
+        ]);
+        let resolved = resolve_request(&request, &variables).unwrap();
+        assert_eq!(resolved.url, "https://staging/users/7");
+        assert_eq!(resolved.body, r#"{"token": "abc"}"#);
+        assert_eq!(resolved.headers.entries[0].key, "X-Token");
+        assert_eq!(resolved.headers.entries[0].value, "abc");
+    }
+
+    #[test]
+    fn test_reports_every_undefined_variable() {
+        let mut request = RequestSpec {
+            url: "{{host}}/{{path}}".to_string(),
+            ..RequestSpec::default()
+        };
+        request
+            .params
+            .add_entry("q".to_string(), "{{query}}".to_string());
+        // Fields of an auth type that is not selected are left alone
+        request.auth.token = "{{unused}}".to_string();
+
+        let err = resolve_request(&request, &vars(&[("host", "h")])).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Undefined variable(s): {{path}}, {{query}}"
+        );
+    }
+
+    #[test]
+    fn test_leaves_unterminated_braces_alone() {
+        let request = RequestSpec {
+            body: "{{a}} and {{b".to_string(),
+            ..RequestSpec::default()
+        };
+        let resolved = resolve_request(&request, &vars(&[("a", "1")])).unwrap();
+        assert_eq!(resolved.body, "1 and {{b");
+    }
+}
@@ -0,0 +1,649 @@
+use crate::network::{FormValue, PreparedBody, PreparedRequest};
+
+/// Stands in for an OAuth 2.0 token, which is only fetched when sending
+const ACCESS_TOKEN: &str = "<access token>";
+
+/// Languages/tools a request can be exported to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SnippetFormat {
+    Curl,
+    Httpie,
+    Reqwest,
+    Python,
+    Fetch,
+}
+
+impl SnippetFormat {
+    pub const ALL: [SnippetFormat; 5] = [
+        SnippetFormat::Curl,
+        SnippetFormat::Httpie,
+        SnippetFormat::Reqwest,
+        SnippetFormat::Python,
+        SnippetFormat::Fetch,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SnippetFormat::Curl => "curl",
+            SnippetFormat::Httpie => "HTTPie",
+            SnippetFormat::Reqwest => "Rust reqwest",
+            SnippetFormat::Python => "Python requests",
+            SnippetFormat::Fetch => "JavaScript fetch",
+        }
+    }
+
+    /// File name used when writing the snippet to disk
+    pub fn file_name(self) -> &'static str {
+        match self {
+            SnippetFormat::Curl => "request.curl.sh",
+            SnippetFormat::Httpie => "request.httpie.sh",
+            SnippetFormat::Reqwest => "request.rs",
+            SnippetFormat::Python => "request.py",
+            SnippetFormat::Fetch => "request.js",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        let idx = Self::ALL.iter().position(|f| *f == self).unwrap_or(0);
+        Self::ALL[(idx + 1) % Self::ALL.len()]
+    }
+
+    pub fn prev(self) -> Self {
+        let idx = Self::ALL.iter().position(|f| *f == self).unwrap_or(0);
+        Self::ALL[(idx + Self::ALL.len() - 1) % Self::ALL.len()]
+    }
+}
+
+/// Render a prepared request as a snippet in the given format
+pub fn render_snippet(request: &PreparedRequest, format: SnippetFormat) -> String {
+    let mut request = request.clone();
+    if request.oauth2.is_some() {
+        request.set_header("Authorization", &format!("Bearer {}", ACCESS_TOKEN));
+    }
+    let request = &request;
+    match format {
+        SnippetFormat::Curl => render_curl(request),
+        SnippetFormat::Httpie => render_httpie(request),
+        SnippetFormat::Reqwest => render_reqwest(request),
+        SnippetFormat::Python => render_python(request),
+        SnippetFormat::Fetch => render_fetch(request),
+    }
+}
+
+/// Quote a word for POSIX shells, leaving simple words unquoted
+pub fn shell_quote(s: &str) -> String {
+    let is_safe = !s.is_empty()
+        && s.chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./:=@%+,".contains(c));
+    if is_safe {
+        s.to_string()
+    } else {
+        format!("'{}'", s.replace('\'', r"'\''"))
+    }
+}
+
+/// A double-quoted string literal valid in JSON, JavaScript and Python
+fn quoted(s: &str) -> String {
+    serde_json::to_string(s).unwrap_or_default()
+}
+
+/// A comment, starting with `marker`, on where to get the OAuth 2.0 token
+/// the snippet leaves as a placeholder
+fn oauth2_note(request: &PreparedRequest, marker: &str) -> String {
+    let Some(oauth2) = &request.oauth2 else {
+        return String::new();
+    };
+    format!(
+        "{} Replace {} with an OAuth 2.0 access token ({} grant from {})\n",
+        marker,
+        ACCESS_TOKEN,
+        oauth2.grant.label(),
+        oauth2.token_url
+    )
+}
+
+/// A comment, starting with `marker`, on how to compute the HMAC signature
+/// that is only added when sending
+fn hmac_note(request: &PreparedRequest, marker: &str) -> String {
+    let Some(hmac) = &request.hmac else {
+        return String::new();
+    };
+    let mut lines = vec![
+        format!(
+            "Sign with {} over {} and send the {} signature",
+            hmac.algorithm.label(),
+            hmac.template(),
+            hmac.encoding.label()
+        ),
+        format!(
+            "as {}<signature> in the {} header",
+            hmac.prefix,
+            hmac.header.trim()
+        ),
+    ];
+    if !hmac.timestamp_header.trim().is_empty() {
+        lines.push(format!(
+            "and the Unix time used as {{timestamp}} in the {} header",
+            hmac.timestamp_header.trim()
+        ));
+    }
+    lines
+        .iter()
+        .map(|line| format!("{} {}\n", marker, line))
+        .collect()
+}
+
+fn render_curl(request: &PreparedRequest) -> String {
+    let mut args = vec!["curl".to_string()];
+    if request.method == reqwest::Method::HEAD {
+        // `-X HEAD` makes curl wait for a body that never arrives
+        args.push("--head".to_string());
+    } else if request.method != reqwest::Method::GET || request.body.is_some() {
+        args.push(format!("-X {}", shell_quote(request.method.as_str())));
+    }
+    args.push(shell_quote(&request.url));
+    if let Some((username, password)) = &request.digest {
+        args.push("--digest".to_string());
+        args.push(format!(
+            "-u {}",
+            shell_quote(&format!("{}:{}", username, password))
+        ));
+    }
+    if let Some(aws) = &request.sigv4 {
+        args.push(format!(
+            "--aws-sigv4 {}",
+            shell_quote(&format!("aws:amz:{}:{}", aws.region, aws.service))
+        ));
+        args.push(format!(
+            "-u {}",
+            shell_quote(&format!("{}:{}", aws.access_key, aws.secret_key))
+        ));
+        if !aws.session_token.is_empty() {
+            args.push(format!(
+                "-H {}",
+                shell_quote(&format!("x-amz-security-token: {}", aws.session_token))
+            ));
+        }
+    }
+    for (name, value) in &request.headers {
+        args.push(format!(
+            "-H {}",
+            shell_quote(&format!("{}: {}", name, value))
+        ));
+    }
+    match &request.body {
+        Some(PreparedBody::Text(body)) => args.push(format!("--data-raw {}", shell_quote(body))),
+        Some(PreparedBody::File(path)) => args.push(format!(
+            "--data-binary {}",
+            shell_quote(&format!("@{}", path.display()))
+        )),
+        Some(PreparedBody::Multipart(fields)) => {
+            for (name, value) in fields {
+                let field = match value {
+                    FormValue::Text(text) => format!("{}={}", name, text),
+                    FormValue::File(path) => format!("{}=@{}", name, path.display()),
+                };
+                args.push(format!("-F {}", shell_quote(&field)));
+            }
+        }
+        None => {}
+    }
+    let mut out = oauth2_note(request, "#");
+    out.push_str(&hmac_note(request, "#"));
+    out.push_str(&args.join(" \\\n  "));
+    out
+}
+
+fn render_httpie(request: &PreparedRequest) -> String {
+    let mut out = oauth2_note(request, "#");
+    out.push_str(&hmac_note(request, "#"));
+    if let Some(aws) = &request.sigv4 {
+        out.push_str(&format!(
+            "# Sign with AWS SigV4 for {} in {}, e.g. with the httpie-aws-authv4 plugin\n",
+            aws.service, aws.region
+        ));
+    }
+    let mut args = vec!["http".to_string()];
+    if let Some(PreparedBody::Multipart(_)) = &request.body {
+        args.push("--multipart".to_string());
+    }
+    if let Some((username, password)) = &request.digest {
+        args.push(format!(
+            "-A digest -a {}",
+            shell_quote(&format!("{}:{}", username, password))
+        ));
+    }
+    args.push(shell_quote(request.method.as_str()));
+    args.push(shell_quote(&request.url));
+    for (name, value) in &request.headers {
+        args.push(shell_quote(&format!("{}:{}", name, value)));
+    }
+    match &request.body {
+        Some(PreparedBody::Text(body)) => args.push(format!("--raw {}", shell_quote(body))),
+        Some(PreparedBody::File(path)) => {
+            args.push(format!("< {}", shell_quote(&path.display().to_string())))
+        }
+        Some(PreparedBody::Multipart(fields)) => {
+            for (name, value) in fields {
+                args.push(match value {
+                    FormValue::Text(text) => shell_quote(&format!("{}={}", name, text)),
+                    FormValue::File(path) => shell_quote(&format!("{}@{}", name, path.display())),
+                });
+            }
+        }
+        None => {}
+    }
+    out.push_str(&args.join(" \\\n  "));
+    out
+}
+
+fn render_reqwest(request: &PreparedRequest) -> String {
+    let mut out = oauth2_note(request, "//");
+    out.push_str(&hmac_note(request, "//"));
+    if let Some((username, _)) = &request.digest {
+        out.push_str(&format!(
+            "// Digest auth for {:?}: reqwest has no built-in support, so answer\n\
+             // the 401 WWW-Authenticate challenge with a second request\n",
+            username
+        ));
+    }
+    if let Some(aws) = &request.sigv4 {
+        out.push_str(&format!(
+            "// Sign with AWS SigV4 for {:?} in {:?}, e.g. with the aws-sigv4 crate\n",
+            aws.service, aws.region
+        ));
+    }
+    out.push_str("let client = reqwest::Client::new();\nlet response = client\n");
+    let method = match request.method.as_str() {
+        m @ ("GET" | "POST" | "PUT" | "DELETE" | "PATCH" | "HEAD" | "OPTIONS" | "TRACE"
+        | "CONNECT") => format!("reqwest::Method::{}", m),
+        m => format!("reqwest::Method::from_bytes(b{:?})?", m),
+    };
+    out.push_str(&format!("    .request({}, {:?})\n", method, request.url));
+    for (name, value) in &request.headers {
+        out.push_str(&format!("    .header({:?}, {:?})\n", name, value));
+    }
+    match &request.body {
+        Some(PreparedBody::Text(body)) => out.push_str(&format!("    .body({:?})\n", body)),
+        Some(PreparedBody::File(path)) => {
+            out.push_str(&format!("    .body(std::fs::read({:?})?)\n", path))
+        }
+        Some(PreparedBody::Multipart(fields)) => {
+            out.push_str("    .multipart(\n        reqwest::multipart::Form::new()\n");
+            for (name, value) in fields {
+                out.push_str(&match value {
+                    FormValue::Text(text) => format!("            .text({:?}, {:?})\n", name, text),
+                    FormValue::File(path) => format!(
+                        "            .part({:?}, reqwest::multipart::Part::bytes(std::fs::read({:?})?))\n",
+                        name, path
+                    ),
+                });
+            }
+            out.push_str("    )\n");
+        }
+        None => {}
+    }
+    out.push_str("    .send()\n    .await?;\n");
+    out.push_str("println!(\"{}\", response.status());\n");
+    out.push_str("println!(\"{}\", response.text().await?);\n");
+    out
+}
+
+fn render_python(request: &PreparedRequest) -> String {
+    let mut out = oauth2_note(request, "#");
+    out.push_str(&hmac_note(request, "#"));
+    out.push_str("import requests\n");
+    if request.sigv4.is_some() {
+        out.push_str("from requests_aws4auth import AWS4Auth\n");
+    }
+    out.push_str("\nresponse = requests.request(\n");
+    out.push_str(&format!("    {},\n", quoted(request.method.as_str())));
+    out.push_str(&format!("    {},\n", quoted(&request.url)));
+    if !request.headers.is_empty() {
+        out.push_str("    headers={\n");
+        for (name, value) in &request.headers {
+            out.push_str(&format!("        {}: {},\n", quoted(name), quoted(value)));
+        }
+        out.push_str("    },\n");
+    }
+    match &request.body {
+        Some(PreparedBody::Text(body)) => out.push_str(&format!("    data={},\n", quoted(body))),
+        Some(PreparedBody::File(path)) => out.push_str(&format!(
+            "    data=open({}, \"rb\"),\n",
+            quoted(&path.display().to_string())
+        )),
+        Some(PreparedBody::Multipart(fields)) => {
+            out.push_str("    files=[\n");
+            for (name, value) in fields {
+                let value = match value {
+                    FormValue::Text(text) => format!("(None, {})", quoted(text)),
+                    FormValue::File(path) => {
+                        format!("open({}, \"rb\")", quoted(&path.display().to_string()))
+                    }
+                };
+                out.push_str(&format!("        ({}, {}),\n", quoted(name), value));
+            }
+            out.push_str("    ],\n");
+        }
+        None => {}
+    }
+    if let Some((username, password)) = &request.digest {
+        out.push_str(&format!(
+            "    auth=requests.auth.HTTPDigestAuth({}, {}),\n",
+            quoted(username),
+            quoted(password)
+        ));
+    }
+    if let Some(aws) = &request.sigv4 {
+        let session_token = match aws.session_token.as_str() {
+            "" => String::new(),
+            token => format!(", session_token={}", quoted(token)),
+        };
+        out.push_str(&format!(
+            "    auth=AWS4Auth({}, {}, {}, {}{}),\n",
+            quoted(&aws.access_key),
+            quoted(&aws.secret_key),
+            quoted(&aws.region),
+            quoted(&aws.service),
+            session_token
+        ));
+    }
+    out.push_str(")\nprint(response.status_code)\nprint(response.text)\n");
+    out
+}
+
+fn render_fetch(request: &PreparedRequest) -> String {
+    let mut out = oauth2_note(request, "//");
+    out.push_str(&hmac_note(request, "//"));
+    if let Some((username, _)) = &request.digest {
+        out.push_str(&format!(
+            "// Digest auth for {}: fetch has no built-in support, so answer\n\
+             // the 401 WWW-Authenticate challenge with a second request\n",
+            quoted(username)
+        ));
+    }
+    if let Some(aws) = &request.sigv4 {
+        out.push_str(&format!(
+            "// Sign with AWS SigV4 for {} in {}, e.g. with @aws-sdk/signature-v4\n",
+            quoted(&aws.service),
+            quoted(&aws.region)
+        ));
+    }
+    if let Some(PreparedBody::File(_) | PreparedBody::Multipart(_)) = &request.body {
+        out.push_str("import { readFile } from \"node:fs/promises\";\n\n");
+    }
+    if let Some(PreparedBody::Multipart(fields)) = &request.body {
+        out.push_str("const form = new FormData();\n");
+        for (name, value) in fields {
+            let value = match value {
+                FormValue::Text(text) => quoted(text),
+                FormValue::File(path) => {
+                    let file_name = path
+                        .file_name()
+                        .map(|n| n.to_string_lossy().into_owned())
+                        .unwrap_or_default();
+                    format!(
+                        "new Blob([await readFile({})]), {}",
+                        quoted(&path.display().to_string()),
+                        quoted(&file_name)
+                    )
+                }
+            };
+            out.push_str(&format!("form.append({}, {});\n", quoted(name), value));
+        }
+        out.push('\n');
+    }
+    out.push_str(&format!(
+        "const response = await fetch({}, {{\n",
+        quoted(&request.url)
+    ));
+    out.push_str(&format!("  method: {},\n", quoted(request.method.as_str())));
+    if !request.headers.is_empty() {
+        out.push_str("  headers: {\n");
+        for (name, value) in &request.headers {
+            out.push_str(&format!("    {}: {},\n", quoted(name), quoted(value)));
+        }
+        out.push_str("  },\n");
+    }
+    match &request.body {
+        Some(PreparedBody::Text(body)) => out.push_str(&format!("  body: {},\n", quoted(body))),
+        Some(PreparedBody::File(path)) => out.push_str(&format!(
+            "  body: await readFile({}),\n",
+            quoted(&path.display().to_string())
+        )),
+        Some(PreparedBody::Multipart(_)) => out.push_str("  body: form,\n"),
+        None => {}
+    }
+    out.push_str("});\nconsole.log(response.status, await response.text());\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::HttpMethod;
+    use crate::auth::{Auth, AuthKind};
+    use crate::network::prepare_request;
+    use crate::request::RequestSpec;
+    use crate::sigv4::AwsSigV4Config;
+
+    fn sample() -> PreparedRequest {
+        let mut request = RequestSpec {
+            method: HttpMethod::POST,
+            url: "https://api.example.com/items".to_string(),
+            body: r#"{"name": "it's"}"#.to_string(),
+            ..RequestSpec::default()
+        };
+        request.params.add_entry("q".to_string(), "a b".to_string());
+        request
+            .headers
+            .add_entry("X-Trace".to_string(), "1".to_string());
+        request.auth = Auth {
+            kind: AuthKind::Basic,
+            username: "user".to_string(),
+            password: "pass".to_string(),
+            ..Auth::default()
+        };
+        prepare_request(&request).unwrap()
+    }
+
+    #[test]
+    fn test_curl_snippet() {
+        let snippet = render_snippet(&sample(), SnippetFormat::Curl);
+        assert_eq!(
+            snippet,
+            "curl \\\n  -X POST \\\n  'https://api.example.com/items?q=a+b' \\\n  \
+             -H 'X-Trace: 1' \\\n  -H 'Authorization: Basic dXNlcjpwYXNz' \\\n  \
+             -H 'Content-Type: application/json' \\\n  \
+             --data-raw '{\"name\": \"it'\\''s\"}'"
+        );
+    }
+
+    #[test]
+    fn test_curl_snippet_round_trips_through_import() {
+        let prepared = sample();
+        let snippet = render_snippet(&prepared, SnippetFormat::Curl);
+        let imported = crate::curl::parse_curl(&snippet).unwrap();
+        assert_eq!(prepare_request(&imported).unwrap(), prepared);
+    }
+
+    #[test]
+    fn test_aws_sigv4_snippets() {
+        let mut request = RequestSpec {
+            url: "https://abc.execute-api.eu-west-1.amazonaws.com/prod/items".to_string(),
+            ..RequestSpec::default()
+        };
+        request.auth.kind = AuthKind::AwsSigV4;
+        request.auth.aws = AwsSigV4Config {
+            access_key: "AKID".to_string(),
+            secret_key: "secret".to_string(),
+            session_token: "token".to_string(),
+            region: "eu-west-1".to_string(),
+            service: "execute-api".to_string(),
+        };
+        let prepared = prepare_request(&request).unwrap();
+
+        let curl = render_snippet(&prepared, SnippetFormat::Curl);
+        assert!(curl.contains(
+            "--aws-sigv4 aws:amz:eu-west-1:execute-api \\\n  -u AKID:secret \\\n  \
+             -H 'x-amz-security-token: token'"
+        ));
+        let imported = crate::curl::parse_curl(&curl).unwrap();
+        assert_eq!(prepare_request(&imported).unwrap(), prepared);
+
+        let httpie = render_snippet(&prepared, SnippetFormat::Httpie);
+        assert!(httpie.starts_with("# Sign with AWS SigV4 for execute-api in eu-west-1"));
+        let python = render_snippet(&prepared, SnippetFormat::Python);
+        assert!(python.contains("from requests_aws4auth import AWS4Auth\n"));
+        assert!(python.contains(
+            r#"auth=AWS4Auth("AKID", "secret", "eu-west-1", "execute-api", session_token="token"),"#
+        ));
+    }
+
+    #[test]
+    fn test_digest_auth_snippets() {
+        let mut request = RequestSpec {
+            url: "https://api.example.com/secret".to_string(),
+            ..RequestSpec::default()
+        };
+        request.auth = Auth {
+            kind: AuthKind::Digest,
+            username: "user".to_string(),
+            password: "pass".to_string(),
+            ..Auth::default()
+        };
+        let prepared = prepare_request(&request).unwrap();
+        assert_eq!(prepared.header("authorization"), None);
+
+        let curl = render_snippet(&prepared, SnippetFormat::Curl);
+        assert!(curl.contains("--digest \\\n  -u user:pass"));
+        let imported = crate::curl::parse_curl(&curl).unwrap();
+        assert_eq!(prepare_request(&imported).unwrap(), prepared);
+        assert!(render_snippet(&prepared, SnippetFormat::Python)
+            .contains(r#"auth=requests.auth.HTTPDigestAuth("user", "pass")"#));
+    }
+
+    #[test]
+    fn test_oauth2_snippets_use_a_token_placeholder() {
+        let mut request = RequestSpec {
+            url: "https://api.example.com/me".to_string(),
+            ..RequestSpec::default()
+        };
+        request.auth.kind = AuthKind::OAuth2;
+        request.auth.oauth2.token_url = "https://auth.example.com/token".to_string();
+        let prepared = prepare_request(&request).unwrap();
+        assert_eq!(prepared.header("authorization"), None);
+
+        for format in SnippetFormat::ALL {
+            let snippet = render_snippet(&prepared, format);
+            assert!(
+                snippet.contains("Bearer <access token>"),
+                "{:?}: {}",
+                format,
+                snippet
+            );
+            assert!(
+                snippet.contains("client credentials grant from https://auth.example.com/token"),
+                "{:?}",
+                format
+            );
+        }
+    }
+
+    #[test]
+    fn test_hmac_snippets_explain_the_signature() {
+        let mut request = RequestSpec {
+            method: HttpMethod::POST,
+            url: "https://api.example.com/hooks".to_string(),
+            body: "{}".to_string(),
+            ..RequestSpec::default()
+        };
+        request.auth.kind = AuthKind::Hmac;
+        request.auth.hmac.secret = "s".to_string();
+        request.auth.hmac.header = "X-Signature".to_string();
+        request.auth.hmac.prefix = "sha256=".to_string();
+        request.auth.hmac.timestamp_header = "X-Timestamp".to_string();
+        let prepared = prepare_request(&request).unwrap();
+
+        for format in SnippetFormat::ALL {
+            let snippet = render_snippet(&prepared, format);
+            let marker = match format {
+                SnippetFormat::Reqwest | SnippetFormat::Fetch => "//",
+                _ => "#",
+            };
+            let expected = format!(
+                "{m} Sign with HMAC-SHA256 over {{timestamp}}\\n{{method}}\\n{{path}}\\n{{body}} \
+                 and send the hex signature\n\
+                 {m} as sha256=<signature> in the X-Signature header\n\
+                 {m} and the Unix time used as {{timestamp}} in the X-Timestamp header\n",
+                m = marker
+            );
+            assert!(snippet.starts_with(&expected), "{:?}: {}", format, snippet);
+        }
+    }
+
+    #[test]
+    fn test_multipart_curl_snippet_round_trips() {
+        let mut request = RequestSpec {
+            method: HttpMethod::POST,
+            url: "https://api.example.com/upload".to_string(),
+            body_mode: crate::request::BodyMode::Multipart,
+            ..RequestSpec::default()
+        };
+        request
+            .form
+            .add_entry("title".to_string(), "My photo".to_string());
+        request
+            .form
+            .add_entry("photo".to_string(), "@photo.jpg".to_string());
+        let prepared = prepare_request(&request).unwrap();
+
+        let snippet = render_snippet(&prepared, SnippetFormat::Curl);
+        assert!(snippet.contains("-F 'title=My photo'"));
+        assert!(snippet.contains("-F photo=@photo.jpg"));
+        let imported = crate::curl::parse_curl(&snippet).unwrap();
+        assert_eq!(prepare_request(&imported).unwrap(), prepared);
+    }
+
+    #[test]
+    fn test_custom_method_is_quoted_for_the_shell() {
+        let request = RequestSpec {
+            method: "A&B".parse().unwrap(),
+            url: "https://api.example.com/".to_string(),
+            ..RequestSpec::default()
+        };
+        let prepared = prepare_request(&request).unwrap();
+
+        let curl = render_snippet(&prepared, SnippetFormat::Curl);
+        assert!(curl.contains("-X 'A&B'"), "{}", curl);
+        let imported = crate::curl::parse_curl(&curl).unwrap();
+        assert_eq!(prepare_request(&imported).unwrap(), prepared);
+        let httpie = render_snippet(&prepared, SnippetFormat::Httpie);
+        assert!(httpie.contains("'A&B' \\\n"), "{}", httpie);
+    }
+
+    #[test]
+    fn test_shell_quote() {
+        assert_eq!(shell_quote("simple/path"), "simple/path");
+        assert_eq!(shell_quote("a b"), "'a b'");
+        assert_eq!(shell_quote("it's"), r"'it'\''s'");
+        assert_eq!(shell_quote(""), "''");
+    }
+
+    #[test]
+    fn test_other_formats_include_every_part() {
+        let prepared = sample();
+        for format in [
+            SnippetFormat::Httpie,
+            SnippetFormat::Reqwest,
+            SnippetFormat::Python,
+            SnippetFormat::Fetch,
+        ] {
+            let snippet = render_snippet(&prepared, format);
+            assert!(snippet.contains("POST"), "{:?}", format);
+            assert!(snippet.contains("q=a+b"), "{:?}", format);
+            assert!(snippet.contains("Basic dXNlcjpwYXNz"), "{:?}", format);
+            assert!(snippet.contains("name"), "{:?}", format);
+        }
+    }
+}
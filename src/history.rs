@@ -0,0 +1,135 @@
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::PathBuf;
+
+use crate::app::RequestHistoryEntry;
+use crate::storage;
+
+/// On-disk request history, stored as a JSON array of entries (oldest first)
+#[derive(Debug, Clone)]
+pub struct HistoryStore {
+    path: PathBuf,
+    limit: usize,
+}
+
+impl HistoryStore {
+    pub fn new(path: PathBuf, limit: usize) -> Self {
+        Self { path, limit }
+    }
+
+    /// `history.json` in the per-user data directory
+    pub fn default_path() -> PathBuf {
+        storage::data_dir().join("history.json")
+    }
+
+    /// Load the stored history.
+    ///
+    /// A file that is not valid JSON is moved aside rather than overwritten,
+    /// and individual entries that fail to deserialize are skipped. Either
+    /// case is reported through the returned warning.
+    pub fn load(&self) -> (Vec<RequestHistoryEntry>, Option<String>) {
+        let contents = match fs::read_to_string(&self.path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return (Vec::new(), None),
+            Err(e) => return (Vec::new(), Some(format!("Could not read history: {}", e))),
+        };
+
+        let values = match serde_json::from_str::<Vec<serde_json::Value>>(&contents) {
+            Ok(values) => values,
+            Err(e) => {
+                let warning = match storage::quarantine(&self.path) {
+                    Ok(moved) => format!(
+                        "History file was corrupt ({}); moved to {}",
+                        e,
+                        moved.display()
+                    ),
+                    Err(move_err) => format!("History file was corrupt: {}", move_err),
+                };
+                return (Vec::new(), Some(warning));
+            }
+        };
+
+        let total = values.len();
+        let mut entries: Vec<RequestHistoryEntry> = values
+            .into_iter()
+            .filter_map(|value| serde_json::from_value(value).ok())
+            .collect();
+        let skipped = total - entries.len();
+        self.trim(&mut entries);
+
+        let warning =
+            (skipped > 0).then(|| format!("Skipped {} unreadable history entries", skipped));
+        (entries, warning)
+    }
+
+    /// Write the most recent `limit` entries to disk
+    pub fn save(&self, entries: &[RequestHistoryEntry]) -> Result<()> {
+        let start = entries.len().saturating_sub(self.limit);
+        storage::write_json(&self.path, &entries[start..]).context("Failed to save history")
+    }
+
+    /// Drop the oldest entries beyond the retention limit
+    pub fn trim(&self, entries: &mut Vec<RequestHistoryEntry>) {
+        if entries.len() > self.limit {
+            entries.drain(..entries.len() - self.limit);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::request::RequestSpec;
+
+    fn entry(url: &str) -> RequestHistoryEntry {
+        RequestHistoryEntry::new(RequestSpec {
+            url: url.to_string(),
+            ..RequestSpec::default()
+        })
+    }
+
+    #[test]
+    fn test_round_trip_respects_limit() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = HistoryStore::new(dir.path().join("history.json"), 2);
+        let entries = vec![entry("a"), entry("b"), entry("c")];
+        store.save(&entries).unwrap();
+
+        let (loaded, warning) = store.load();
+        assert!(warning.is_none());
+        let urls: Vec<_> = loaded.iter().map(|e| e.request.url.as_str()).collect();
+        assert_eq!(urls, ["b", "c"]);
+    }
+
+    #[test]
+    fn test_corrupt_file_is_moved_aside() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("history.json");
+        fs::write(&path, "[{\"method\": \"GET\", \"url\": ").unwrap();
+        let store = HistoryStore::new(path.clone(), 10);
+
+        let (loaded, warning) = store.load();
+        assert!(loaded.is_empty());
+        assert!(warning.unwrap().contains("corrupt"));
+        assert!(!path.exists());
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_unreadable_entries_are_skipped() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("history.json");
+        let good = serde_json::to_value(entry("ok")).unwrap();
+        fs::write(
+            &path,
+            serde_json::to_string(&vec![good, serde_json::json!(42)]).unwrap(),
+        )
+        .unwrap();
+        let store = HistoryStore::new(path, 10);
+
+        let (loaded, warning) = store.load();
+        assert_eq!(loaded.len(), 1);
+        assert_eq!(loaded[0].request.url, "ok");
+        assert!(warning.is_some());
+    }
+}
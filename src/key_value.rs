@@ -7,26 +7,34 @@ use ratatui::{
     widgets::Paragraph,
     Frame,
 };
-use reqwest::header::HeaderMap;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct KeyValueEntry {
     pub key: String,
     pub value: String,
+    #[serde(default = "default_enabled")]
     pub enabled: bool,
 }
 
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+fn default_enabled() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub enum KeyValueField {
+    #[default]
     Key,
     Value,
 }
 
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct KeyValueEntries {
     pub entries: Vec<KeyValueEntry>,
+    // Cursor state is UI-only and kept out of saved files
+    #[serde(skip)]
     pub focused_index: usize,
+    #[serde(skip)]
     pub focused_field: KeyValueField,
 }
 
@@ -71,26 +79,11 @@ impl KeyValueEntries {
         let mut map = HashMap::new();
         for entry in &self.entries {
             if entry.enabled {
-                map.insert(entry.key.clone(), entry.value.clone());
+                map.insert(entry.key.trim().to_string(), entry.value.clone());
             }
         }
         map
     }
-
-    pub fn to_header_map(&self) -> HeaderMap {
-        let mut headers = HeaderMap::new();
-        for entry in &self.entries {
-            if entry.enabled {
-                if let (Ok(name), Ok(value)) = (
-                    reqwest::header::HeaderName::from_bytes(entry.key.as_bytes()),
-                    reqwest::header::HeaderValue::from_str(&entry.value),
-                ) {
-                    headers.insert(name, value);
-                }
-            }
-        }
-        headers
-    }
 }
 
 /// Widget for rendering key-value entries in a two-column layout
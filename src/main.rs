@@ -1,11 +1,38 @@
 mod app;
+mod assertions;
+mod auth;
+mod captures;
+mod cli;
+mod collection;
+mod config;
+mod cookies;
+mod curl;
+mod environment;
+mod export;
 mod highlight;
+mod history;
+mod jwt;
 mod key_value;
 mod network;
+mod oauth;
+mod postman;
+mod request;
+mod runner;
+mod scripts;
+mod settings;
+mod signing;
+mod sigv4;
+mod storage;
+mod timing;
 mod ui;
+mod workspace;
 
+use clap::Parser;
 use crossterm::{
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode},
+    event::{
+        self, DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture,
+        Event, KeyCode,
+    },
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
@@ -17,22 +44,48 @@ use std::error::Error;
 use std::{io, time::Duration};
 use tokio::sync::mpsc;
 
-use crate::app::{App, FocusedPane, InputMode};
-use crate::network::{make_request, ApiResponse};
+use crate::app::{App, CollectionAction, FocusedPane, InputMode, ResponseMessage};
+use crate::cli::{Cli, Command};
+use crate::export::SnippetFormat;
+use crate::request::BodyMode;
 use crate::ui::ui;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn Error>> {
+    let cli = Cli::parse();
+    match cli.command {
+        Some(Command::Send(args)) => std::process::exit(cli::send(args).await),
+        Some(Command::Run(args)) => std::process::exit(cli::run(args).await),
+        Some(Command::Import(args)) => std::process::exit(cli::import(args)),
+        None => {}
+    }
+    // Parse before touching the terminal so errors print normally
+    let initial_request = match cli.curl.as_deref().map(curl::parse_curl).transpose() {
+        Ok(request) => request,
+        Err(e) => {
+            eprintln!("Error: {:#}", e);
+            std::process::exit(2);
+        }
+    };
+
     // Setup terminal
     enable_raw_mode()?;
     let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
+    execute!(
+        stdout,
+        EnterAlternateScreen,
+        EnableMouseCapture,
+        EnableBracketedPaste
+    )?;
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
     // Create App and Channel
     let mut app = App::new();
-    let (tx, mut rx) = mpsc::channel::<Result<ApiResponse, String>>(10);
+    if let Some(request) = initial_request {
+        app.load_request(request);
+    }
+    let (tx, mut rx) = mpsc::channel::<ResponseMessage>(10);
 
     // Run the main loop
     let res = run_app(&mut terminal, &mut app, tx, &mut rx).await;
@@ -42,7 +95,8 @@ async fn main() -> Result<(), Box<dyn Error>> {
     execute!(
         terminal.backend_mut(),
         LeaveAlternateScreen,
-        DisableMouseCapture
+        DisableMouseCapture,
+        DisableBracketedPaste
     )?;
     terminal.show_cursor()?;
 
@@ -56,15 +110,19 @@ async fn main() -> Result<(), Box<dyn Error>> {
 async fn run_app<B: Backend>(
     terminal: &mut Terminal<B>,
     app: &mut App,
-    tx: mpsc::Sender<Result<ApiResponse, String>>,
-    rx: &mut mpsc::Receiver<Result<ApiResponse, String>>,
+    tx: mpsc::Sender<ResponseMessage>,
+    rx: &mut mpsc::Receiver<ResponseMessage>,
 ) -> io::Result<()> {
     loop {
         terminal.draw(|f| ui(f, app))?;
 
         // 1. Poll for User Input
         if event::poll(Duration::from_millis(100))? {
-            if let Event::Key(key) = event::read()? {
+            let event = event::read()?;
+            if let Event::Paste(text) = &event {
+                app.paste(text);
+            }
+            if let Event::Key(key) = event {
                 // Global Quit
                 if app.input_mode == InputMode::Normal && key.code == KeyCode::Char('q') {
                     app.quit();
@@ -78,50 +136,95 @@ async fn run_app<B: Backend>(
                                 FocusedPane::Url => FocusedPane::RequestDetails,
                                 FocusedPane::RequestDetails => FocusedPane::Body,
                                 FocusedPane::Body => FocusedPane::Response,
-                                FocusedPane::Response => FocusedPane::Method,
+                                FocusedPane::Response => FocusedPane::Collections,
+                                FocusedPane::Collections => FocusedPane::Method,
                             };
                         }
-                        KeyCode::Char('i') => {
+                        KeyCode::Char('i') if app.focused_pane == FocusedPane::Method => {
+                            app.begin_method_edit();
+                        }
+                        KeyCode::Char('i') if app.focuses_auth() => {
+                            app.begin_auth_edit();
+                        }
+                        KeyCode::Char('i') if app.focuses_scripts() => {
+                            app.open_script_editor();
+                        }
+                        KeyCode::Char('i') if app.focused_pane != FocusedPane::Collections => {
                             app.input_mode = InputMode::Editing;
                         }
+                        // Collections sidebar
+                        KeyCode::Char('s') => {
+                            app.begin_collection_prompt(CollectionAction::SaveRequest);
+                        }
+                        KeyCode::Enter if app.focused_pane == FocusedPane::Collections => {
+                            app.open_selected_request();
+                        }
+                        KeyCode::Char('n') if app.focused_pane == FocusedPane::Collections => {
+                            app.begin_collection_prompt(CollectionAction::NewFolder);
+                        }
+                        KeyCode::Char('r') if app.focused_pane == FocusedPane::Collections => {
+                            app.begin_collection_prompt(CollectionAction::Rename);
+                        }
+                        KeyCode::Char('c') if app.focused_pane == FocusedPane::Collections => {
+                            app.duplicate_selected();
+                        }
+                        KeyCode::Char('d') if app.focused_pane == FocusedPane::Collections => {
+                            app.begin_collection_prompt(CollectionAction::ConfirmDelete);
+                        }
                         KeyCode::Enter => {
                             // Save to history before sending
-                            app.save_to_history();
-
-                            // Trigger Request!
-                            let sender = tx.clone();
-                            let method = app.method.clone();
-                            let url = app.url_input.clone();
-                            let headers = app.headers.clone();
-                            let params = app.params.clone();
-                            let auth = app.authorization.clone();
-                            let body = app.get_body_text();
-
-                            app.response_text = Some("Loading...".to_string());
-
-                            tokio::spawn(async move {
-                                match make_request(method, url, &headers, &params, &auth, body).await {
-                                    Ok(resp) => {
-                                        let _ = sender.send(Ok(resp)).await;
-                                    }
-                                    Err(e) => {
-                                        let _ = sender.send(Err(e.to_string())).await;
-                                    }
-                                }
-                            });
+                            let history_id = app.save_to_history();
+                            app.send_request(app.current_request(), history_id, tx.clone());
+                        }
+                        KeyCode::Esc if app.in_flight.is_some() || app.pending_retry.is_some() => {
+                            app.cancel_request();
+                        }
+                        KeyCode::Char('e') => {
+                            app.cycle_environment();
+                        }
+                        KeyCode::Char('m') if app.focused_pane == FocusedPane::Body => {
+                            app.next_body_mode();
+                        }
+                        KeyCode::Char('t')
+                            if app.focused_pane == FocusedPane::Body
+                                && app.body_mode == BodyMode::Text =>
+                        {
+                            app.next_text_content_type();
+                        }
+                        KeyCode::Char('t') if app.focuses_auth() => {
+                            app.next_auth_kind();
+                        }
+                        KeyCode::Char('x') if app.focuses_auth() => {
+                            app.forget_oauth_token();
+                        }
+                        KeyCode::Char('y') => {
+                            app.open_export(SnippetFormat::Curl);
+                        }
+                        KeyCode::Char('k') => {
+                            app.open_cookies();
+                        }
+                        KeyCode::Char('j') => {
+                            app.open_jwt_view();
+                        }
+                        KeyCode::Char('p')
+                            if !key
+                                .modifiers
+                                .contains(crossterm::event::KeyModifiers::CONTROL) =>
+                        {
+                            app.open_curl_import();
                         }
                         // Handle Method Cycling
                         KeyCode::Right | KeyCode::Char(' ') => {
                             if app.focused_pane == FocusedPane::Method {
                                 app.next_method();
-                            } else if app.focused_pane == FocusedPane::RequestDetails {
+                            } else {
                                 app.next_tab();
                             }
                         }
                         KeyCode::Left => {
                             if app.focused_pane == FocusedPane::Method {
                                 app.prev_method();
-                            } else if app.focused_pane == FocusedPane::RequestDetails {
+                            } else {
                                 app.prev_tab();
                             }
                         }
@@ -130,53 +233,169 @@ async fn run_app<B: Backend>(
                         KeyCode::Up => {
                             if app.focused_pane == FocusedPane::Url {
                                 app.prev_history();
+                            } else if app.focused_pane == FocusedPane::Collections {
+                                app.collection_select_prev();
                             } else if app.focused_pane == FocusedPane::Response {
                                 app.response_scroll = app.response_scroll.saturating_sub(1);
-                            } else if app.focused_pane == FocusedPane::RequestDetails {
+                            } else if app.focuses_auth() {
+                                app.prev_auth_field();
+                            } else if app.focuses_scripts() {
+                                app.script_stage = app.script_stage.other();
+                            } else if app.focuses_key_values() {
                                 // Navigate up in key-value rows
-                                let entries = app.get_active_tab_mut();
-                                if entries.focused_index > 0 {
-                                    entries.focused_index -= 1;
+                                if let Some(entries) = app.get_active_tab_mut() {
+                                    entries.focused_index = entries.focused_index.saturating_sub(1);
                                 }
                             }
                         }
                         KeyCode::Down => {
                             if app.focused_pane == FocusedPane::Url {
                                 app.next_history();
+                            } else if app.focused_pane == FocusedPane::Collections {
+                                app.collection_select_next();
                             } else if app.focused_pane == FocusedPane::Response {
                                 app.response_scroll = app.response_scroll.saturating_add(1);
-                            } else if app.focused_pane == FocusedPane::RequestDetails {
+                            } else if app.focuses_auth() {
+                                app.next_auth_field();
+                            } else if app.focuses_scripts() {
+                                app.script_stage = app.script_stage.other();
+                            } else if app.focuses_key_values() {
                                 // Navigate down in key-value rows
-                                let entries = app.get_active_tab_mut();
-                                // Allow navigating one past the end (for adding new entry)
-                                if entries.focused_index <= entries.entries.len() {
-                                    entries.focused_index += 1;
+                                if let Some(entries) = app.get_active_tab_mut() {
+                                    // Allow navigating one past the end (for adding new entry)
+                                    if entries.focused_index <= entries.entries.len() {
+                                        entries.focused_index += 1;
+                                    }
                                 }
                             }
                         }
                         KeyCode::Char('p')
                             if key
                                 .modifiers
-                                .contains(crossterm::event::KeyModifiers::CONTROL) =>
+                                .contains(crossterm::event::KeyModifiers::CONTROL)
+                                && app.focused_pane == FocusedPane::Url =>
                         {
-                            if app.focused_pane == FocusedPane::Url {
-                                app.prev_history();
-                            }
+                            app.prev_history();
                         }
                         KeyCode::Char('n')
                             if key
                                 .modifiers
-                                .contains(crossterm::event::KeyModifiers::CONTROL) =>
+                                .contains(crossterm::event::KeyModifiers::CONTROL)
+                                && app.focused_pane == FocusedPane::Url =>
                         {
-                            if app.focused_pane == FocusedPane::Url {
-                                app.next_history();
-                            }
+                            app.next_history();
                         }
                         _ => {}
                     }
                 } else if app.input_mode == InputMode::Editing {
                     // Handle Body pane separately - route all keys to TextArea
-                    if app.focused_pane == FocusedPane::Body {
+                    if let Some(export) = app.export.as_mut() {
+                        // Snippet export popup
+                        match key.code {
+                            KeyCode::Esc | KeyCode::Char('q') => app.close_export(),
+                            KeyCode::Right | KeyCode::Tab => {
+                                let format = export.format.next();
+                                app.open_export(format);
+                            }
+                            KeyCode::Left | KeyCode::BackTab => {
+                                let format = export.format.prev();
+                                app.open_export(format);
+                            }
+                            KeyCode::Up => export.scroll = export.scroll.saturating_sub(1),
+                            KeyCode::Down => export.scroll = export.scroll.saturating_add(1),
+                            KeyCode::Char('w') => app.write_export(),
+                            _ => {}
+                        }
+                    } else if let Some(view) = app.jwt_view.as_mut() {
+                        // JWT decoder popup
+                        match key.code {
+                            KeyCode::Esc | KeyCode::Char('q') => app.close_jwt_view(),
+                            KeyCode::Right | KeyCode::Tab => app.cycle_jwt(true),
+                            KeyCode::Left | KeyCode::BackTab => app.cycle_jwt(false),
+                            KeyCode::Up => view.scroll = view.scroll.saturating_sub(1),
+                            KeyCode::Down => view.scroll = view.scroll.saturating_add(1),
+                            _ => {}
+                        }
+                    } else if let Some(view) = app.cookie_view.as_mut() {
+                        // Cookie jar popup
+                        if let Some(edit) = view.edit.as_mut() {
+                            match key.code {
+                                KeyCode::Esc => view.edit = None,
+                                KeyCode::Enter => app.submit_cookie_edit(),
+                                KeyCode::Char(c) => edit.input.push(c),
+                                KeyCode::Backspace => {
+                                    edit.input.pop();
+                                }
+                                _ => {}
+                            }
+                        } else {
+                            match key.code {
+                                KeyCode::Esc | KeyCode::Char('q') => app.close_cookies(),
+                                KeyCode::Up => app.cookie_select_prev(),
+                                KeyCode::Down => app.cookie_select_next(),
+                                KeyCode::Enter => app.begin_cookie_edit(false),
+                                KeyCode::Char('a') => app.begin_cookie_edit(true),
+                                KeyCode::Char('d') | KeyCode::Delete => {
+                                    app.delete_selected_cookie()
+                                }
+                                KeyCode::Char('D') => app.clear_cookies(),
+                                _ => {}
+                            }
+                        }
+                    } else if let Some(editor) = app.script_editor.as_mut() {
+                        // Script editor popup
+                        match key.code {
+                            KeyCode::Esc => app.close_script_editor(),
+                            _ => {
+                                editor.textarea.input(key);
+                            }
+                        }
+                    } else if let Some(textarea) = app.curl_import.as_mut() {
+                        // curl import popup
+                        match key.code {
+                            KeyCode::Esc => app.close_curl_import(),
+                            KeyCode::Enter => app.import_curl(),
+                            _ => {
+                                textarea.input(key);
+                            }
+                        }
+                    } else if app.focused_pane == FocusedPane::Collections {
+                        // Name prompt (or delete confirmation) in the sidebar
+                        let confirming = app
+                            .collection_prompt
+                            .as_ref()
+                            .is_some_and(|p| p.action == CollectionAction::ConfirmDelete);
+                        match key.code {
+                            KeyCode::Char('y') if confirming => app.submit_collection_prompt(),
+                            _ if confirming => app.cancel_collection_prompt(),
+                            KeyCode::Esc => app.cancel_collection_prompt(),
+                            KeyCode::Enter => app.submit_collection_prompt(),
+                            KeyCode::Char(c) => {
+                                if let Some(prompt) = app.collection_prompt.as_mut() {
+                                    prompt.input.push(c);
+                                }
+                            }
+                            KeyCode::Backspace => {
+                                if let Some(prompt) = app.collection_prompt.as_mut() {
+                                    prompt.input.pop();
+                                }
+                            }
+                            _ => {}
+                        }
+                    } else if app.focused_pane == FocusedPane::Body
+                        && app.body_mode == BodyMode::Binary
+                    {
+                        // File path for the binary body
+                        match key.code {
+                            KeyCode::Esc => app.input_mode = InputMode::Normal,
+                            KeyCode::Char(c) => app.body_file.push(c),
+                            KeyCode::Backspace => {
+                                app.body_file.pop();
+                            }
+                            _ => {}
+                        }
+                        app.validate_body();
+                    } else if app.focused_pane == FocusedPane::Body && !app.body_mode.uses_form() {
                         match key.code {
                             KeyCode::Esc => {
                                 app.input_mode = InputMode::Normal;
@@ -188,7 +407,30 @@ async fn run_app<B: Backend>(
                                 app.validate_body();
                             }
                         }
-                    } else if app.focused_pane == FocusedPane::RequestDetails {
+                    } else if app.focuses_auth() {
+                        // Typing into the focused auth field
+                        match key.code {
+                            KeyCode::Esc => app.input_mode = InputMode::Normal,
+                            KeyCode::Tab | KeyCode::Enter => {
+                                // Keep typing if the next field is text
+                                app.next_auth_field();
+                                if app.auth_input_mut().is_none() {
+                                    app.input_mode = InputMode::Normal;
+                                }
+                            }
+                            KeyCode::Char(c) => {
+                                if let Some(input) = app.auth_input_mut() {
+                                    input.push(c);
+                                }
+                            }
+                            KeyCode::Backspace => {
+                                if let Some(input) = app.auth_input_mut() {
+                                    input.pop();
+                                }
+                            }
+                            _ => {}
+                        }
+                    } else if app.focuses_key_values() {
                         // Handle key-value field editing
                         match key.code {
                             KeyCode::Esc => {
@@ -196,7 +438,9 @@ async fn run_app<B: Backend>(
                             }
                             KeyCode::Tab => {
                                 // Switch between Key and Value fields
-                                let entries = app.get_active_tab_mut();
+                                let Some(entries) = app.get_active_tab_mut() else {
+                                    continue;
+                                };
                                 entries.focused_field = match entries.focused_field {
                                     crate::key_value::KeyValueField::Key => {
                                         crate::key_value::KeyValueField::Value
@@ -208,7 +452,9 @@ async fn run_app<B: Backend>(
                             }
                             KeyCode::Enter => {
                                 // Move to next row, create new if at end
-                                let entries = app.get_active_tab_mut();
+                                let Some(entries) = app.get_active_tab_mut() else {
+                                    continue;
+                                };
                                 if entries.focused_index >= entries.entries.len() {
                                     // Add new empty entry
                                     entries.add_entry(String::new(), String::new());
@@ -226,7 +472,9 @@ async fn run_app<B: Backend>(
                                     .contains(crossterm::event::KeyModifiers::CONTROL) =>
                             {
                                 // Remove current row
-                                let entries = app.get_active_tab_mut();
+                                let Some(entries) = app.get_active_tab_mut() else {
+                                    continue;
+                                };
                                 let idx = entries.focused_index;
                                 if idx < entries.entries.len() {
                                     entries.remove_entry(idx);
@@ -240,7 +488,9 @@ async fn run_app<B: Backend>(
                             }
                             KeyCode::Char(c) => {
                                 // Add character to current field
-                                let entries = app.get_active_tab_mut();
+                                let Some(entries) = app.get_active_tab_mut() else {
+                                    continue;
+                                };
                                 let focused_field = entries.focused_field.clone();
                                 let focused_index = entries.focused_index;
 
@@ -270,7 +520,9 @@ async fn run_app<B: Backend>(
                             }
                             KeyCode::Backspace => {
                                 // Remove character from current field
-                                let entries = app.get_active_tab_mut();
+                                let Some(entries) = app.get_active_tab_mut() else {
+                                    continue;
+                                };
                                 let focused_field = entries.focused_field.clone();
                                 let focused_index = entries.focused_index;
 
@@ -287,22 +539,29 @@ async fn run_app<B: Backend>(
                             }
                             _ => {}
                         }
+                    } else if let Some(input) = app.method_input.as_mut() {
+                        // Typing a method name
+                        match key.code {
+                            KeyCode::Esc => app.finish_method_edit(false),
+                            KeyCode::Enter => app.finish_method_edit(true),
+                            KeyCode::Char(c) => input.push(c),
+                            KeyCode::Backspace => {
+                                input.pop();
+                            }
+                            _ => {}
+                        }
                     } else {
                         // URL pane - character-by-character handling
                         match key.code {
                             KeyCode::Esc => {
                                 app.input_mode = InputMode::Normal;
                             }
-                            KeyCode::Char(c) => match app.focused_pane {
-                                FocusedPane::Url => app.url_input.push(c),
-                                _ => {}
-                            },
-                            KeyCode::Backspace => match app.focused_pane {
-                                FocusedPane::Url => {
-                                    app.url_input.pop();
-                                }
-                                _ => {}
-                            },
+                            KeyCode::Char(c) if app.focused_pane == FocusedPane::Url => {
+                                app.url_input.push(c);
+                            }
+                            KeyCode::Backspace if app.focused_pane == FocusedPane::Url => {
+                                app.url_input.pop();
+                            }
                             _ => {}
                         }
                     }
@@ -311,18 +570,10 @@ async fn run_app<B: Backend>(
         }
 
         // 2. Poll for Network Responses
-        if let Ok(response) = rx.try_recv() {
-            match response {
-                Ok(resp) => {
-                    app.response_status = Some(resp.status);
-                    app.response_text = Some(resp.body); // Headers? We can add a tab for that later
-                }
-                Err(err_msg) => {
-                    app.response_status = None;
-                    app.response_text = Some(format!("Error: {}", err_msg));
-                }
-            }
+        while let Ok(message) = rx.try_recv() {
+            app.finish_request(message);
         }
+        app.send_due_retry(&tx);
 
         if !app.running {
             return Ok(());
@@ -1,119 +1,462 @@
-use reqwest::{Method, Client};
-use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
-use std::str::FromStr;
-use anyhow::{Result, Context};
-use crate::app::HttpMethod;
-use crate::key_value::KeyValueEntries;
-use base64::{Engine as _, engine::general_purpose};
-
-#[derive(Debug)]
+use crate::auth::{digest_authorization, new_cnonce, parse_digest_challenge, AuthKind};
+use crate::cookies::{parse_set_cookie, SetCookie, SharedJar};
+use crate::jwt;
+use crate::oauth::{OAuth2Config, TokenCache};
+use crate::request::{BodyMode, RequestSpec};
+use crate::settings::RequestSettings;
+use crate::signing::{self, HmacConfig};
+use crate::sigv4::{self, AwsSigV4Config};
+use crate::timing::{ConnectProbe, Timing};
+use anyhow::{anyhow, Context, Result};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, AUTHORIZATION, WWW_AUTHENTICATE};
+use reqwest::{multipart, Client, Method, RequestBuilder, Response, StatusCode, Url};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Instant, SystemTime};
+
+#[derive(Debug, Clone)]
 pub struct ApiResponse {
     pub status: u16,
-    pub headers: String,
+    /// HTTP version the server answered with, e.g. `HTTP/1.1`
+    pub version: String,
+    /// URL of the final response, after any redirects
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    /// Body as text, pretty-printed when it is JSON
     pub body: String,
+    /// Body exactly as received
+    pub raw_body: Vec<u8>,
+    /// Size of the body in bytes, as received
+    pub size: usize,
+    pub timing: Timing,
 }
 
-pub async fn make_request(
-    method: HttpMethod,
-    url: String,
-    headers: &KeyValueEntries,
-    params: &KeyValueEntries,
-    auth: &KeyValueEntries,
-    body_str: String
-) -> Result<ApiResponse> {
-    let client = Client::new();
-    
-    let req_method = match method {
-        HttpMethod::GET => Method::GET,
-        HttpMethod::POST => Method::POST,
-        HttpMethod::PUT => Method::PUT,
-        HttpMethod::DELETE => Method::DELETE,
-        HttpMethod::PATCH => Method::PATCH,
-    };
+impl ApiResponse {
+    /// All values of a header, in the order the server sent them
+    pub fn header_values<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a str> {
+        self.headers
+            .iter()
+            .filter(move |(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Cookies set by this response
+    pub fn cookies(&self) -> Vec<SetCookie> {
+        self.header_values("set-cookie")
+            .filter_map(parse_set_cookie)
+            .collect()
+    }
+
+    /// Values a JSONPath query (RFC 9535) selects from a JSON body
+    pub fn json_path(&self, query: &str) -> Result<Vec<serde_json::Value>> {
+        let path = serde_json_path::JsonPath::parse(query)
+            .map_err(|e| anyhow!("Invalid JSONPath '{}': {}", query, e))?;
+        let body: serde_json::Value =
+            serde_json::from_slice(&self.raw_body).context("Body is not JSON")?;
+        Ok(path.query(&body).all().into_iter().cloned().collect())
+    }
+}
+
+/// A request body as it will be sent. Files are only read when sending.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PreparedBody {
+    Text(String),
+    File(PathBuf),
+    Multipart(Vec<(String, FormValue)>),
+}
+
+/// One `multipart/form-data` field
+#[derive(Debug, Clone, PartialEq)]
+pub enum FormValue {
+    Text(String),
+    File(PathBuf),
+}
+
+/// A request exactly as it will be sent: auth applied, query params folded
+/// into the URL and default headers added. Shared by `make_request` and the
+/// snippet exporter so both always agree.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PreparedRequest {
+    pub method: Method,
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Option<PreparedBody>,
+    /// Username and password for Digest auth, answered after a 401
+    pub digest: Option<(String, String)>,
+    /// AWS credentials to sign the request with just before it is sent
+    pub sigv4: Option<AwsSigV4Config>,
+    /// HMAC signature to compute over the request just before it is sent
+    pub hmac: Option<HmacConfig>,
+    /// OAuth 2.0 settings to fetch a bearer token with just before sending
+    pub oauth2: Option<OAuth2Config>,
+}
 
-    // Build headers from KeyValueEntries
-    let mut header_map = HeaderMap::new();
-    for entry in &headers.entries {
-        if entry.enabled {
+impl PreparedRequest {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Set a header, replacing any existing header with the same name
+    pub fn set_header(&mut self, name: &str, value: &str) {
+        self.headers.retain(|(k, _)| !k.eq_ignore_ascii_case(name));
+        self.headers.push((name.to_string(), value.to_string()));
+    }
+
+    pub fn header_map(&self) -> HeaderMap {
+        let mut header_map = HeaderMap::new();
+        for (name, value) in &self.headers {
             if let (Ok(hn), Ok(hv)) = (
-                HeaderName::from_str(entry.key.trim()),
-                HeaderValue::from_str(entry.value.trim())
+                HeaderName::from_bytes(name.as_bytes()),
+                HeaderValue::from_str(value),
             ) {
                 header_map.insert(hn, hv);
             }
         }
+        header_map
     }
+}
+
+/// Build the on-the-wire form of a request
+pub fn prepare_request(request: &RequestSpec) -> Result<PreparedRequest> {
+    let mut prepared = PreparedRequest {
+        method: request.method.to_reqwest()?,
+        url: request.url.clone(),
+        headers: Vec::new(),
+        body: None,
+        digest: None,
+        sigv4: None,
+        hmac: None,
+        oauth2: None,
+    };
 
-    // Build query params from KeyValueEntries
-    let mut query_params = Vec::new();
-    for entry in &params.entries {
-        if entry.enabled {
-            query_params.push((entry.key.clone(), entry.value.clone()));
+    // Headers from KeyValueEntries, skipping ones that are not valid HTTP
+    for entry in request.headers.entries.iter().filter(|e| e.enabled) {
+        let (key, value) = (entry.key.trim(), entry.value.trim());
+        if HeaderName::from_bytes(key.as_bytes()).is_ok() && HeaderValue::from_str(value).is_ok() {
+            prepared.set_header(key, value);
         }
     }
 
-    // Handle authorization - look for common auth patterns
-    for entry in &auth.entries {
-        if entry.enabled {
-            // Handle Bearer token
-            if entry.key.eq_ignore_ascii_case("Authorization") || entry.key.eq_ignore_ascii_case("Bearer") {
-                if let Ok(hv) = HeaderValue::from_str(&entry.value) {
-                    header_map.insert(reqwest::header::AUTHORIZATION, hv);
-                }
+    if let Some((name, value)) = request.auth.header() {
+        if HeaderName::from_bytes(name.as_bytes()).is_ok() && HeaderValue::from_str(&value).is_ok()
+        {
+            prepared.set_header(&name, &value);
+        }
+    }
+    if request.auth.kind == AuthKind::Digest {
+        prepared.digest = Some((request.auth.username.clone(), request.auth.password.clone()));
+    }
+    if request.auth.kind == AuthKind::AwsSigV4 {
+        prepared.sigv4 = Some(request.auth.aws.clone());
+    }
+    if request.auth.kind == AuthKind::Hmac {
+        prepared.hmac = Some(request.auth.hmac.clone());
+    }
+    if request.auth.kind == AuthKind::OAuth2 {
+        prepared.oauth2 = Some(request.auth.oauth2.clone());
+    }
+    if request.auth.kind == AuthKind::Jwt {
+        let token = jwt::encode(&request.auth.jwt)?;
+        prepared.set_header("Authorization", &format!("Bearer {}", token));
+    }
+
+    // Append query params to any query the URL already has
+    let pairs: Vec<(String, String)> = request
+        .params
+        .entries
+        .iter()
+        .filter(|e| e.enabled)
+        .map(|e| (e.key.clone(), e.value.clone()))
+        .chain(request.auth.query_param())
+        .collect();
+    if !pairs.is_empty() {
+        prepared.url = match Url::parse(&prepared.url) {
+            Ok(mut url) => {
+                url.query_pairs_mut().extend_pairs(&pairs);
+                url.to_string()
             }
-            // Handle API Key
-            else if entry.key.eq_ignore_ascii_case("API-Key") || entry.key.eq_ignore_ascii_case("X-API-Key") {
-                if let (Ok(hn), Ok(hv)) = (
-                    HeaderName::from_str(&entry.key),
-                    HeaderValue::from_str(&entry.value)
-                ) {
-                    header_map.insert(hn, hv);
-                }
+            // Not sendable as it is; keep the params visible for the error
+            Err(_) => {
+                let query = pairs
+                    .iter()
+                    .map(|(key, value)| {
+                        format!(
+                            "{}={}",
+                            urlencoding::encode(key),
+                            urlencoding::encode(value)
+                        )
+                    })
+                    .collect::<Vec<_>>()
+                    .join("&");
+                let separator = if prepared.url.contains('?') { '&' } else { '?' };
+                format!("{}{}{}", prepared.url, separator, query)
             }
-            // Handle username/password for Basic auth
-            else if entry.key.eq_ignore_ascii_case("username") {
-                // Look for password entry
-                if let Some(password_entry) = auth.entries.iter().find(|e| {
-                    e.enabled && e.key.eq_ignore_ascii_case("password")
-                }) {
-                    let credentials = format!("{}:{}", entry.value, password_entry.value);
-                    let encoded = general_purpose::STANDARD.encode(credentials.as_bytes());
-                    if let Ok(hv) = HeaderValue::from_str(&format!("Basic {}", encoded)) {
-                        header_map.insert(reqwest::header::AUTHORIZATION, hv);
+        };
+    }
+
+    let form_fields = || {
+        request
+            .form
+            .entries
+            .iter()
+            .filter(|e| e.enabled && !e.key.trim().is_empty())
+    };
+    let (body, content_type) = match request.body_mode {
+        BodyMode::Json | BodyMode::Text | BodyMode::Xml => {
+            let body =
+                (!request.body.trim().is_empty()).then(|| PreparedBody::Text(request.body.clone()));
+            let content_type = match request.body_mode {
+                BodyMode::Json => "application/json",
+                BodyMode::Xml => "application/xml",
+                _ => request.text_content_type.as_str(),
+            };
+            (body, Some(content_type))
+        }
+        BodyMode::FormUrlEncoded => {
+            let encoded = form_fields()
+                .map(|e| {
+                    format!(
+                        "{}={}",
+                        urlencoding::encode(e.key.trim()),
+                        urlencoding::encode(&e.value)
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join("&");
+            let body = (!encoded.is_empty()).then_some(PreparedBody::Text(encoded));
+            (body, Some("application/x-www-form-urlencoded"))
+        }
+        BodyMode::Multipart => {
+            let parts = form_fields()
+                .map(|e| {
+                    let value = match e.value.strip_prefix('@') {
+                        Some(path) => FormValue::File(PathBuf::from(path)),
+                        None => FormValue::Text(e.value.clone()),
+                    };
+                    (e.key.trim().to_string(), value)
+                })
+                .collect::<Vec<_>>();
+            // The client adds the Content-Type, as it has to carry the boundary
+            let body = (!parts.is_empty()).then_some(PreparedBody::Multipart(parts));
+            (body, None)
+        }
+        BodyMode::Binary => {
+            let path = request.body_file.trim();
+            let body = (!path.is_empty()).then(|| PreparedBody::File(PathBuf::from(path)));
+            (body, Some("application/octet-stream"))
+        }
+    };
+
+    // Never override a Content-Type the user set explicitly
+    if let (Some(_), Some(content_type)) = (&body, content_type) {
+        if prepared.header("Content-Type").is_none() {
+            prepared.set_header("Content-Type", content_type);
+        }
+    }
+    prepared.body = body;
+
+    Ok(prepared)
+}
+
+async fn read_file(path: &Path) -> Result<Vec<u8>> {
+    tokio::fs::read(path)
+        .await
+        .with_context(|| format!("Failed to read {}", path.display()))
+}
+
+/// A client with its DNS lookups and connections timed, plus the OAuth 2.0
+/// tokens it sends
+#[derive(Debug, Clone)]
+pub struct HttpClient {
+    client: Client,
+    tokens: TokenCache,
+}
+
+impl HttpClient {
+    pub fn new(settings: &RequestSettings, cookies: Option<SharedJar>) -> Result<Self> {
+        let client = ConnectProbe::install(settings.client_builder(cookies)?)
+            .build()
+            .context("Failed to build HTTP client")?;
+        Ok(Self {
+            client,
+            tokens: TokenCache::default(),
+        })
+    }
+}
+
+/// Clients kept between requests so connections and TLS sessions are
+/// reused. There is one client per distinct settings and cookie jar; all
+/// of them share one OAuth 2.0 token cache.
+#[derive(Debug, Default)]
+pub struct ClientPool {
+    clients: HashMap<(RequestSettings, Option<String>), HttpClient>,
+    tokens: TokenCache,
+}
+
+impl ClientPool {
+    /// The client for `settings`, storing cookies in the named jar. A
+    /// `fresh_connection` request gets a new client that is not kept.
+    pub fn client(
+        &mut self,
+        settings: &RequestSettings,
+        cookies: Option<(&str, SharedJar)>,
+    ) -> Result<HttpClient> {
+        let (name, jar) = cookies.unzip();
+        let new_client = |jar| {
+            HttpClient::new(settings, jar).map(|client| HttpClient {
+                tokens: self.tokens.clone(),
+                ..client
+            })
+        };
+        if settings.fresh_connection == Some(true) {
+            return new_client(jar);
+        }
+        let key = (settings.clone(), name.map(str::to_string));
+        if let Some(client) = self.clients.get(&key) {
+            return Ok(client.clone());
+        }
+        let client = new_client(jar)?;
+        self.clients.insert(key, client.clone());
+        Ok(client)
+    }
+
+    pub fn tokens(&self) -> &TokenCache {
+        &self.tokens
+    }
+}
+
+/// A reqwest request for `prepared`, with `headers` applied after the body
+/// so explicit headers replace the defaults it sets
+async fn build_request(
+    client: &Client,
+    prepared: &PreparedRequest,
+    headers: HeaderMap,
+) -> Result<RequestBuilder> {
+    let mut builder = client.request(prepared.method.clone(), &prepared.url);
+    match &prepared.body {
+        Some(PreparedBody::Text(text)) => builder = builder.body(text.clone()),
+        Some(PreparedBody::File(path)) => builder = builder.body(read_file(path).await?),
+        Some(PreparedBody::Multipart(fields)) => {
+            let mut form = multipart::Form::new();
+            for (name, value) in fields {
+                form = match value {
+                    FormValue::Text(text) => form.text(name.clone(), text.clone()),
+                    FormValue::File(path) => {
+                        let mut part = multipart::Part::bytes(read_file(path).await?);
+                        if let Some(file_name) = path.file_name() {
+                            part = part.file_name(file_name.to_string_lossy().into_owned());
+                        }
+                        form.part(name.clone(), part)
                     }
-                }
+                };
             }
+            builder = builder.multipart(form);
         }
+        None => {}
     }
+    Ok(builder.headers(headers))
+}
 
-    // Build URL with query params
-    let final_url = if query_params.is_empty() {
-        url
-    } else {
-        let query_string = query_params.iter()
-            .map(|(k, v)| format!("{}={}", 
-                urlencoding::encode(k), 
-                urlencoding::encode(v)))
-            .collect::<Vec<_>>()
-            .join("&");
-        format!("{}?{}", url, query_string)
-    };
+/// Build the request, sign it when its auth is a signature, and send it
+async fn send(
+    client: &HttpClient,
+    prepared: &PreparedRequest,
+    headers: HeaderMap,
+) -> Result<Response> {
+    let mut request = build_request(&client.client, prepared, headers)
+        .await?
+        .build()
+        .context("Failed to build request")?;
+    if let Some(aws) = &prepared.sigv4 {
+        sigv4::sign(&mut request, aws, SystemTime::now())?;
+    }
+    if let Some(hmac) = &prepared.hmac {
+        signing::sign(&mut request, hmac, SystemTime::now())?;
+    }
+    client
+        .client
+        .execute(request)
+        .await
+        .context("Failed to send request")
+}
+
+pub async fn make_request(client: &HttpClient, request: &RequestSpec) -> Result<ApiResponse> {
+    let prepared = prepare_request(request)?;
+    let mut headers = prepared.header_map();
+    let mut cached_token = false;
+    if prepared.oauth2.is_some() {
+        let (token, cached) = client
+            .tokens
+            .access_token(&client.client, &request.auth)
+            .await?;
+        headers.insert(AUTHORIZATION, bearer(&token)?);
+        cached_token = cached;
+    }
 
-    let mut builder = client.request(req_method, &final_url)
-        .headers(header_map);
-    
-    // For MVP, if there is body content, assume JSON and attach it.
-    if !body_str.trim().is_empty() {
-        builder = builder.header("Content-Type", "application/json")
-                         .body(body_str);
+    let probe = ConnectProbe::default();
+    let started = Instant::now();
+    let mut resp = probe
+        .track(send(client, &prepared, headers.clone()))
+        .await?;
+    // A cached token may have been revoked early; retry once with a new one
+    if cached_token && resp.status() == StatusCode::UNAUTHORIZED {
+        client.tokens.forget(&request.auth);
+        let (token, _) = client
+            .tokens
+            .access_token(&client.client, &request.auth)
+            .await?;
+        headers.insert(AUTHORIZATION, bearer(&token)?);
+        resp = probe.track(send(client, &prepared, headers)).await?;
+    }
+    if let Some((username, password)) = &prepared.digest {
+        let challenge = resp
+            .headers()
+            .get_all(WWW_AUTHENTICATE)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .find_map(parse_digest_challenge);
+        if let (StatusCode::UNAUTHORIZED, Some(challenge)) = (resp.status(), challenge) {
+            let url = Url::parse(&prepared.url)?;
+            let uri = match url.query() {
+                Some(query) => format!("{}?{}", url.path(), query),
+                None => url.path().to_string(),
+            };
+            let authorization = digest_authorization(
+                &challenge,
+                username,
+                password,
+                prepared.method.as_str(),
+                &uri,
+                &new_cnonce(),
+            )?;
+            let mut headers = prepared.header_map();
+            headers.insert(AUTHORIZATION, HeaderValue::from_str(&authorization)?);
+            resp = probe.track(send(client, &prepared, headers)).await?;
+        }
     }
+    let headers_received = Instant::now();
 
-    let resp = builder.send().await.context("Failed to send request")?;
-    
     let status = resp.status().as_u16();
-    let headers_text = format!("{:#?}", resp.headers()); // Debug print headers for now
-    
-    let body_text = resp.text().await.context("Failed to read response body")?;
+    let version = format!("{:?}", resp.version());
+    let final_url = resp.url().to_string();
+    let headers = resp
+        .headers()
+        .iter()
+        .map(|(name, value)| {
+            (
+                name.to_string(),
+                String::from_utf8_lossy(value.as_bytes()).into_owned(),
+            )
+        })
+        .collect();
+
+    let body_bytes = resp.bytes().await.context("Failed to read response body")?;
+    let finished = Instant::now();
+    let timing = probe.timing(started, headers_received, finished);
+    let body_text = String::from_utf8_lossy(&body_bytes).into_owned();
 
     // Try to prettify JSON
     let pretty_body = if let Ok(json) = serde_json::from_str::<serde_json::Value>(&body_text) {
@@ -124,7 +467,214 @@ pub async fn make_request(
 
     Ok(ApiResponse {
         status,
-        headers: headers_text,
+        version,
+        url: final_url,
+        headers,
         body: pretty_body,
+        raw_body: body_bytes.to_vec(),
+        size: body_bytes.len(),
+        timing,
     })
 }
+
+fn bearer(token: &str) -> Result<HeaderValue> {
+    HeaderValue::from_str(&format!("Bearer {}", token)).context("Invalid OAuth2 access token")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn with_body_mode(mode: BodyMode) -> RequestSpec {
+        RequestSpec {
+            body_mode: mode,
+            body: "<a>1</a>".to_string(),
+            ..RequestSpec::default()
+        }
+    }
+
+    #[test]
+    fn test_content_type_follows_body_mode() {
+        let xml = prepare_request(&with_body_mode(BodyMode::Xml)).unwrap();
+        assert_eq!(xml.header("content-type"), Some("application/xml"));
+        assert_eq!(xml.body, Some(PreparedBody::Text("<a>1</a>".to_string())));
+
+        let mut text = with_body_mode(BodyMode::Text);
+        text.text_content_type = "text/csv".to_string();
+        let text = prepare_request(&text).unwrap();
+        assert_eq!(text.header("Content-Type"), Some("text/csv"));
+
+        // An explicit header always wins
+        let mut explicit = with_body_mode(BodyMode::Json);
+        explicit.headers.add_entry(
+            "content-type".to_string(),
+            "application/vnd.api+json".to_string(),
+        );
+        let explicit = prepare_request(&explicit).unwrap();
+        assert_eq!(
+            explicit.header("Content-Type"),
+            Some("application/vnd.api+json")
+        );
+        assert_eq!(explicit.headers.len(), 1);
+    }
+
+    #[test]
+    fn test_query_params_extend_existing_query() {
+        let mut request = RequestSpec {
+            url: "https://api.test/search?q=rust".to_string(),
+            ..RequestSpec::default()
+        };
+        request
+            .params
+            .add_entry("page".to_string(), "2".to_string());
+        request.auth.kind = AuthKind::ApiKey;
+        request.auth.key_name = "api key".to_string();
+        request.auth.key_value = "a&b".to_string();
+        request.auth.key_location = crate::auth::ApiKeyLocation::Query;
+        let prepared = prepare_request(&request).unwrap();
+        assert_eq!(
+            prepared.url,
+            "https://api.test/search?q=rust&page=2&api+key=a%26b"
+        );
+
+        request.url = "https://api.test/search".to_string();
+        let prepared = prepare_request(&request).unwrap();
+        assert_eq!(prepared.url, "https://api.test/search?page=2&api+key=a%26b");
+    }
+
+    #[test]
+    fn test_form_body_modes() {
+        let mut request = with_body_mode(BodyMode::FormUrlEncoded);
+        request
+            .form
+            .add_entry("name".to_string(), "a b&c".to_string());
+        request
+            .form
+            .add_entry("file".to_string(), "@/tmp/x.png".to_string());
+        request.form.add_entry("off".to_string(), "1".to_string());
+        request.form.toggle_enabled(2);
+
+        let form = prepare_request(&request).unwrap();
+        assert_eq!(
+            form.body,
+            Some(PreparedBody::Text(
+                "name=a%20b%26c&file=%40%2Ftmp%2Fx.png".to_string()
+            ))
+        );
+        assert_eq!(
+            form.header("Content-Type"),
+            Some("application/x-www-form-urlencoded")
+        );
+
+        request.body_mode = BodyMode::Multipart;
+        let multipart = prepare_request(&request).unwrap();
+        assert_eq!(
+            multipart.body,
+            Some(PreparedBody::Multipart(vec![
+                ("name".to_string(), FormValue::Text("a b&c".to_string())),
+                (
+                    "file".to_string(),
+                    FormValue::File(PathBuf::from("/tmp/x.png"))
+                ),
+            ]))
+        );
+        // Left to the client, which knows the boundary
+        assert_eq!(multipart.header("Content-Type"), None);
+    }
+
+    /// Serve `ok` over HTTP/1.1 keep-alive, counting accepted connections
+    async fn counting_server() -> (String, std::sync::Arc<std::sync::atomic::AtomicUsize>) {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        let accepted = std::sync::Arc::new(AtomicUsize::new(0));
+        let counter = accepted.clone();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                counter.fetch_add(1, Ordering::SeqCst);
+                tokio::spawn(async move {
+                    let mut buf = Vec::new();
+                    let mut chunk = [0u8; 1024];
+                    while let Ok(n) = stream.read(&mut chunk).await {
+                        if n == 0 {
+                            break;
+                        }
+                        buf.extend_from_slice(&chunk[..n]);
+                        while let Some(end) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+                            buf.drain(..end + 4);
+                            let response = b"HTTP/1.1 200 OK\r\ncontent-length: 2\r\n\r\nok";
+                            if stream.write_all(response).await.is_err() {
+                                return;
+                            }
+                        }
+                    }
+                });
+            }
+        });
+        (url, accepted)
+    }
+
+    #[tokio::test]
+    async fn test_pool_reuses_connections() {
+        use std::sync::atomic::Ordering;
+
+        let (url, accepted) = counting_server().await;
+        let request = RequestSpec {
+            url,
+            ..RequestSpec::default()
+        };
+        let mut pool = ClientPool::default();
+        let settings = RequestSettings::default();
+        for _ in 0..3 {
+            let client = pool.client(&settings, None).unwrap();
+            assert_eq!(make_request(&client, &request).await.unwrap().body, "ok");
+        }
+        assert_eq!(accepted.load(Ordering::SeqCst), 1);
+
+        let fresh = RequestSettings {
+            fresh_connection: Some(true),
+            ..RequestSettings::default()
+        };
+        for _ in 0..2 {
+            let client = pool.client(&fresh, None).unwrap();
+            make_request(&client, &request).await.unwrap();
+        }
+        assert_eq!(accepted.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_timing_breakdown() {
+        let (url, _) = counting_server().await;
+        let request = RequestSpec {
+            url: url.replace("127.0.0.1", "localhost"),
+            ..RequestSpec::default()
+        };
+        let client = HttpClient::new(&RequestSettings::default(), None).unwrap();
+
+        let cold = make_request(&client, &request).await.unwrap().timing;
+        assert!(!cold.dns.is_zero());
+        assert!(!cold.connect.is_zero());
+        assert!(cold.tls_handshake.is_zero());
+        let phases: Duration = cold.phases().iter().map(|(_, _, d)| *d).sum();
+        assert_eq!(phases, cold.total);
+
+        let warm = make_request(&client, &request).await.unwrap().timing;
+        assert!(warm.dns.is_zero() && warm.connect.is_zero());
+
+        // Overlapping requests on a fresh client each see only their own
+        // connection
+        let client = HttpClient::new(&RequestSettings::default(), None).unwrap();
+        let (a, b) = tokio::join!(
+            make_request(&client, &request),
+            make_request(&client, &request)
+        );
+        for timing in [a.unwrap().timing, b.unwrap().timing] {
+            assert!(!timing.dns.is_zero() && !timing.connect.is_zero());
+            let phases: Duration = timing.phases().iter().map(|(_, _, d)| *d).sum();
+            assert_eq!(phases, timing.total);
+        }
+    }
+}
@@ -0,0 +1,605 @@
+use anyhow::{anyhow, bail, Context, Result};
+use base64::{engine::general_purpose, Engine as _};
+use reqwest::{Client, Url};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest as _, Sha256};
+use std::collections::HashMap;
+use std::process::{Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+use crate::auth::Auth;
+
+/// Redirect URI used for the authorization code flow when none is set
+pub const DEFAULT_REDIRECT_URI: &str = "http://127.0.0.1:8976/callback";
+
+/// Tokens this close to expiring are refreshed before use
+const EXPIRY_MARGIN: Duration = Duration::from_secs(30);
+
+/// How long to wait for the browser to come back to the redirect URI
+const AUTHORIZE_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// How an OAuth 2.0 access token is obtained
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OAuthGrant {
+    #[default]
+    ClientCredentials,
+    Password,
+    RefreshToken,
+    /// Sign in through the browser, with PKCE and a loopback redirect
+    AuthorizationCode,
+}
+
+impl OAuthGrant {
+    pub const ALL: [OAuthGrant; 4] = [
+        OAuthGrant::ClientCredentials,
+        OAuthGrant::Password,
+        OAuthGrant::RefreshToken,
+        OAuthGrant::AuthorizationCode,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            OAuthGrant::ClientCredentials => "client credentials",
+            OAuthGrant::Password => "password",
+            OAuthGrant::RefreshToken => "refresh token",
+            OAuthGrant::AuthorizationCode => "authorization code (PKCE)",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        let idx = Self::ALL.iter().position(|g| *g == self).unwrap_or(0);
+        Self::ALL[(idx + 1) % Self::ALL.len()]
+    }
+}
+
+/// OAuth 2.0 settings of a request; the password grant also uses the
+/// auth's username and password
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OAuth2Config {
+    pub grant: OAuthGrant,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub token_url: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub auth_url: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub client_id: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub client_secret: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub scope: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub refresh_token: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub redirect_uri: String,
+}
+
+impl OAuth2Config {
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// A token from the token endpoint
+#[derive(Debug, Clone)]
+struct Token {
+    access_token: String,
+    refresh_token: Option<String>,
+    expires_at: Option<Instant>,
+}
+
+impl Token {
+    fn is_fresh(&self) -> bool {
+        self.expires_at
+            .is_none_or(|expires_at| expires_at > Instant::now() + EXPIRY_MARGIN)
+    }
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    #[serde(default)]
+    refresh_token: Option<String>,
+    #[serde(default)]
+    expires_in: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct ErrorResponse {
+    error: String,
+    #[serde(default)]
+    error_description: Option<String>,
+}
+
+/// Access tokens shared by every request, keyed by the OAuth settings that
+/// produced them. Kept in memory only.
+#[derive(Debug, Clone, Default)]
+pub struct TokenCache(Arc<Mutex<HashMap<(OAuth2Config, String), Token>>>);
+
+impl TokenCache {
+    fn key(auth: &Auth) -> (OAuth2Config, String) {
+        (auth.oauth2.clone(), auth.username.clone())
+    }
+
+    fn get(&self, auth: &Auth) -> Option<Token> {
+        let tokens = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        tokens.get(&Self::key(auth)).cloned()
+    }
+
+    fn insert(&self, auth: &Auth, token: Token) {
+        let mut tokens = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        tokens.insert(Self::key(auth), token);
+    }
+
+    /// Drop the cached token, e.g. after the API rejected it
+    pub fn forget(&self, auth: &Auth) {
+        let mut tokens = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        tokens.remove(&Self::key(auth));
+    }
+
+    /// A line describing the cached token, for the Auth tab
+    pub fn status(&self, auth: &Auth) -> Option<String> {
+        let token = self.get(auth)?;
+        Some(match token.expires_at {
+            Some(expires_at) if expires_at <= Instant::now() => "Token expired".to_string(),
+            Some(expires_at) => format!(
+                "Token cached, expires in {}s",
+                (expires_at - Instant::now()).as_secs()
+            ),
+            None => "Token cached".to_string(),
+        })
+    }
+
+    /// An access token for `auth`: the cached one while it is fresh, else
+    /// one from the refresh token, else a new one. The flag is `true` when
+    /// the token came from the cache.
+    pub async fn access_token(&self, client: &Client, auth: &Auth) -> Result<(String, bool)> {
+        self.access_token_with(client, auth, &open_in_browser).await
+    }
+
+    async fn access_token_with(
+        &self,
+        client: &Client,
+        auth: &Auth,
+        open_url: &(dyn Fn(&str) -> Result<()> + Sync),
+    ) -> Result<(String, bool)> {
+        let cached = self.get(auth);
+        if let Some(token) = cached.as_ref().filter(|t| t.is_fresh()) {
+            return Ok((token.access_token.clone(), true));
+        }
+
+        let config = &auth.oauth2;
+        let refresh_token = cached.and_then(|t| t.refresh_token).or_else(|| {
+            (config.grant == OAuthGrant::RefreshToken).then(|| config.refresh_token.clone())
+        });
+        let token = match refresh_token {
+            Some(refresh_token) => {
+                match refresh(client, config, &refresh_token).await {
+                    Ok(token) => token,
+                    // A stale refresh token is expected; start over
+                    Err(_) if config.grant != OAuthGrant::RefreshToken => {
+                        fetch_token(client, auth, open_url).await?
+                    }
+                    Err(e) => return Err(e),
+                }
+            }
+            None => fetch_token(client, auth, open_url).await?,
+        };
+        self.insert(auth, token.clone());
+        Ok((token.access_token, false))
+    }
+}
+
+/// Get a new token with the configured grant
+async fn fetch_token(
+    client: &Client,
+    auth: &Auth,
+    open_url: &(dyn Fn(&str) -> Result<()> + Sync),
+) -> Result<Token> {
+    let config = &auth.oauth2;
+    match config.grant {
+        OAuthGrant::ClientCredentials => {
+            token_request(
+                client,
+                config,
+                vec![("grant_type", "client_credentials".into())],
+            )
+            .await
+        }
+        OAuthGrant::Password => {
+            let params = vec![
+                ("grant_type", "password".to_string()),
+                ("username", auth.username.clone()),
+                ("password", auth.password.clone()),
+            ];
+            token_request(client, config, params).await
+        }
+        OAuthGrant::RefreshToken => refresh(client, config, &config.refresh_token).await,
+        OAuthGrant::AuthorizationCode => authorization_code(client, config, open_url).await,
+    }
+}
+
+async fn refresh(client: &Client, config: &OAuth2Config, refresh_token: &str) -> Result<Token> {
+    if refresh_token.trim().is_empty() {
+        bail!("OAuth2: no refresh token set");
+    }
+    let params = vec![
+        ("grant_type", "refresh_token".to_string()),
+        ("refresh_token", refresh_token.to_string()),
+    ];
+    let mut token = token_request(client, config, params).await?;
+    // Servers that do not rotate refresh tokens leave the old one valid
+    token
+        .refresh_token
+        .get_or_insert_with(|| refresh_token.to_string());
+    Ok(token)
+}
+
+/// POST to the token endpoint, sending the client credentials in the body
+async fn token_request(
+    client: &Client,
+    config: &OAuth2Config,
+    mut params: Vec<(&str, String)>,
+) -> Result<Token> {
+    if config.token_url.trim().is_empty() {
+        bail!("OAuth2: the token URL is not set");
+    }
+    params.push(("client_id", config.client_id.clone()));
+    if !config.client_secret.is_empty() {
+        params.push(("client_secret", config.client_secret.clone()));
+    }
+    if !config.scope.is_empty() {
+        params.push(("scope", config.scope.clone()));
+    }
+    let body = params
+        .iter()
+        .map(|(k, v)| format!("{}={}", urlencoding::encode(k), urlencoding::encode(v)))
+        .collect::<Vec<_>>()
+        .join("&");
+
+    let response = client
+        .post(config.token_url.trim())
+        .header("Content-Type", "application/x-www-form-urlencoded")
+        .header("Accept", "application/json")
+        .body(body)
+        .send()
+        .await
+        .context("OAuth2: failed to reach the token endpoint")?;
+    let status = response.status();
+    let text = response.text().await?;
+    if !status.is_success() {
+        return Err(match serde_json::from_str::<ErrorResponse>(&text) {
+            Ok(error) => anyhow!(
+                "OAuth2: token request failed with {}: {}{}",
+                status,
+                error.error,
+                error
+                    .error_description
+                    .map(|d| format!(" ({})", d))
+                    .unwrap_or_default()
+            ),
+            Err(_) => anyhow!("OAuth2: token request failed with {}: {}", status, text),
+        });
+    }
+    let token: TokenResponse =
+        serde_json::from_str(&text).context("OAuth2: unexpected token response")?;
+    Ok(Token {
+        access_token: token.access_token,
+        refresh_token: token.refresh_token,
+        expires_at: token
+            .expires_in
+            .map(|secs| Instant::now() + Duration::from_secs(secs)),
+    })
+}
+
+/// Random URL-safe text, for the PKCE verifier and `state`
+fn random_token() -> String {
+    let mut bytes = [0u8; 32];
+    // Fall back to the clock, which is still unique per attempt
+    if getrandom::fill(&mut bytes).is_err() {
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos();
+        bytes = Sha256::digest(nanos.to_le_bytes()).into();
+    }
+    general_purpose::URL_SAFE_NO_PAD.encode(bytes)
+}
+
+/// The S256 PKCE challenge for a verifier (RFC 7636)
+pub fn pkce_challenge(verifier: &str) -> String {
+    general_purpose::URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes()))
+}
+
+/// Send the user to the authorization URL and swap the code that comes
+/// back on the loopback redirect for a token
+async fn authorization_code(
+    client: &Client,
+    config: &OAuth2Config,
+    open_url: &(dyn Fn(&str) -> Result<()> + Sync),
+) -> Result<Token> {
+    let redirect_uri = match config.redirect_uri.trim() {
+        "" => DEFAULT_REDIRECT_URI,
+        uri => uri,
+    };
+    let redirect = Url::parse(redirect_uri).context("OAuth2: invalid redirect URI")?;
+    let host = redirect.host_str().unwrap_or("127.0.0.1");
+    let port = redirect.port_or_known_default().unwrap_or(80);
+    let listener = TcpListener::bind((host, port))
+        .await
+        .with_context(|| format!("OAuth2: cannot listen on {}:{}", host, port))?;
+
+    let verifier = random_token();
+    let state = random_token();
+    let mut auth_url =
+        Url::parse(config.auth_url.trim()).context("OAuth2: invalid authorization URL")?;
+    {
+        let mut query = auth_url.query_pairs_mut();
+        query
+            .append_pair("response_type", "code")
+            .append_pair("client_id", &config.client_id)
+            .append_pair("redirect_uri", redirect_uri)
+            .append_pair("state", &state)
+            .append_pair("code_challenge", &pkce_challenge(&verifier))
+            .append_pair("code_challenge_method", "S256");
+        if !config.scope.is_empty() {
+            query.append_pair("scope", &config.scope);
+        }
+    }
+    open_url(auth_url.as_str())?;
+
+    let code = tokio::time::timeout(
+        AUTHORIZE_TIMEOUT,
+        wait_for_code(&listener, redirect.path(), &state),
+    )
+    .await
+    .map_err(|_| anyhow!("OAuth2: timed out waiting for the browser to sign in"))??;
+
+    let params = vec![
+        ("grant_type", "authorization_code".to_string()),
+        ("code", code),
+        ("redirect_uri", redirect_uri.to_string()),
+        ("code_verifier", verifier),
+    ];
+    token_request(client, config, params).await
+}
+
+/// Serve the redirect URI until the authorization server sends the browser
+/// back with a code (or an error)
+async fn wait_for_code(listener: &TcpListener, path: &str, state: &str) -> Result<String> {
+    loop {
+        let (mut stream, _) = listener.accept().await?;
+        let mut buf = vec![0u8; 8192];
+        let n = stream.read(&mut buf).await?;
+        let request = String::from_utf8_lossy(&buf[..n]);
+        let target = request.split_whitespace().nth(1).unwrap_or("/");
+        let Ok(url) = Url::parse(&format!("http://localhost{}", target)) else {
+            continue;
+        };
+        if url.path() != path {
+            let _ = stream
+                .write_all(b"HTTP/1.1 404 Not Found\r\ncontent-length: 0\r\n\r\n")
+                .await;
+            continue;
+        }
+
+        let params: HashMap<_, _> = url.query_pairs().into_owned().collect();
+        let result = if let Some(error) = params.get("error") {
+            Err(anyhow!("OAuth2: authorization failed: {}", error))
+        } else if params.get("state").map(String::as_str) != Some(state) {
+            Err(anyhow!("OAuth2: the redirect's state does not match"))
+        } else {
+            params
+                .get("code")
+                .cloned()
+                .ok_or_else(|| anyhow!("OAuth2: the redirect has no code"))
+        };
+        let page = match &result {
+            Ok(_) => "Signed in. You can close this window and return to the terminal.",
+            Err(_) => "Sign-in failed. See the terminal for details.",
+        };
+        let response = format!(
+            "HTTP/1.1 200 OK\r\ncontent-type: text/plain; charset=utf-8\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+            page.len(),
+            page
+        );
+        let _ = stream.write_all(response.as_bytes()).await;
+        return result;
+    }
+}
+
+/// Open a URL with the desktop's default browser
+fn open_in_browser(url: &str) -> Result<()> {
+    let mut command = if cfg!(target_os = "macos") {
+        Command::new("open")
+    } else if cfg!(windows) {
+        let mut command = Command::new("cmd");
+        command.args(["/C", "start", ""]);
+        command
+    } else {
+        Command::new("xdg-open")
+    };
+    command
+        .arg(url)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .with_context(|| format!("OAuth2: could not open a browser for {}", url))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::AuthKind;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// A token endpoint that hands out numbered tokens valid for
+    /// `expires_in` seconds and records each request body
+    async fn mock_token_endpoint(expires_in: u64) -> (String, Arc<Mutex<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/token", listener.local_addr().unwrap());
+        let bodies = Arc::new(Mutex::new(Vec::new()));
+        let seen = bodies.clone();
+        let issued = Arc::new(AtomicUsize::new(0));
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut buf = vec![0u8; 8192];
+                let n = stream.read(&mut buf).await.unwrap();
+                let request = String::from_utf8_lossy(&buf[..n]).into_owned();
+                let body = request.split("\r\n\r\n").nth(1).unwrap_or("").to_string();
+                let (status, json) = if body.contains("refresh_token=revoked") {
+                    (
+                        "400 Bad Request",
+                        r#"{"error": "invalid_grant"}"#.to_string(),
+                    )
+                } else {
+                    let n = issued.fetch_add(1, Ordering::SeqCst) + 1;
+                    (
+                        "200 OK",
+                        format!(
+                            r#"{{"access_token": "token-{}", "token_type": "Bearer", "expires_in": {}, "refresh_token": "refresh-{}"}}"#,
+                            n, expires_in, n
+                        ),
+                    )
+                };
+                seen.lock().unwrap().push(body);
+                let response = format!(
+                    "HTTP/1.1 {}\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                    status,
+                    json.len(),
+                    json
+                );
+                stream.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+        (url, bodies)
+    }
+
+    fn oauth(grant: OAuthGrant, token_url: &str) -> Auth {
+        Auth {
+            kind: AuthKind::OAuth2,
+            username: "alice".to_string(),
+            password: "pw".to_string(),
+            oauth2: OAuth2Config {
+                grant,
+                token_url: token_url.to_string(),
+                client_id: "app".to_string(),
+                client_secret: "s3cret".to_string(),
+                scope: "read write".to_string(),
+                ..OAuth2Config::default()
+            },
+            ..Auth::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_caches_and_refreshes_tokens() {
+        let (url, bodies) = mock_token_endpoint(3600).await;
+        let client = Client::new();
+        let cache = TokenCache::default();
+        let auth = oauth(OAuthGrant::ClientCredentials, &url);
+
+        assert_eq!(
+            cache.access_token(&client, &auth).await.unwrap(),
+            ("token-1".to_string(), false)
+        );
+        assert_eq!(
+            cache.access_token(&client, &auth).await.unwrap(),
+            ("token-1".to_string(), true)
+        );
+        assert_eq!(
+            bodies.lock().unwrap()[0],
+            "grant_type=client_credentials&client_id=app&client_secret=s3cret&scope=read%20write"
+        );
+
+        // An expiring token is refreshed with its refresh token
+        cache
+            .0
+            .lock()
+            .unwrap()
+            .values_mut()
+            .for_each(|t| t.expires_at = Some(Instant::now()));
+        assert_eq!(
+            cache.access_token(&client, &auth).await.unwrap().0,
+            "token-2"
+        );
+        assert!(bodies.lock().unwrap()[1]
+            .starts_with("grant_type=refresh_token&refresh_token=refresh-1&"));
+
+        // The password grant sends the user's credentials
+        let password = oauth(OAuthGrant::Password, &url);
+        cache.access_token(&client, &password).await.unwrap();
+        assert!(bodies.lock().unwrap()[2]
+            .starts_with("grant_type=password&username=alice&password=pw&"));
+
+        let mut revoked = oauth(OAuthGrant::RefreshToken, &url);
+        revoked.oauth2.refresh_token = "revoked".to_string();
+        let err = cache.access_token(&client, &revoked).await.unwrap_err();
+        assert!(err.to_string().contains("invalid_grant"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn test_authorization_code_with_pkce() {
+        let (url, bodies) = mock_token_endpoint(3600).await;
+        let client = Client::new();
+        let mut auth = oauth(OAuthGrant::AuthorizationCode, &url);
+        auth.oauth2.auth_url = "https://login.example.com/authorize".to_string();
+        // Any free port will do for the test's redirect listener
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        auth.oauth2.redirect_uri = format!("http://127.0.0.1:{}/cb", port);
+
+        // Stand in for the browser: follow the authorization URL's redirect
+        let challenge = Arc::new(Mutex::new(String::new()));
+        let seen_challenge = challenge.clone();
+        let browser = move |url: &str| -> Result<()> {
+            let url = Url::parse(url)?;
+            let params: HashMap<_, _> = url.query_pairs().into_owned().collect();
+            *seen_challenge.lock().unwrap() = params["code_challenge"].clone();
+            assert_eq!(params["code_challenge_method"], "S256");
+            let redirect = format!(
+                "{}?code=abc&state={}",
+                params["redirect_uri"], params["state"]
+            );
+            tokio::spawn(async move { reqwest::get(redirect).await.unwrap() });
+            Ok(())
+        };
+
+        let cache = TokenCache::default();
+        let (token, _) = cache
+            .access_token_with(&client, &auth, &browser)
+            .await
+            .unwrap();
+        assert_eq!(token, "token-1");
+
+        let body = bodies.lock().unwrap()[0].clone();
+        let params: HashMap<_, _> = Url::parse(&format!("http://localhost/?{}", body))
+            .unwrap()
+            .query_pairs()
+            .into_owned()
+            .collect();
+        assert_eq!(params["grant_type"], "authorization_code");
+        assert_eq!(params["code"], "abc");
+        assert_eq!(
+            pkce_challenge(&params["code_verifier"]),
+            *challenge.lock().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_pkce_challenge_rfc_example() {
+        // RFC 7636 appendix B
+        assert_eq!(
+            pkce_challenge("dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk"),
+            "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM"
+        );
+    }
+}
@@ -0,0 +1,135 @@
+use serde::{Deserialize, Serialize};
+
+use crate::app::HttpMethod;
+use crate::auth::Auth;
+use crate::key_value::KeyValueEntries;
+
+/// How the request body is edited and encoded
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BodyMode {
+    #[default]
+    Json,
+    /// Raw text sent with `text_content_type`
+    Text,
+    Xml,
+    /// `application/x-www-form-urlencoded` built from the form table
+    FormUrlEncoded,
+    /// `multipart/form-data` built from the form table; values starting
+    /// with `@` are read from files
+    Multipart,
+    /// The contents of `body_file`, sent as-is
+    Binary,
+}
+
+impl BodyMode {
+    pub const ALL: [BodyMode; 6] = [
+        BodyMode::Json,
+        BodyMode::Text,
+        BodyMode::Xml,
+        BodyMode::FormUrlEncoded,
+        BodyMode::Multipart,
+        BodyMode::Binary,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            BodyMode::Json => "JSON",
+            BodyMode::Text => "Text",
+            BodyMode::Xml => "XML",
+            BodyMode::FormUrlEncoded => "Form URL-encoded",
+            BodyMode::Multipart => "Multipart",
+            BodyMode::Binary => "File",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        let idx = Self::ALL.iter().position(|m| *m == self).unwrap_or(0);
+        Self::ALL[(idx + 1) % Self::ALL.len()]
+    }
+
+    /// Whether the body is edited as a key-value table
+    pub fn uses_form(self) -> bool {
+        matches!(self, BodyMode::FormUrlEncoded | BodyMode::Multipart)
+    }
+}
+
+/// Content types offered for `BodyMode::Text`; any other type can be set
+/// with an explicit `Content-Type` header
+pub const TEXT_CONTENT_TYPES: [&str; 5] = [
+    "text/plain",
+    "text/html",
+    "text/csv",
+    "application/javascript",
+    "application/yaml",
+];
+
+/// Everything needed to send a request, without any editor state.
+///
+/// This is the shape shared by history entries and saved requests.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RequestSpec {
+    pub method: HttpMethod,
+    pub url: String,
+    #[serde(default)]
+    pub headers: KeyValueEntries,
+    #[serde(default)]
+    pub params: KeyValueEntries,
+    /// Older files stored a key-value table here, converted on load
+    #[serde(default, deserialize_with = "crate::auth::deserialize")]
+    pub auth: Auth,
+    /// Per-request overrides of the client settings, see `RequestSettings`
+    #[serde(default)]
+    pub settings: KeyValueEntries,
+    /// Assertions checked against each response, see `assertions`
+    #[serde(default)]
+    pub tests: KeyValueEntries,
+    /// Variables set from each response, see `captures`
+    #[serde(default)]
+    pub captures: KeyValueEntries,
+    /// Rhai script run before sending, see `scripts`
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub pre_request_script: String,
+    /// Rhai script run on each response
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub post_response_script: String,
+    #[serde(default)]
+    pub body_mode: BodyMode,
+    #[serde(default)]
+    pub body: String,
+    /// Fields for the form body modes
+    #[serde(default)]
+    pub form: KeyValueEntries,
+    /// Path of the file sent in `BodyMode::Binary`
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub body_file: String,
+    /// Content type for `BodyMode::Text`
+    #[serde(default = "default_text_content_type")]
+    pub text_content_type: String,
+}
+
+fn default_text_content_type() -> String {
+    TEXT_CONTENT_TYPES[0].to_string()
+}
+
+impl Default for RequestSpec {
+    fn default() -> Self {
+        Self {
+            method: HttpMethod::GET,
+            url: String::new(),
+            headers: KeyValueEntries::new(),
+            params: KeyValueEntries::new(),
+            auth: Auth::default(),
+            settings: KeyValueEntries::new(),
+            tests: KeyValueEntries::new(),
+            captures: KeyValueEntries::new(),
+            pre_request_script: String::new(),
+            post_response_script: String::new(),
+            body_mode: BodyMode::Json,
+            body: String::new(),
+            form: KeyValueEntries::new(),
+            body_file: String::new(),
+            text_content_type: default_text_content_type(),
+        }
+    }
+}
@@ -0,0 +1,433 @@
+use serde_json::{json, Value};
+use std::fmt::Write as _;
+use std::time::{Duration, Instant};
+
+use crate::assertions::TestResult;
+use crate::collection::Folder;
+use crate::request::RequestSpec;
+use crate::workspace::Workspace;
+
+/// The outcome of sending one saved request during a run
+#[derive(Debug, Clone, PartialEq)]
+pub struct RequestRun {
+    /// `/`-separated path of the request within the folder that was run
+    pub name: String,
+    pub method: String,
+    /// URL with variables substituted, once they could be
+    pub url: String,
+    pub status: Option<u16>,
+    /// Time spent sending, summed over every attempt
+    pub time: Duration,
+    /// Why there is no response to check: an undefined variable, a
+    /// connection error, ...
+    pub error: Option<String>,
+    pub tests: Vec<TestResult>,
+}
+
+impl RequestRun {
+    pub fn passed(&self) -> bool {
+        self.error.is_none() && self.tests.iter().all(|t| t.passed)
+    }
+
+    fn failures(&self) -> impl Iterator<Item = &TestResult> {
+        self.tests.iter().filter(|t| !t.passed)
+    }
+}
+
+/// Every request of a folder, sent in order
+#[derive(Debug, Clone, PartialEq)]
+pub struct RunReport {
+    /// The folder that was run, as given on the command line
+    pub suite: String,
+    pub environment: Option<String>,
+    pub runs: Vec<RequestRun>,
+    pub time: Duration,
+}
+
+/// Send every request in `folder` and its subfolders one after another,
+/// checking each response against the request's Tests tab
+pub async fn run_folder(workspace: &mut Workspace, suite: &str, folder: &Folder) -> RunReport {
+    let started = Instant::now();
+    let mut runs = Vec::new();
+    for (name, saved) in folder.requests() {
+        runs.push(run_request(workspace, name, &saved.request).await);
+    }
+    RunReport {
+        suite: suite.to_string(),
+        environment: workspace.environments.active().map(|env| env.name.clone()),
+        runs,
+        time: started.elapsed(),
+    }
+}
+
+async fn run_request(workspace: &mut Workspace, name: String, request: &RequestSpec) -> RequestRun {
+    let started = Instant::now();
+    let mut run = RequestRun {
+        name,
+        method: request.method.to_string(),
+        url: request.url.clone(),
+        status: None,
+        time: Duration::ZERO,
+        error: None,
+        tests: Vec::new(),
+    };
+    let sent = match workspace.prepare(request) {
+        Ok((prepared, client)) => {
+            run.url = prepared.url.clone();
+            // Captured values are in place before the next request resolves
+            workspace.exchange(request, prepared, client).await
+        }
+        Err(e) => Err(e),
+    };
+    match sent {
+        Ok(exchange) => {
+            run.url = exchange.request.url;
+            run.status = Some(exchange.response.status);
+            run.time = exchange.time;
+            run.tests = exchange.checks.tests;
+        }
+        Err(e) => {
+            run.time = started.elapsed();
+            run.error = Some(format!("{:#}", e));
+        }
+    }
+    run
+}
+
+impl RunReport {
+    pub fn passed(&self) -> usize {
+        self.runs.iter().filter(|run| run.passed()).count()
+    }
+
+    /// Requests that got a response which failed a test
+    pub fn failed(&self) -> usize {
+        self.runs
+            .iter()
+            .filter(|run| run.error.is_none() && !run.passed())
+            .count()
+    }
+
+    /// Requests that could not be sent
+    pub fn errors(&self) -> usize {
+        self.runs.iter().filter(|run| run.error.is_some()).count()
+    }
+
+    /// A table of the requests, with failed tests and errors under their
+    /// request, and a closing count
+    pub fn summary(&self) -> String {
+        let name_width = self
+            .runs
+            .iter()
+            .map(|run| run.name.chars().count())
+            .chain([7])
+            .max()
+            .unwrap_or(0);
+        let mut out = String::new();
+        let _ = writeln!(
+            out,
+            "  {:<name_width$}  {:<7}  {:<6}  {:>8}  Tests",
+            "Request", "Method", "Status", "Time"
+        );
+        for run in &self.runs {
+            let mark = if run.passed() { "✓" } else { "✗" };
+            let status = run.status.map_or("-".to_string(), |s| s.to_string());
+            let tests = match run.tests.len() {
+                0 => "-".to_string(),
+                total => format!("{}/{}", total - run.failures().count(), total),
+            };
+            let _ = writeln!(
+                out,
+                "{} {:<name_width$}  {:<7}  {:<6}  {:>5} ms  {}",
+                mark,
+                run.name,
+                run.method,
+                status,
+                run.time.as_millis(),
+                tests
+            );
+            for failure in run.failures() {
+                let _ = writeln!(out, "    ✗ {}  {}", failure.name, failure.message);
+            }
+            if let Some(error) = &run.error {
+                let _ = writeln!(out, "    error: {}", error);
+            }
+        }
+        let _ = writeln!(
+            out,
+            "\n{} requests: {} passed, {} failed, {} errors in {:.2}s",
+            self.runs.len(),
+            self.passed(),
+            self.failed(),
+            self.errors(),
+            self.time.as_secs_f64()
+        );
+        out
+    }
+
+    /// A JUnit XML report with one test case per request; failed tests
+    /// become a `<failure>` and requests that could not be sent an `<error>`
+    pub fn junit_xml(&self) -> String {
+        let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        let counts = format!(
+            "tests=\"{}\" failures=\"{}\" errors=\"{}\" time=\"{:.3}\"",
+            self.runs.len(),
+            self.failed(),
+            self.errors(),
+            self.time.as_secs_f64()
+        );
+        let suite = xml_escape(&self.suite);
+        let _ = writeln!(out, "<testsuites name=\"{}\" {}>", suite, counts);
+        let _ = writeln!(out, "  <testsuite name=\"{}\" {}>", suite, counts);
+        for run in &self.runs {
+            let _ = write!(
+                out,
+                "    <testcase classname=\"{}\" name=\"{}\" time=\"{:.3}\"",
+                suite,
+                xml_escape(&run.name),
+                run.time.as_secs_f64()
+            );
+            let failures: Vec<_> = run.failures().collect();
+            if let Some(error) = &run.error {
+                let _ = writeln!(out, ">");
+                let _ = writeln!(out, "      <error message=\"{}\"/>", xml_escape(error));
+                let _ = writeln!(out, "    </testcase>");
+            } else if let Some(first) = failures.first() {
+                let details: Vec<_> = failures
+                    .iter()
+                    .map(|t| format!("{}: {}", t.name, t.message))
+                    .collect();
+                let _ = writeln!(out, ">");
+                let _ = writeln!(
+                    out,
+                    "      <failure message=\"{}\" type=\"assertion\">{}</failure>",
+                    xml_escape(&format!("{}: {}", first.name, first.message)),
+                    xml_escape(&details.join("\n"))
+                );
+                let _ = writeln!(out, "    </testcase>");
+            } else {
+                let _ = writeln!(out, "/>");
+            }
+        }
+        let _ = writeln!(out, "  </testsuite>");
+        let _ = writeln!(out, "</testsuites>");
+        out
+    }
+
+    /// The whole run as JSON, for tools that want more than JUnit carries
+    pub fn to_json(&self) -> Value {
+        let requests: Vec<_> = self
+            .runs
+            .iter()
+            .map(|run| {
+                json!({
+                    "name": run.name,
+                    "method": run.method,
+                    "url": run.url,
+                    "status": run.status,
+                    "time_ms": run.time.as_millis() as u64,
+                    "passed": run.passed(),
+                    "error": run.error,
+                    "tests": run.tests,
+                })
+            })
+            .collect();
+        json!({
+            "suite": self.suite,
+            "environment": self.environment,
+            "time_ms": self.time.as_millis() as u64,
+            "summary": {
+                "requests": self.runs.len(),
+                "passed": self.passed(),
+                "failed": self.failed(),
+                "errors": self.errors(),
+            },
+            "requests": requests,
+        })
+    }
+}
+
+fn xml_escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            '\n' => out.push_str("&#10;"),
+            // Not allowed in XML 1.0 at all
+            c if c.is_control() && c != '\t' && c != '\r' => {}
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collection::SavedRequest;
+    use crate::environment::EnvironmentStore;
+    use crate::key_value::KeyValueEntries;
+
+    /// Serve canned `(path, status, body)` responses, one request per
+    /// connection
+    async fn mock_server(routes: &'static [(&'static str, u16, &'static str)]) -> String {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let mut buf = Vec::new();
+                    let mut chunk = [0u8; 1024];
+                    while !buf.windows(4).any(|w| w == b"\r\n\r\n") {
+                        match stream.read(&mut chunk).await {
+                            Ok(0) | Err(_) => return,
+                            Ok(n) => buf.extend_from_slice(&chunk[..n]),
+                        }
+                    }
+                    let head = String::from_utf8_lossy(&buf);
+                    let path = head.split(' ').nth(1).unwrap_or_default();
+                    let (status, body) = routes
+                        .iter()
+                        .find(|(route, _, _)| *route == path)
+                        .map_or((404, "{}"), |(_, status, body)| (*status, *body));
+                    let response = format!(
+                        "HTTP/1.1 {} X\r\ncontent-type: application/json\r\n\
+                         content-length: {}\r\nconnection: close\r\n\r\n{}",
+                        status,
+                        body.len(),
+                        body
+                    );
+                    let _ = stream.write_all(response.as_bytes()).await;
+                });
+            }
+        });
+        url
+    }
+
+    fn saved(name: &str, url: &str, tests: &[(&str, &str)]) -> SavedRequest {
+        let mut entries = KeyValueEntries::new();
+        for (key, value) in tests {
+            entries.add_entry(key.to_string(), value.to_string());
+        }
+        SavedRequest {
+            name: name.to_string(),
+            request: RequestSpec {
+                url: url.to_string(),
+                tests: entries,
+                ..RequestSpec::default()
+            },
+        }
+    }
+
+    #[tokio::test]
+    async fn test_runs_folder_against_mock_server() {
+        let base = mock_server(&[("/users", 200, r#"{"count": 2}"#)]).await;
+        let mut root = Folder::new("Collection");
+        let users = root.add_folder(&[], "users").unwrap();
+        let mut list = saved(
+            "list",
+            "{{base}}/users",
+            &[("status", "200"), ("$.count", "== 2")],
+        );
+        list.request
+            .captures
+            .add_entry("count".to_string(), "$.count".to_string());
+        root.save_request(&users, list).unwrap();
+        root.save_request(
+            &users,
+            saved("missing", "{{base}}/nope/{{count}}", &[("status", "200")]),
+        )
+        .unwrap();
+        root.save_request(&[], saved("broken", "{{undefined}}/x", &[]))
+            .unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let mut workspace = Workspace::detached();
+        workspace.environments = EnvironmentStore::with_active(dir.path(), &[("base", &base)]);
+
+        let report = run_folder(&mut workspace, "Collection", &root).await;
+        let names: Vec<_> = report.runs.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, ["users/list", "users/missing", "broken"]);
+        assert_eq!(report.runs[0].url, format!("{}/users", base));
+        assert!(report.runs[0].passed());
+        // Sent with the value captured from the first response
+        assert_eq!(report.runs[1].url, format!("{}/nope/2", base));
+        assert_eq!(report.runs[1].status, Some(404));
+        assert_eq!(report.runs[1].tests[0].message, "got 404");
+        assert!(report.runs[2]
+            .error
+            .as_deref()
+            .unwrap()
+            .contains("undefined"));
+        assert_eq!(
+            (report.passed(), report.failed(), report.errors()),
+            (1, 1, 1)
+        );
+
+        let summary = report.summary();
+        assert!(summary.contains("✗ users/missing"));
+        assert!(summary.contains("    ✗ status 200  got 404"));
+        assert!(summary.contains("3 requests: 1 passed, 1 failed, 1 errors"));
+
+        let xml = report.junit_xml();
+        assert!(
+            xml.contains("<testsuite name=\"Collection\" tests=\"3\" failures=\"1\" errors=\"1\"")
+        );
+        assert!(xml.contains("name=\"users/list\""));
+        assert!(xml.contains("<failure message=\"status 200: got 404\" type=\"assertion\">"));
+        assert!(xml.contains("<error message=\""));
+
+        let json = report.to_json();
+        assert_eq!(json["environment"], "local");
+        assert_eq!(json["summary"]["failed"], 1);
+        assert_eq!(json["requests"][0]["tests"][1]["name"], "$.count == 2");
+        assert_eq!(json["requests"][1]["status"], 404);
+    }
+
+    #[tokio::test]
+    async fn test_post_response_script_retries() {
+        let base = mock_server(&[("/job/2", 200, r#"{"state": "done"}"#)]).await;
+        let mut root = Folder::new("Collection");
+        let mut poll = saved("poll", &format!("{}/job/{{{{polls}}}}", base), &[]);
+        poll.request.pre_request_script = r#"
+            let runs = get_var("pre_runs");
+            set_var("pre_runs", if runs == () { 1 } else { parse_int(runs) + 1 });
+        "#
+        .to_string();
+        poll.request.post_response_script = r#"
+            let polls = parse_int(get_var("polls")) + 1;
+            set_var("polls", polls);
+            if polls < 3 { retry(0); }
+            test("polled three times", polls == 3);
+        "#
+        .to_string();
+        root.save_request(&[], poll).unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let mut workspace = Workspace::detached();
+        workspace.environments = EnvironmentStore::with_active(dir.path(), &[("polls", "0")]);
+
+        let report = run_folder(&mut workspace, "Collection", &root).await;
+        let run = &report.runs[0];
+        assert!(run.passed(), "{:?}", run);
+        assert_eq!(run.tests.len(), 1);
+        // Each attempt ran the pre-request script and resolved the URL again
+        assert_eq!(run.url, format!("{}/job/2", base));
+        let variables = workspace.environments.active().unwrap().variable_map();
+        assert_eq!(variables["polls"], "3");
+        assert_eq!(variables["pre_runs"], "3");
+    }
+
+    #[test]
+    fn test_xml_escape() {
+        assert_eq!(
+            xml_escape("a<b & \"c\"\n'd'\u{1}"),
+            "a&lt;b &amp; &quot;c&quot;&#10;&apos;d&apos;"
+        );
+    }
+}
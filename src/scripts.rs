@@ -0,0 +1,607 @@
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose, Engine as _};
+use hmac::{Hmac, Mac};
+use rhai::{Dynamic, Engine, Map, Scope};
+use sha2::{Digest as _, Sha256};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::rc::Rc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::assertions::{self, TestResult};
+use crate::captures;
+use crate::environment::EnvironmentStore;
+use crate::key_value::KeyValueEntries;
+use crate::network::ApiResponse;
+use crate::request::RequestSpec;
+
+/// Shown under the Scripts tab
+pub const HINT: &str =
+    "Rhai scripts. Before sending: request.method, .url, .headers, .params, .body \
+     can be changed. After: response.status, .headers, .body, .json, .time_ms; \
+     test(name, ok), retry(ms). Both: get_var, set_var, print, timestamp, uuid, \
+     random_hex(n), sha256, hmac_sha256(key, text), base64_encode, base64_decode";
+
+/// How many times one request is sent before `retry()` is ignored
+pub const MAX_ATTEMPTS: u32 = 30;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScriptStage {
+    PreRequest,
+    PostResponse,
+}
+
+impl ScriptStage {
+    pub fn label(self) -> &'static str {
+        match self {
+            ScriptStage::PreRequest => "Pre-request",
+            ScriptStage::PostResponse => "Post-response",
+        }
+    }
+
+    pub fn other(self) -> Self {
+        match self {
+            ScriptStage::PreRequest => ScriptStage::PostResponse,
+            ScriptStage::PostResponse => ScriptStage::PreRequest,
+        }
+    }
+}
+
+/// Everything checked after a response arrives
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ResponseChecks {
+    /// The Tests tab, failed captures and the post-response script's tests
+    pub tests: Vec<TestResult>,
+    /// Lines printed by the script
+    pub log: Vec<String>,
+    /// Delay before sending the request again, if the script asked to
+    pub retry: Option<Duration>,
+    /// Why the post-response script stopped, also recorded as a failed test
+    pub error: Option<String>,
+}
+
+/// Recorded when a script keeps asking to retry
+pub fn retries_exhausted() -> TestResult {
+    TestResult {
+        name: "retry".to_string(),
+        passed: false,
+        message: format!("gave up after {} attempts", MAX_ATTEMPTS),
+    }
+}
+
+/// State scripts read and change through the registered functions
+#[derive(Default)]
+struct Host {
+    variables: HashMap<String, String>,
+    /// Variables set by the script, stored once it finishes
+    set: Vec<(String, String)>,
+    tests: Vec<TestResult>,
+    log: Vec<String>,
+    retry: Option<Duration>,
+}
+
+/// Run the request's pre-request script, which may change it. Returns the
+/// lines it printed.
+pub fn pre_request(
+    request: &mut RequestSpec,
+    environments: &mut EnvironmentStore,
+) -> Result<Vec<String>> {
+    if request.pre_request_script.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+    let host = new_host(environments);
+    let engine = engine(&host, ScriptStage::PreRequest);
+    let mut scope = Scope::new();
+    scope.push("request", request_map(request));
+    engine
+        .run_with_scope(&mut scope, &request.pre_request_script)
+        .map_err(|e| anyhow!("Pre-request script: {}", e))?;
+
+    let map = scope
+        .get_value::<Map>("request")
+        .ok_or_else(|| anyhow!("Pre-request script: `request` must stay an object map"))?;
+    apply_request_map(&map, request).map_err(|e| anyhow!("Pre-request script: {:#}", e))?;
+    let host = host.take();
+    store_variables(environments, &host.set)?;
+    Ok(host.log)
+}
+
+/// Check a response against the request's Tests tab, run its captures and
+/// then its post-response script. The TUI, `send` and `run` all use this.
+pub fn after_response(
+    request: &RequestSpec,
+    resp: &ApiResponse,
+    environments: &mut EnvironmentStore,
+) -> ResponseChecks {
+    let mut checks = ResponseChecks {
+        tests: assertions::run(&request.tests, resp),
+        ..ResponseChecks::default()
+    };
+    checks
+        .tests
+        .extend(captures::apply(environments, &request.captures, resp));
+    if request.post_response_script.trim().is_empty() {
+        return checks;
+    }
+
+    let host = new_host(environments);
+    let engine = engine(&host, ScriptStage::PostResponse);
+    let mut scope = Scope::new();
+    scope.push_constant("response", response_map(resp));
+    let result = engine.run_with_scope(&mut scope, &request.post_response_script);
+    let host = host.take();
+    checks.tests.extend(host.tests);
+    checks.log = host.log;
+    checks.retry = host.retry;
+    let error = match result {
+        Ok(()) => store_variables(environments, &host.set)
+            .err()
+            .map(|e| format!("{:#}", e)),
+        Err(e) => Some(e.to_string()),
+    };
+    if let Some(error) = error {
+        checks.tests.push(TestResult {
+            name: "post-response script".to_string(),
+            passed: false,
+            message: error.clone(),
+        });
+        checks.error = Some(error);
+    }
+    checks
+}
+
+fn new_host(environments: &EnvironmentStore) -> Rc<RefCell<Host>> {
+    let variables = environments
+        .active()
+        .map(|env| env.variable_map())
+        .unwrap_or_default();
+    Rc::new(RefCell::new(Host {
+        variables,
+        ..Host::default()
+    }))
+}
+
+fn store_variables(environments: &mut EnvironmentStore, values: &[(String, String)]) -> Result<()> {
+    if values.is_empty() {
+        return Ok(());
+    }
+    environments
+        .set_variables(values)
+        .map_err(|e| anyhow!("set_var: {:#}", e))
+}
+
+/// An engine with no access to files or the network, and limits so a
+/// runaway script stops with an error instead of hanging the UI
+fn engine(host: &Rc<RefCell<Host>>, stage: ScriptStage) -> Engine {
+    let mut engine = Engine::new();
+    engine
+        .set_max_operations(1_000_000)
+        .set_max_call_levels(32)
+        .set_max_expr_depths(64, 32)
+        .set_max_string_size(10 * 1024 * 1024)
+        .set_max_array_size(100_000)
+        .set_max_map_size(100_000);
+    engine.disable_symbol("eval");
+    // The default resolver would let `import` load .rhai files
+    engine.set_module_resolver(rhai::module_resolvers::DummyModuleResolver::new());
+
+    let h = host.clone();
+    engine.on_print(move |text| h.borrow_mut().log.push(text.to_string()));
+    let h = host.clone();
+    engine.on_debug(move |text, _, _| h.borrow_mut().log.push(text.to_string()));
+
+    let h = host.clone();
+    engine.register_fn("get_var", move |name: &str| -> Dynamic {
+        h.borrow()
+            .variables
+            .get(name)
+            .map_or(Dynamic::UNIT, |value| value.clone().into())
+    });
+    let h = host.clone();
+    engine.register_fn("set_var", move |name: &str, value: Dynamic| {
+        let mut host = h.borrow_mut();
+        let value = text(&value);
+        host.variables.insert(name.to_string(), value.clone());
+        host.set.push((name.to_string(), value));
+    });
+
+    if stage == ScriptStage::PostResponse {
+        let h = host.clone();
+        engine.register_fn("test", move |name: &str, passed: bool| {
+            h.borrow_mut().tests.push(TestResult {
+                name: name.to_string(),
+                passed,
+                message: String::new(),
+            });
+        });
+        let h = host.clone();
+        engine.register_fn("test", move |name: &str, passed: bool, message: &str| {
+            h.borrow_mut().tests.push(TestResult {
+                name: name.to_string(),
+                passed,
+                message: message.to_string(),
+            });
+        });
+        let h = host.clone();
+        engine.register_fn("retry", move |ms: i64| {
+            h.borrow_mut().retry = Some(Duration::from_millis(ms.max(0) as u64));
+        });
+    }
+
+    engine.register_fn("timestamp", || unix_now().as_secs() as i64);
+    engine.register_fn("timestamp_ms", || unix_now().as_millis() as i64);
+    engine.register_fn("uuid", || {
+        let mut bytes = random_bytes(16);
+        bytes[6] = (bytes[6] & 0x0f) | 0x40;
+        bytes[8] = (bytes[8] & 0x3f) | 0x80;
+        let hex = hex::encode(bytes);
+        format!(
+            "{}-{}-{}-{}-{}",
+            &hex[..8],
+            &hex[8..12],
+            &hex[12..16],
+            &hex[16..20],
+            &hex[20..]
+        )
+    });
+    engine.register_fn("random_hex", |len: i64| {
+        let hex = hex::encode(random_bytes((len.clamp(0, 1024) as usize).div_ceil(2)));
+        hex[..len.clamp(0, 1024) as usize].to_string()
+    });
+    engine.register_fn("sha256", |text: &str| hex::encode(Sha256::digest(text)));
+    engine.register_fn("hmac_sha256", |key: &str, text: &str| {
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(key.as_bytes()).expect("HMAC takes keys of any length");
+        mac.update(text.as_bytes());
+        hex::encode(mac.finalize().into_bytes())
+    });
+    engine.register_fn("base64_encode", |text: &str| {
+        general_purpose::STANDARD.encode(text)
+    });
+    engine.register_fn("base64_decode", |text: &str| -> Dynamic {
+        general_purpose::STANDARD
+            .decode(text.trim())
+            .ok()
+            .and_then(|bytes| String::from_utf8(bytes).ok())
+            .map_or(Dynamic::UNIT, Into::into)
+    });
+    engine
+}
+
+fn unix_now() -> Duration {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+}
+
+fn random_bytes(len: usize) -> Vec<u8> {
+    let mut bytes = vec![0u8; len];
+    let _ = getrandom::fill(&mut bytes);
+    bytes
+}
+
+/// Strings as they are, anything else as Rhai prints it
+fn text(value: &Dynamic) -> String {
+    value
+        .clone()
+        .into_string()
+        .unwrap_or_else(|_| value.to_string())
+}
+
+fn request_map(request: &RequestSpec) -> Map {
+    let mut map = Map::new();
+    map.insert("method".into(), request.method.to_string().into());
+    map.insert("url".into(), request.url.clone().into());
+    map.insert("headers".into(), entries_map(&request.headers).into());
+    map.insert("params".into(), entries_map(&request.params).into());
+    map.insert("body".into(), request.body.clone().into());
+    map
+}
+
+/// Enabled rows as a map; for repeated keys the last row wins
+fn entries_map(entries: &KeyValueEntries) -> Map {
+    entries
+        .entries
+        .iter()
+        .filter(|entry| entry.enabled && !entry.key.trim().is_empty())
+        .map(|entry| (entry.key.trim().into(), entry.value.clone().into()))
+        .collect()
+}
+
+/// Copy what the script changed back into the request
+fn apply_request_map(map: &Map, request: &mut RequestSpec) -> Result<()> {
+    let field = |name: &str| map.get(name).map(text).unwrap_or_default();
+    let method = field("method");
+    if method != request.method.to_string() {
+        request.method = method
+            .parse()
+            .map_err(|e| anyhow!("request.method: {:#}", e))?;
+    }
+    request.url = field("url");
+    request.body = field("body");
+    for (name, entries) in [
+        ("headers", &mut request.headers),
+        ("params", &mut request.params),
+    ] {
+        let Some(changed) = map.get(name) else {
+            continue;
+        };
+        let changed = changed
+            .clone()
+            .try_cast::<Map>()
+            .ok_or_else(|| anyhow!("request.{} must stay an object map", name))?;
+        update_entries(entries, &changed);
+    }
+    Ok(())
+}
+
+/// Apply the keys the script added, changed or removed. Disabled rows and
+/// keys it left alone stay as they are, including repeated keys, which
+/// only collapse to one row when the script sets a new value for them
+fn update_entries(entries: &mut KeyValueEntries, map: &Map) {
+    let strings = |map: &Map| -> BTreeMap<String, String> {
+        map.iter()
+            .map(|(key, value)| (key.to_string(), text(value)))
+            .collect()
+    };
+    let before = strings(&entries_map(entries));
+    let after = strings(map);
+    let mut changed = BTreeSet::new();
+    entries.entries.retain_mut(|entry| {
+        let key = entry.key.trim();
+        if !entry.enabled || key.is_empty() {
+            return true;
+        }
+        match after.get(key) {
+            Some(value) if before.get(key) == Some(value) => true,
+            Some(value) => {
+                if !changed.insert(key.to_string()) {
+                    return false;
+                }
+                entry.value = value.clone();
+                true
+            }
+            None => false,
+        }
+    });
+    for (key, value) in after {
+        if !before.contains_key(&key) {
+            entries.add_entry(key, value);
+        }
+    }
+}
+
+fn response_map(resp: &ApiResponse) -> Map {
+    let mut headers = Map::new();
+    for (name, value) in &resp.headers {
+        let name = name.to_ascii_lowercase();
+        let value = match headers.get(name.as_str()) {
+            Some(previous) => format!("{}, {}", text(previous), value),
+            None => value.clone(),
+        };
+        headers.insert(name.into(), value.into());
+    }
+    let json = serde_json::from_slice::<serde_json::Value>(&resp.raw_body)
+        .ok()
+        .and_then(|value| rhai::serde::to_dynamic(value).ok())
+        .unwrap_or(Dynamic::UNIT);
+
+    let mut map = Map::new();
+    map.insert("status".into(), (resp.status as i64).into());
+    map.insert("url".into(), resp.url.clone().into());
+    map.insert("headers".into(), headers.into());
+    map.insert(
+        "body".into(),
+        String::from_utf8_lossy(&resp.raw_body).into_owned().into(),
+    );
+    map.insert("json".into(), json);
+    map.insert("size".into(), (resp.size as i64).into());
+    map.insert(
+        "time_ms".into(),
+        (resp.timing.total.as_millis() as i64).into(),
+    );
+    map
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::HttpMethod;
+
+    fn store(dir: &tempfile::TempDir) -> EnvironmentStore {
+        EnvironmentStore::with_active(dir.path(), &[("secret", "k")])
+    }
+
+    fn response(status: u16, body: &str) -> ApiResponse {
+        ApiResponse {
+            status,
+            version: "HTTP/1.1".to_string(),
+            url: "http://localhost/jobs/1".to_string(),
+            headers: vec![("Content-Type".to_string(), "application/json".to_string())],
+            body: body.to_string(),
+            size: body.len(),
+            raw_body: body.as_bytes().to_vec(),
+            timing: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_pre_request_script_changes_request() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut environments = store(&dir);
+        let mut request = RequestSpec {
+            url: "http://localhost/orders".to_string(),
+            body: r#"{"id":1}"#.to_string(),
+            pre_request_script: r#"
+                request.method = "post";
+                request.url += "?v=2";
+                request.headers["X-Signature"] = hmac_sha256(get_var("secret"), request.body);
+                request.headers.remove("Accept");
+                request.params.page = 3;
+                set_var("nonce", random_hex(8));
+                print(`signed ${request.body}`);
+            "#
+            .to_string(),
+            ..RequestSpec::default()
+        };
+        request
+            .headers
+            .add_entry("Accept".to_string(), "*/*".to_string());
+        request
+            .headers
+            .add_entry("X-Off".to_string(), "1".to_string());
+        request.headers.toggle_enabled(1);
+
+        let log = pre_request(&mut request, &mut environments).unwrap();
+        assert_eq!(log, [r#"signed {"id":1}"#]);
+        assert_eq!(request.method, HttpMethod::POST);
+        assert_eq!(request.url, "http://localhost/orders?v=2");
+        let headers: Vec<_> = request
+            .headers
+            .entries
+            .iter()
+            .map(|e| (e.key.as_str(), e.enabled))
+            .collect();
+        assert_eq!(headers, [("X-Off", false), ("X-Signature", true)]);
+        assert_eq!(
+            request.headers.entries[1].value,
+            hex::encode({
+                let mut mac = Hmac::<Sha256>::new_from_slice(b"k").unwrap();
+                mac.update(br#"{"id":1}"#);
+                mac.finalize().into_bytes()
+            })
+        );
+        assert_eq!(request.params.entries[0].value, "3");
+        assert_eq!(
+            environments.active().unwrap().variable_map()["nonce"].len(),
+            8
+        );
+    }
+
+    #[test]
+    fn test_repeated_keys_survive_unrelated_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut environments = store(&dir);
+        let mut request = RequestSpec {
+            pre_request_script: r#"request.headers["X-Signature"] = "s";"#.to_string(),
+            ..RequestSpec::default()
+        };
+        request.params.add_entry("tag".to_string(), "a".to_string());
+        request.params.add_entry("tag".to_string(), "b".to_string());
+        request
+            .headers
+            .add_entry("Accept".to_string(), "*/*".to_string());
+        request
+            .headers
+            .add_entry("Accept".to_string(), "text/html".to_string());
+
+        pre_request(&mut request, &mut environments).unwrap();
+        let rows = |entries: &KeyValueEntries| -> Vec<(String, String)> {
+            entries
+                .entries
+                .iter()
+                .map(|e| (e.key.clone(), e.value.clone()))
+                .collect()
+        };
+        let pair = |k: &str, v: &str| (k.to_string(), v.to_string());
+        assert_eq!(rows(&request.params), [pair("tag", "a"), pair("tag", "b")]);
+        assert_eq!(
+            rows(&request.headers),
+            [
+                pair("Accept", "*/*"),
+                pair("Accept", "text/html"),
+                pair("X-Signature", "s")
+            ]
+        );
+
+        // Setting a repeated key replaces all of its rows
+        request.pre_request_script = r#"request.params.tag = "c";"#.to_string();
+        pre_request(&mut request, &mut environments).unwrap();
+        assert_eq!(rows(&request.params), [pair("tag", "c")]);
+    }
+
+    #[test]
+    fn test_script_errors_are_reported() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut environments = store(&dir);
+        let mut request = RequestSpec {
+            pre_request_script: "let x = ;".to_string(),
+            ..RequestSpec::default()
+        };
+        let err = pre_request(&mut request, &mut environments).unwrap_err();
+        assert!(err.to_string().starts_with("Pre-request script: "));
+
+        request.pre_request_script = "loop {}".to_string();
+        assert!(pre_request(&mut request, &mut environments).is_err());
+
+        request.pre_request_script = r#"request.method = "BAD METHOD""#.to_string();
+        let err = pre_request(&mut request, &mut environments).unwrap_err();
+        assert!(err.to_string().contains("request.method"));
+
+        request.post_response_script = "response.nope.x".to_string();
+        let checks = after_response(&request, &response(200, "{}"), &mut environments);
+        assert!(checks.error.is_some());
+        assert_eq!(checks.tests[0].name, "post-response script");
+        assert!(!checks.tests[0].passed);
+    }
+
+    #[test]
+    fn test_scripts_cannot_import_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut environments = store(&dir);
+        let module = dir.path().join("module.rhai");
+        std::fs::write(&module, "fn secret() { 42 }").unwrap();
+        let path = module.with_extension("");
+        let mut request = RequestSpec {
+            pre_request_script: format!("import {:?} as m; print(m::secret());", path),
+            ..RequestSpec::default()
+        };
+        let err = pre_request(&mut request, &mut environments).unwrap_err();
+        assert!(err.to_string().contains("module"), "{}", err);
+    }
+
+    #[test]
+    fn test_post_response_script_records_tests_and_variables() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut environments = store(&dir);
+        let mut request = RequestSpec {
+            post_response_script: r#"
+                test("job is known", response.status == 200);
+                test("json parsed", response.json.state == "running", response.json.state);
+                set_var("job_state", response.json.state);
+                if response.json.state != "done" { retry(250); }
+            "#
+            .to_string(),
+            ..RequestSpec::default()
+        };
+        request
+            .tests
+            .add_entry("status".to_string(), "200".to_string());
+
+        let checks = after_response(
+            &request,
+            &response(200, r#"{"state": "running"}"#),
+            &mut environments,
+        );
+        let names: Vec<_> = checks
+            .tests
+            .iter()
+            .map(|t| (t.name.as_str(), t.passed))
+            .collect();
+        assert_eq!(
+            names,
+            [
+                ("status 200", true),
+                ("job is known", true),
+                ("json parsed", true)
+            ]
+        );
+        assert_eq!(checks.tests[2].message, "running");
+        assert_eq!(checks.retry, Some(Duration::from_millis(250)));
+        assert_eq!(checks.error, None);
+        assert_eq!(
+            environments.active().unwrap().variable_map()["job_state"],
+            "running"
+        );
+    }
+}
@@ -0,0 +1,496 @@
+use anyhow::{anyhow, bail, Context, Result};
+use base64::{engine::general_purpose, Engine as _};
+use p12_keystore::KeyStore;
+use reqwest::{redirect, tls, Certificate, Client, ClientBuilder, Identity, NoProxy, Proxy};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::cookies::SharedJar;
+use crate::key_value::KeyValueEntries;
+use crate::request::RequestSpec;
+
+/// Keys accepted in the Settings tab and the `request` section of the config
+pub const SETTING_KEYS: [&str; 13] = [
+    "timeout",
+    "connect_timeout",
+    "follow_redirects",
+    "max_redirects",
+    "proxy",
+    "no_proxy",
+    "ca_cert",
+    "client_cert",
+    "client_key",
+    "client_cert_password",
+    "min_tls_version",
+    "insecure",
+    "fresh_connection",
+];
+
+/// How the HTTP client behaves for one request.
+///
+/// Built from the global defaults in `config.json`, then the active
+/// environment's settings, then the request's own Settings tab; unset
+/// fields keep reqwest's defaults.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct RequestSettings {
+    /// Limit for the whole request, from connecting to reading the body
+    pub timeout: Option<Duration>,
+    pub connect_timeout: Option<Duration>,
+    /// `false` returns 3xx responses as-is
+    pub follow_redirects: Option<bool>,
+    pub max_redirects: Option<usize>,
+    /// HTTP(S) or SOCKS proxy URL; `none` also ignores the proxy env vars
+    pub proxy: Option<String>,
+    /// Comma-separated hosts that bypass the proxy
+    pub no_proxy: Option<String>,
+    /// Comma-separated PEM files with extra trusted root certificates
+    pub ca_cert: Option<String>,
+    /// Client certificate for mutual TLS: a PEM file (which may also hold
+    /// the key) or a PKCS#12 `.p12`/`.pfx` file
+    pub client_cert: Option<String>,
+    /// PEM private key, when it is not in `client_cert`
+    pub client_key: Option<String>,
+    /// Password of a PKCS#12 client certificate
+    pub client_cert_password: Option<String>,
+    /// `1.2` or `1.3`
+    pub min_tls_version: Option<String>,
+    /// Accept any server certificate. Flagged in the UI while active.
+    pub insecure: Option<bool>,
+    /// Send on a new client instead of reusing pooled connections, e.g. to
+    /// measure cold-start latency
+    pub fresh_connection: Option<bool>,
+}
+
+impl RequestSettings {
+    /// Parse `key = value` pairs; later pairs override earlier ones and
+    /// empty values are ignored
+    pub fn parse<'a>(pairs: impl IntoIterator<Item = (&'a str, &'a str)>) -> Result<Self> {
+        let mut settings = Self::default();
+        for (key, value) in pairs {
+            let (key, value) = (key.trim(), value.trim());
+            if value.is_empty() {
+                continue;
+            }
+            settings
+                .set(key, value)
+                .with_context(|| format!("Invalid setting {} = '{}'", key, value))?;
+        }
+        Ok(settings)
+    }
+
+    fn set(&mut self, key: &str, value: &str) -> Result<()> {
+        match key.to_ascii_lowercase().as_str() {
+            "timeout" => self.timeout = Some(parse_duration(value)?),
+            "connect_timeout" => self.connect_timeout = Some(parse_duration(value)?),
+            "follow_redirects" => self.follow_redirects = Some(parse_bool(value)?),
+            "max_redirects" => self.max_redirects = Some(value.parse()?),
+            "proxy" => self.proxy = Some(value.to_string()),
+            "no_proxy" => self.no_proxy = Some(value.to_string()),
+            "ca_cert" => self.ca_cert = Some(value.to_string()),
+            "client_cert" => self.client_cert = Some(value.to_string()),
+            "client_key" => self.client_key = Some(value.to_string()),
+            "client_cert_password" => self.client_cert_password = Some(value.to_string()),
+            "min_tls_version" => {
+                tls_version(value)?;
+                self.min_tls_version = Some(value.to_string());
+            }
+            "insecure" => self.insecure = Some(parse_bool(value)?),
+            "fresh_connection" => self.fresh_connection = Some(parse_bool(value)?),
+            other => bail!(
+                "Unknown setting '{}' (expected one of {})",
+                other,
+                SETTING_KEYS.join(", ")
+            ),
+        }
+        Ok(())
+    }
+
+    /// A client builder that applies these settings, storing and sending
+    /// cookies through `cookies` when given
+    pub fn client_builder(&self, cookies: Option<SharedJar>) -> Result<ClientBuilder> {
+        let mut builder = Client::builder();
+        if let Some(jar) = cookies {
+            builder = builder.cookie_provider(Arc::new(jar));
+        }
+        if let Some(timeout) = self.timeout {
+            builder = builder.timeout(timeout);
+        }
+        if let Some(timeout) = self.connect_timeout {
+            builder = builder.connect_timeout(timeout);
+        }
+        builder = match (self.follow_redirects, self.max_redirects) {
+            (Some(false), _) => builder.redirect(redirect::Policy::none()),
+            (_, Some(max)) => builder.redirect(redirect::Policy::limited(max)),
+            _ => builder,
+        };
+        match self.proxy.as_deref() {
+            Some(proxy) if proxy.eq_ignore_ascii_case("none") => builder = builder.no_proxy(),
+            Some(proxy) => {
+                let proxy = Proxy::all(proxy)
+                    .with_context(|| format!("Invalid proxy URL '{}'", proxy))?
+                    .no_proxy(self.no_proxy.as_deref().and_then(NoProxy::from_string));
+                builder = builder.proxy(proxy);
+            }
+            None => {}
+        }
+        if let Some(paths) = &self.ca_cert {
+            let mut certs = Vec::new();
+            for path in paths.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+                let pem = fs::read(path)
+                    .with_context(|| format!("Failed to read CA certificate {}", path))?;
+                certs.extend(
+                    Certificate::from_pem_bundle(&pem)
+                        .with_context(|| format!("Invalid PEM certificate in {}", path))?,
+                );
+            }
+            builder = builder.tls_certs_merge(certs);
+        }
+        if let Some(path) = &self.client_cert {
+            builder = builder.identity(self.client_identity(path)?);
+        }
+        if let Some(version) = &self.min_tls_version {
+            builder = builder.tls_version_min(tls_version(version)?);
+        }
+        if self.insecure == Some(true) {
+            builder = builder.tls_danger_accept_invalid_certs(true);
+        }
+        Ok(builder)
+    }
+
+    fn client_identity(&self, cert_path: &str) -> Result<Identity> {
+        let data = fs::read(cert_path)
+            .with_context(|| format!("Failed to read client certificate {}", cert_path))?;
+        let is_pkcs12 = Path::new(cert_path)
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("p12") || ext.eq_ignore_ascii_case("pfx"));
+        let pem = if is_pkcs12 {
+            let password = self.client_cert_password.as_deref().unwrap_or("");
+            pkcs12_to_pem(&data, password)
+                .with_context(|| format!("Failed to open PKCS#12 file {}", cert_path))?
+        } else {
+            let mut pem = data;
+            if let Some(key_path) = &self.client_key {
+                pem.push(b'\n');
+                pem.extend(
+                    fs::read(key_path)
+                        .with_context(|| format!("Failed to read client key {}", key_path))?,
+                );
+            }
+            pem
+        };
+        Identity::from_pem(&pem).context("Invalid client certificate or key")
+    }
+}
+
+/// Re-encode the first key and its certificate chain from a PKCS#12 file
+/// as PEM, which is what the TLS backend accepts
+fn pkcs12_to_pem(data: &[u8], password: &str) -> Result<Vec<u8>> {
+    let store = KeyStore::from_pkcs12(data, password)?;
+    let (_, chain) = store
+        .private_key_chain()
+        .ok_or_else(|| anyhow!("no private key found"))?;
+    let mut pem = pem_block("PRIVATE KEY", chain.key());
+    for cert in chain.chain() {
+        pem.push_str(&pem_block("CERTIFICATE", cert.as_der()));
+    }
+    Ok(pem.into_bytes())
+}
+
+fn pem_block(label: &str, der: &[u8]) -> String {
+    let encoded = general_purpose::STANDARD.encode(der);
+    let mut pem = format!("-----BEGIN {}-----\n", label);
+    for line in encoded.as_bytes().chunks(64) {
+        pem.push_str(std::str::from_utf8(line).unwrap_or_default());
+        pem.push('\n');
+    }
+    pem.push_str(&format!("-----END {}-----\n", label));
+    pem
+}
+
+fn tls_version(value: &str) -> Result<tls::Version> {
+    match value {
+        "1.2" => Ok(tls::Version::TLS_1_2),
+        "1.3" => Ok(tls::Version::TLS_1_3),
+        _ => bail!("expected 1.2 or 1.3"),
+    }
+}
+
+/// Settings for a request: the configured defaults, then the active
+/// environment's settings, then the request's own Settings entries
+pub fn effective_settings(
+    defaults: &BTreeMap<String, String>,
+    environment: Option<&KeyValueEntries>,
+    request: &RequestSpec,
+) -> Result<RequestSettings> {
+    RequestSettings::parse(
+        defaults
+            .iter()
+            .map(|(k, v)| (k.as_str(), v.as_str()))
+            .chain(environment.into_iter().flat_map(enabled_pairs))
+            .chain(enabled_pairs(&request.settings)),
+    )
+}
+
+fn enabled_pairs(entries: &KeyValueEntries) -> impl Iterator<Item = (&str, &str)> {
+    entries
+        .entries
+        .iter()
+        .filter(|e| e.enabled && !e.key.trim().is_empty())
+        .map(|e| (e.key.as_str(), e.value.as_str()))
+}
+
+/// `30`, `30s`, `1.5s`, `500ms` or `2m`; bare numbers are seconds
+fn parse_duration(value: &str) -> Result<Duration> {
+    let (number, unit) = match value.find(|c: char| c.is_ascii_alphabetic()) {
+        Some(idx) => value.split_at(idx),
+        None => (value, "s"),
+    };
+    let number: f64 = number.trim().parse()?;
+    let secs = match unit.trim() {
+        "ms" => number / 1000.0,
+        "s" => number,
+        "m" => number * 60.0,
+        other => bail!("unknown unit '{}', use ms, s or m", other),
+    };
+    Duration::try_from_secs_f64(secs).map_err(|e| anyhow!(e))
+}
+
+fn parse_bool(value: &str) -> Result<bool> {
+    match value.to_ascii_lowercase().as_str() {
+        "true" | "yes" | "on" | "1" => Ok(true),
+        "false" | "no" | "off" | "0" => Ok(false),
+        _ => bail!("expected true or false"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_entries_override_defaults() {
+        let defaults = BTreeMap::from([
+            ("timeout".to_string(), "30".to_string()),
+            ("proxy".to_string(), "http://proxy.corp:3128".to_string()),
+        ]);
+        let mut request = RequestSpec::default();
+        request
+            .settings
+            .add_entry("timeout".to_string(), "1.5s".to_string());
+        request
+            .settings
+            .add_entry("connect_timeout".to_string(), "250ms".to_string());
+        request
+            .settings
+            .add_entry("follow_redirects".to_string(), "no".to_string());
+        request
+            .settings
+            .add_entry("max_redirects".to_string(), "bogus".to_string());
+        request.settings.toggle_enabled(3);
+
+        let settings = effective_settings(&defaults, None, &request).unwrap();
+        assert_eq!(settings.timeout, Some(Duration::from_millis(1500)));
+        assert_eq!(settings.connect_timeout, Some(Duration::from_millis(250)));
+        assert_eq!(settings.follow_redirects, Some(false));
+        assert_eq!(settings.max_redirects, None);
+        assert_eq!(settings.proxy.as_deref(), Some("http://proxy.corp:3128"));
+        assert!(settings.client_builder(None).is_ok());
+    }
+
+    #[test]
+    fn test_rejects_bad_settings() {
+        assert!(RequestSettings::parse([("timeout", "soon")]).is_err());
+        assert!(RequestSettings::parse([("timeout", "5h")]).is_err());
+        assert!(RequestSettings::parse([("retries", "3")]).is_err());
+        let bad_proxy = RequestSettings::parse([("proxy", "not a url")]).unwrap();
+        assert!(bad_proxy.client_builder(None).is_err());
+    }
+
+    /// A CA, a server certificate for `localhost` and a client certificate,
+    /// written as PEM files into `dir`
+    fn generate_pki(
+        dir: &Path,
+    ) -> (
+        rcgen::CertifiedIssuer<'static, rcgen::KeyPair>,
+        rcgen::Certificate,
+        rcgen::KeyPair,
+    ) {
+        use rcgen::{
+            BasicConstraints, CertificateParams, CertifiedIssuer, ExtendedKeyUsagePurpose, IsCa,
+            KeyPair,
+        };
+
+        let mut ca_params = CertificateParams::new(Vec::<String>::new()).unwrap();
+        ca_params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        let ca = CertifiedIssuer::self_signed(ca_params, KeyPair::generate().unwrap()).unwrap();
+
+        let server_key = KeyPair::generate().unwrap();
+        let mut server_params = CertificateParams::new(vec!["localhost".to_string()]).unwrap();
+        server_params.extended_key_usages = vec![ExtendedKeyUsagePurpose::ServerAuth];
+        let server_cert = server_params.signed_by(&server_key, &ca).unwrap();
+
+        let client_key = KeyPair::generate().unwrap();
+        let mut client_params = CertificateParams::new(Vec::<String>::new()).unwrap();
+        client_params.extended_key_usages = vec![ExtendedKeyUsagePurpose::ClientAuth];
+        let client_cert = client_params.signed_by(&client_key, &ca).unwrap();
+
+        fs::write(dir.join("ca.pem"), ca.pem()).unwrap();
+        fs::write(dir.join("client.pem"), client_cert.pem()).unwrap();
+        fs::write(dir.join("client.key"), client_key.serialize_pem()).unwrap();
+
+        let mut store = KeyStore::new();
+        let chain = p12_keystore::PrivateKeyChain::new(
+            client_key.serialize_der(),
+            [1u8; 20],
+            [p12_keystore::Certificate::from_der(client_cert.der()).unwrap()],
+        );
+        store.add_entry(
+            "client",
+            p12_keystore::KeyStoreEntry::PrivateKeyChain(chain),
+        );
+        fs::write(
+            dir.join("client.p12"),
+            store.writer("secret").write().unwrap(),
+        )
+        .unwrap();
+
+        (ca, server_cert, server_key)
+    }
+
+    /// Serve `ok` over HTTPS to clients presenting a certificate from `ca`
+    async fn spawn_mtls_server(
+        ca: &rcgen::CertifiedIssuer<'static, rcgen::KeyPair>,
+        server_cert: rcgen::Certificate,
+        server_key: rcgen::KeyPair,
+    ) -> u16 {
+        use std::sync::Arc;
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        use tokio_rustls::rustls::{self, pki_types::PrivateKeyDer, server::WebPkiClientVerifier};
+
+        let provider = Arc::new(rustls::crypto::aws_lc_rs::default_provider());
+        let mut roots = rustls::RootCertStore::empty();
+        roots.add(ca.der().clone()).unwrap();
+        let verifier =
+            WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider.clone())
+                .build()
+                .unwrap();
+        let config = rustls::ServerConfig::builder_with_provider(provider)
+            .with_safe_default_protocol_versions()
+            .unwrap()
+            .with_client_cert_verifier(verifier)
+            .with_single_cert(
+                vec![server_cert.der().clone()],
+                PrivateKeyDer::try_from(server_key.serialize_der()).unwrap(),
+            )
+            .unwrap();
+        let acceptor = tokio_rustls::TlsAcceptor::from(Arc::new(config));
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            loop {
+                let Ok((stream, _)) = listener.accept().await else {
+                    return;
+                };
+                let acceptor = acceptor.clone();
+                tokio::spawn(async move {
+                    let Ok(mut tls) = acceptor.accept(stream).await else {
+                        return;
+                    };
+                    let mut buf = [0u8; 4096];
+                    let _ = tls.read(&mut buf).await;
+                    let _ = tls
+                        .write_all(
+                            b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\nok",
+                        )
+                        .await;
+                    let _ = tls.shutdown().await;
+                });
+            }
+        });
+        port
+    }
+
+    #[tokio::test]
+    async fn test_tls_settings_against_local_mtls_server() {
+        let dir = tempfile::tempdir().unwrap();
+        let (ca, server_cert, server_key) = generate_pki(dir.path());
+        let port = spawn_mtls_server(&ca, server_cert, server_key).await;
+        let url = format!("https://localhost:{}/", port);
+        let path = |name: &str| dir.path().join(name).display().to_string();
+
+        let send = |pairs: Vec<(&'static str, String)>| {
+            let url = url.clone();
+            async move {
+                let settings = RequestSettings::parse(pairs.iter().map(|(k, v)| (*k, v.as_str())))?;
+                let response = settings
+                    .client_builder(None)?
+                    .build()?
+                    .get(&url)
+                    .send()
+                    .await?;
+                anyhow::Ok(response.text().await?)
+            }
+        };
+
+        // Private CA and client certificate as PEM files
+        let pem = send(vec![
+            ("ca_cert", path("ca.pem")),
+            ("client_cert", path("client.pem")),
+            ("client_key", path("client.key")),
+            ("min_tls_version", "1.2".to_string()),
+        ])
+        .await;
+        assert_eq!(pem.unwrap(), "ok");
+
+        // Same client certificate from a PKCS#12 bundle
+        let pkcs12 = send(vec![
+            ("ca_cert", path("ca.pem")),
+            ("client_cert", path("client.p12")),
+            ("client_cert_password", "secret".to_string()),
+        ])
+        .await;
+        assert_eq!(pkcs12.unwrap(), "ok");
+
+        // Untrusted server, then a server that wants a client certificate
+        assert!(send(vec![
+            ("client_cert", path("client.p12")),
+            ("client_cert_password", "secret".to_string())
+        ])
+        .await
+        .is_err());
+        assert!(send(vec![("ca_cert", path("ca.pem"))]).await.is_err());
+
+        // Insecure mode skips server verification, not client auth
+        let insecure = send(vec![
+            ("insecure", "true".to_string()),
+            ("client_cert", path("client.pem")),
+            ("client_key", path("client.key")),
+        ])
+        .await;
+        assert_eq!(insecure.unwrap(), "ok");
+
+        // The handshake is timed apart from the TCP connect
+        let settings = RequestSettings::parse([
+            ("ca_cert", path("ca.pem").as_str()),
+            ("client_cert", path("client.pem").as_str()),
+            ("client_key", path("client.key").as_str()),
+        ])
+        .unwrap();
+        let client = crate::network::HttpClient::new(&settings, None).unwrap();
+        let request = RequestSpec {
+            url: url.clone(),
+            ..Default::default()
+        };
+        let timing = crate::network::make_request(&client, &request)
+            .await
+            .unwrap()
+            .timing;
+        assert!(!timing.connect.is_zero());
+        assert!(!timing.tls_handshake.is_zero());
+    }
+}
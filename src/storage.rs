@@ -0,0 +1,90 @@
+use anyhow::{Context, Result};
+use serde::{de::DeserializeOwned, Serialize};
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const APP_DIR: &str = "api-client";
+
+/// Per-user data directory (e.g. `~/.local/share/api-client` on Linux)
+pub fn data_dir() -> PathBuf {
+    dirs::data_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join(APP_DIR)
+}
+
+/// Per-user configuration directory (e.g. `~/.config/api-client` on Linux)
+pub fn config_dir() -> PathBuf {
+    dirs::config_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join(APP_DIR)
+}
+
+/// Directory for files meant to be shared with a team: `.api-client` in the
+/// current directory when it exists (so it can be committed alongside the
+/// code), otherwise the per-user data directory
+pub fn workspace_dir() -> PathBuf {
+    let local = PathBuf::from(".api-client");
+    if local.is_dir() {
+        local
+    } else {
+        data_dir()
+    }
+}
+
+/// Read and deserialize a JSON file, returning `None` if it does not exist
+pub fn read_json<T: DeserializeOwned>(path: &Path) -> Result<Option<T>> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    };
+    let value = serde_json::from_str(&contents)
+        .with_context(|| format!("Failed to parse {}", path.display()))?;
+    Ok(Some(value))
+}
+
+/// Serialize `value` as pretty JSON and write it atomically
+pub fn write_json<T: Serialize + ?Sized>(path: &Path, value: &T) -> Result<()> {
+    let mut contents = serde_json::to_vec_pretty(value)?;
+    contents.push(b'\n');
+    write_atomic(path, &contents)
+}
+
+/// Write to a temporary sibling file and rename it into place, so readers
+/// never observe a half-written file
+pub fn write_atomic(path: &Path, contents: &[u8]) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+
+    let mut tmp_name = path.file_name().unwrap_or_default().to_os_string();
+    tmp_name.push(".tmp");
+    let tmp_path = path.with_file_name(tmp_name);
+
+    let mut file = File::create(&tmp_path)
+        .with_context(|| format!("Failed to create {}", tmp_path.display()))?;
+    file.write_all(contents)?;
+    file.sync_all()?;
+    drop(file);
+
+    fs::rename(&tmp_path, path).with_context(|| format!("Failed to replace {}", path.display()))?;
+    Ok(())
+}
+
+/// Move an unreadable file out of the way so it is kept for inspection
+/// instead of being overwritten. Returns the new location.
+pub fn quarantine(path: &Path) -> Result<PathBuf> {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".corrupt-{}", timestamp));
+    let target = path.with_file_name(name);
+    fs::rename(path, &target)
+        .with_context(|| format!("Failed to move aside {}", path.display()))?;
+    Ok(target)
+}
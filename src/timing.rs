@@ -0,0 +1,336 @@
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use reqwest::ClientBuilder;
+use rustls::crypto::{ActiveKeyExchange, CompletedKeyExchange, SupportedKxGroup};
+use rustls::ffdhe_groups::FfdheGroup;
+use rustls::{NamedGroup, ProtocolVersion};
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, Once};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use tower_layer::Layer;
+use tower_service::Service;
+
+/// Where the time of one request went. Phases are sequential, so they add
+/// up to `total`; a reused connection has no DNS, connect or TLS time.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Timing {
+    pub dns: Duration,
+    /// TCP connect, including any proxy tunnel
+    pub connect: Duration,
+    /// TLS handshake, for HTTPS
+    #[serde(default)]
+    pub tls_handshake: Duration,
+    /// From sending the request until the response headers arrived
+    pub first_byte: Duration,
+    pub download: Duration,
+    pub total: Duration,
+}
+
+impl Timing {
+    /// `(label, start offset, duration)` of each phase, for a waterfall
+    pub fn phases(&self) -> Vec<(&'static str, Duration, Duration)> {
+        let mut start = Duration::ZERO;
+        [
+            ("DNS lookup", self.dns),
+            ("TCP connect", self.connect),
+            ("TLS handshake", self.tls_handshake),
+            ("First byte", self.first_byte),
+            ("Download", self.download),
+        ]
+        .into_iter()
+        .map(|(label, duration)| {
+            let phase = (label, start, duration);
+            start += duration;
+            phase
+        })
+        .collect()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Event {
+    Dns(Instant, Instant),
+    /// A new connection, from name resolution to the end of any TLS
+    /// handshake
+    Connect(Instant, Instant),
+    /// A TLS client hello being built, right after the TCP connect
+    TlsStart(Instant),
+}
+
+tokio::task_local! {
+    /// The probe of the request the current task is sending
+    static CURRENT: ConnectProbe;
+}
+
+/// Records the DNS lookups, connections and TLS handshakes made while
+/// sending one request, since reqwest only reports the finished response.
+///
+/// Clients are shared between requests, so events go to the probe of the
+/// task that waited for them. A connection finished in the background
+/// after its request took a pooled one is not counted for anyone.
+#[derive(Debug, Clone, Default)]
+pub struct ConnectProbe(Arc<Mutex<Vec<Event>>>);
+
+impl ConnectProbe {
+    /// Hook probes into a client's resolver, connector and TLS handshakes.
+    ///
+    /// Handshakes are seen through the process-wide default rustls crypto
+    /// provider, which the first call replaces with a timed copy. If
+    /// anything installs a default provider before that, the TLS phase
+    /// silently reads zero and the handshake counts as connect time.
+    pub fn install(builder: ClientBuilder) -> ClientBuilder {
+        install_timed_crypto();
+        builder
+            .dns_resolver(TimedResolver)
+            .connector_layer(TimedConnectLayer)
+    }
+
+    /// Run `future`, recording the lookups and connections it waits for
+    pub async fn track<F: Future>(&self, future: F) -> F::Output {
+        CURRENT.scope(self.clone(), future).await
+    }
+
+    /// Record an event for the request being sent, if any
+    fn record(event: Event) {
+        let _ = CURRENT.try_with(|probe| {
+            let mut events = probe.0.lock().unwrap_or_else(|e| e.into_inner());
+            events.push(event);
+        });
+    }
+
+    /// Split a request sent at `sent` whose headers arrived at `headers`
+    /// and body finished at `done` into phases
+    pub fn timing(&self, sent: Instant, headers: Instant, done: Instant) -> Timing {
+        let events = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        let mut dns = Duration::ZERO;
+        let mut connect = Duration::ZERO;
+        let mut tls_handshake = Duration::ZERO;
+        for event in events.iter() {
+            match *event {
+                Event::Dns(start, end) => dns += end - start,
+                Event::Connect(start, end) => {
+                    connect += end - start;
+                    // A retried handshake builds a second hello; the first counts
+                    let hello = events
+                        .iter()
+                        .filter_map(|event| match event {
+                            Event::TlsStart(at) if (start..=end).contains(at) => Some(*at),
+                            _ => None,
+                        })
+                        .min();
+                    if let Some(hello) = hello {
+                        tls_handshake += end - hello;
+                    }
+                }
+                Event::TlsStart(_) => {}
+            }
+        }
+
+        // Name resolution and the handshake happen inside the connector
+        let connect = connect.saturating_sub(dns + tls_handshake);
+        let waiting = headers - sent;
+        Timing {
+            dns,
+            connect,
+            tls_handshake,
+            first_byte: waiting.saturating_sub(dns + connect + tls_handshake),
+            download: done - headers,
+            total: done - sent,
+        }
+    }
+}
+
+/// Install the default TLS crypto provider with its key exchanges timed,
+/// which is how the start of a handshake is seen. Has no effect if another
+/// provider was installed first; the handshake then counts as connect time.
+fn install_timed_crypto() {
+    static INSTALL: Once = Once::new();
+    INSTALL.call_once(|| {
+        let mut provider = rustls::crypto::aws_lc_rs::default_provider();
+        provider.kx_groups = provider
+            .kx_groups
+            .into_iter()
+            .map(|group| {
+                let timed: &'static dyn SupportedKxGroup = Box::leak(Box::new(TimedKxGroup(group)));
+                timed
+            })
+            .collect();
+        let _ = provider.install_default();
+    });
+}
+
+/// A key exchange group that records when a client hello starts one
+#[derive(Debug)]
+struct TimedKxGroup(&'static dyn SupportedKxGroup);
+
+impl SupportedKxGroup for TimedKxGroup {
+    fn start(&self) -> Result<Box<dyn ActiveKeyExchange>, rustls::Error> {
+        ConnectProbe::record(Event::TlsStart(Instant::now()));
+        self.0.start()
+    }
+
+    fn start_and_complete(
+        &self,
+        peer_pub_key: &[u8],
+    ) -> Result<CompletedKeyExchange, rustls::Error> {
+        self.0.start_and_complete(peer_pub_key)
+    }
+
+    fn ffdhe_group(&self) -> Option<FfdheGroup<'static>> {
+        self.0.ffdhe_group()
+    }
+
+    fn name(&self) -> NamedGroup {
+        self.0.name()
+    }
+
+    fn fips(&self) -> bool {
+        self.0.fips()
+    }
+
+    fn usable_for_version(&self, version: ProtocolVersion) -> bool {
+        self.0.usable_for_version(version)
+    }
+}
+
+/// The system resolver, timed
+struct TimedResolver;
+
+impl Resolve for TimedResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let host = name.as_str().to_string();
+        Box::pin(async move {
+            let start = Instant::now();
+            let addrs: Vec<_> = tokio::net::lookup_host((host.as_str(), 0)).await?.collect();
+            ConnectProbe::record(Event::Dns(start, Instant::now()));
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+#[derive(Clone)]
+struct TimedConnectLayer;
+
+impl<S> Layer<S> for TimedConnectLayer {
+    type Service = TimedConnect<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        TimedConnect { inner }
+    }
+}
+
+/// Times each new connection, including its TLS handshake
+#[derive(Clone)]
+struct TimedConnect<S> {
+    inner: S,
+}
+
+impl<S, R> Service<R> for TimedConnect<S>
+where
+    S: Service<R>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<S::Response, S::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: R) -> Self::Future {
+        let start = Instant::now();
+        let connecting = self.inner.call(request);
+        Box::pin(async move {
+            let conn = connecting.await;
+            ConnectProbe::record(Event::Connect(start, Instant::now()));
+            conn
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn probe(events: Vec<Event>) -> ConnectProbe {
+        ConnectProbe(Arc::new(Mutex::new(events)))
+    }
+
+    fn ms(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    #[test]
+    fn test_cold_https_request() {
+        let t = Instant::now();
+        let timing = probe(vec![
+            Event::Dns(t, t + ms(10)),
+            Event::TlsStart(t + ms(30)),
+            Event::Connect(t, t + ms(60)),
+        ])
+        .timing(t, t + ms(100), t + ms(120));
+        assert_eq!(
+            timing,
+            Timing {
+                dns: ms(10),
+                connect: ms(20),
+                tls_handshake: ms(30),
+                first_byte: ms(40),
+                download: ms(20),
+                total: ms(120),
+            }
+        );
+        let phases: Duration = timing.phases().iter().map(|(_, _, d)| *d).sum();
+        assert_eq!(phases, timing.total);
+    }
+
+    #[test]
+    fn test_retried_handshake_counts_from_the_first_hello() {
+        let t = Instant::now();
+        let timing = probe(vec![
+            Event::TlsStart(t + ms(20)),
+            Event::TlsStart(t + ms(50)),
+            Event::Connect(t, t + ms(80)),
+            // A hello outside any connection of this request is ignored
+            Event::TlsStart(t + ms(90)),
+        ])
+        .timing(t, t + ms(100), t + ms(100));
+        assert_eq!(timing.dns, Duration::ZERO);
+        assert_eq!(timing.tls_handshake, ms(60));
+        assert_eq!(timing.connect, ms(20));
+        assert_eq!(timing.first_byte, ms(20));
+    }
+
+    #[test]
+    fn test_pooled_connection_has_no_connect_phases() {
+        let t = Instant::now();
+        let timing = probe(Vec::new()).timing(t, t + ms(30), t + ms(35));
+        assert_eq!(
+            timing,
+            Timing {
+                first_byte: ms(30),
+                download: ms(5),
+                total: ms(35),
+                ..Timing::default()
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_events_are_recorded_only_inside_track() {
+        let t = Instant::now();
+        let tracked = ConnectProbe::default();
+        ConnectProbe::record(Event::Dns(t, t + ms(1)));
+        tracked
+            .track(async { ConnectProbe::record(Event::Dns(t, t + ms(2))) })
+            .await;
+        let other = ConnectProbe::default();
+        other.track(async {}).await;
+
+        assert_eq!(*tracked.0.lock().unwrap(), [Event::Dns(t, t + ms(2))]);
+        assert!(other.0.lock().unwrap().is_empty());
+    }
+}
@@ -1,28 +1,64 @@
-use crate::app::{App, FocusedPane, InputMode, RequestTab};
+use crate::app::{
+    App, CollectionAction, CookieView, FocusedPane, InputMode, JwtView, RequestTab, ResponseTab,
+};
+use crate::assertions::{self, TestResult};
+use crate::auth::AuthKind;
+use crate::captures;
+use crate::cookies::unix_now;
+use crate::export::SnippetFormat;
 use crate::highlight::Highlighter;
+use crate::jwt::TIME_CLAIMS;
 use crate::key_value::KeyValueWidget;
+use crate::network::ApiResponse;
+use crate::request::BodyMode;
+use crate::scripts::{self, ScriptStage};
+use crate::settings::SETTING_KEYS;
+use crate::timing::Timing;
 use ratatui::{
-    layout::{Constraint, Direction, Layout},
+    layout::{Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
     text::{Line, Span, Text},
-    widgets::{Block, Borders, Paragraph, Wrap},
+    widgets::{Block, Borders, Clear, Paragraph, Wrap},
     Frame,
 };
+use std::time::Duration;
+
+/// Frames of the in-flight request spinner
+const SPINNER: [&str; 10] = ["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
 
 pub fn ui(f: &mut Frame, app: &App) {
-    // 1. Split Screen: Request (Top), Response (Middle), Footer (Bottom)
+    // 1. Split Screen: Main (Top), Footer (Bottom)
+    let outer = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Min(0),    // Main
+            Constraint::Length(3), // Footer
+        ])
+        .split(f.area());
+    let footer_area = outer[1];
+
+    // 2. Main: Collections sidebar (Left), Request/Response (Right)
+    let main_chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Length(30), // Collections
+            Constraint::Min(0),     // Request + Response
+        ])
+        .split(outer[0]);
+
+    render_collections(f, app, main_chunks[0]);
+
+    // 3. Request (Top), Response (Bottom)
     let chunks = Layout::default()
         .direction(Direction::Vertical)
         .constraints([
             Constraint::Percentage(50), // Request
             Constraint::Min(5),         // Response
-            Constraint::Length(3),      // Footer
         ])
-        .split(f.area());
+        .split(main_chunks[1]);
 
     let request_area = chunks[0];
     let response_area = chunks[1];
-    let footer_area = chunks[2];
 
     // --- Request Section ---
     let request_chunks = Layout::default()
@@ -40,13 +76,16 @@ pub fn ui(f: &mut Frame, app: &App) {
     let url_chunks = Layout::default()
         .direction(Direction::Horizontal)
         .constraints([
-            Constraint::Length(12), // Method Dropdown
+            Constraint::Length(14), // Method Dropdown
             Constraint::Min(0),     // URL Input
         ])
         .split(top_row);
 
     // Render Method
-    let method_str = format!(" {} ", app.method); // Pad for looks
+    let method_str = match &app.method_input {
+        Some(input) => format!(" {}_", input),
+        None => format!(" {} ", app.method), // Pad for looks
+    };
     let method_block = Block::default()
         .borders(Borders::ALL)
         .title("Method")
@@ -59,13 +98,37 @@ pub fn ui(f: &mut Frame, app: &App) {
     f.render_widget(method_p, url_chunks[0]);
 
     // Render URL
-    let url_block = Block::default().borders(Borders::ALL).title("URL").style(
-        if app.focused_pane == FocusedPane::Url {
+    let env_name = app
+        .environments
+        .active()
+        .map(|env| env.name.as_str())
+        .unwrap_or("none");
+    let mut url_block = Block::default().borders(Borders::ALL).title("URL");
+    if app.insecure_tls() {
+        url_block = url_block.title(
+            Line::from(Span::styled(
+                " INSECURE TLS ",
+                Style::default()
+                    .fg(Color::White)
+                    .bg(Color::Red)
+                    .add_modifier(Modifier::BOLD),
+            ))
+            .right_aligned(),
+        );
+    }
+    let url_block = url_block
+        .title(
+            Line::from(Span::styled(
+                format!(" env: {} ", env_name),
+                Style::default().fg(Color::Magenta),
+            ))
+            .right_aligned(),
+        )
+        .style(if app.focused_pane == FocusedPane::Url {
             Style::default().fg(Color::Yellow)
         } else {
             Style::default()
-        },
-    );
+        });
     let url_p = Paragraph::new(app.url_input.as_str()).block(url_block);
     f.render_widget(url_p, url_chunks[1]);
 
@@ -98,48 +161,30 @@ pub fn ui(f: &mut Frame, app: &App) {
         .split(request_inner);
 
     // Render tab bar with all three tabs
-    let tabs = vec![
+    let tabs = [
         ("Headers", RequestTab::Headers),
         ("Params", RequestTab::Params),
         ("Auth", RequestTab::Authorization),
+        ("Settings", RequestTab::Settings),
+        ("Tests", RequestTab::Tests),
+        ("Captures", RequestTab::Captures),
+        ("Scripts", RequestTab::Scripts),
     ];
 
-    let mut tab_spans = Vec::new();
-    for (i, (label, tab)) in tabs.iter().enumerate() {
-        if i > 0 {
-            tab_spans.push(Span::raw(" "));
-        }
-
-        let style = if *tab == app.active_request_tab {
-            Style::default()
-                .fg(Color::Blue)
-                .add_modifier(Modifier::BOLD)
-        } else {
-            Style::default().fg(Color::DarkGray)
-        };
-
-        tab_spans.push(Span::styled(format!("[{}]", label), style));
-    }
-
-    let tab_line = Line::from(tab_spans);
+    let tab_line = tab_bar(
+        tabs.iter()
+            .map(|(label, tab)| (*label, app.is_request_tab_active(tab))),
+    );
     let tab_paragraph = Paragraph::new(tab_line);
     f.render_widget(tab_paragraph, request_sections[0]);
 
-    // Render key-value widget for active tab
-    let active_entries = match app.active_request_tab {
-        RequestTab::Headers => &app.headers,
-        RequestTab::Params => &app.params,
-        RequestTab::Authorization => &app.authorization,
-    };
-
     let is_editing =
         app.input_mode == InputMode::Editing && app.focused_pane == FocusedPane::RequestDetails;
-
-    let kv_widget = KeyValueWidget::new(active_entries)
-        .focused(app.focused_pane == FocusedPane::RequestDetails)
-        .editing(is_editing);
-
-    kv_widget.render(f, request_sections[1]);
+    match app.active_request_tab {
+        RequestTab::Authorization => render_auth(f, app, request_sections[1], is_editing),
+        RequestTab::Scripts => render_scripts(f, app, request_sections[1]),
+        _ => render_key_values(f, app, request_sections[1], is_editing),
+    }
 
     // Body - with validation error styling
     let has_error = app.validation_error.is_some();
@@ -156,9 +201,51 @@ pub fn ui(f: &mut Frame, app: &App) {
         .title(app.get_validation_status())
         .style(body_style);
 
-    let mut body_textarea = app.body_input.clone();
-    body_textarea.set_block(body_block);
-    f.render_widget(&body_textarea, details_chunks[1]);
+    match app.body_mode {
+        BodyMode::FormUrlEncoded | BodyMode::Multipart => {
+            let body_inner = body_block.inner(details_chunks[1]);
+            f.render_widget(body_block, details_chunks[1]);
+            let form_sections = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Min(0), Constraint::Length(1)])
+                .split(body_inner);
+            KeyValueWidget::new(&app.form)
+                .focused(app.focused_pane == FocusedPane::Body)
+                .editing(
+                    app.input_mode == InputMode::Editing && app.focused_pane == FocusedPane::Body,
+                )
+                .render(f, form_sections[0]);
+            if app.body_mode == BodyMode::Multipart {
+                let hint = Paragraph::new("Values starting with @ are sent as files")
+                    .style(Style::default().fg(Color::DarkGray));
+                f.render_widget(hint, form_sections[1]);
+            }
+        }
+        BodyMode::Binary => {
+            let path = app.body_file.trim();
+            let detail = match std::fs::metadata(path) {
+                _ if path.is_empty() => "Press i and type the path of the file to send".to_string(),
+                Ok(meta) if meta.is_file() => format_bytes(meta.len() as usize),
+                _ => String::new(),
+            };
+            let cursor =
+                if app.input_mode == InputMode::Editing && app.focused_pane == FocusedPane::Body {
+                    "_"
+                } else {
+                    ""
+                };
+            let lines = vec![
+                field_line("File", format!("{}{}", app.body_file, cursor)),
+                Line::from(Span::styled(detail, Style::default().fg(Color::DarkGray))),
+            ];
+            f.render_widget(Paragraph::new(lines).block(body_block), details_chunks[1]);
+        }
+        BodyMode::Json | BodyMode::Text | BodyMode::Xml => {
+            let mut body_textarea = app.body_input.clone();
+            body_textarea.set_block(body_block);
+            f.render_widget(&body_textarea, details_chunks[1]);
+        }
+    }
 
     // --- Response Section ---
     let response_block = Block::default()
@@ -174,31 +261,788 @@ pub fn ui(f: &mut Frame, app: &App) {
             Style::default()
         });
 
+    let response_inner = response_block.inner(response_area);
+    f.render_widget(response_block, response_area);
+
+    let response_sections = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(1), // Tab bar
+            Constraint::Min(0),    // Tab content
+        ])
+        .split(response_inner);
+
+    let tests_label = match app.test_results.len() {
+        0 => "Tests".to_string(),
+        total => {
+            let passed = app.test_results.iter().filter(|r| r.passed).count();
+            format!("Tests {}/{}", passed, total)
+        }
+    };
+    let response_tabs = [
+        ("Body", ResponseTab::Body),
+        ("Headers", ResponseTab::Headers),
+        ("Cookies", ResponseTab::Cookies),
+        ("Timing", ResponseTab::Timing),
+        (tests_label.as_str(), ResponseTab::Tests),
+    ];
+    let response_tab_line = tab_bar(
+        response_tabs
+            .iter()
+            .map(|(label, tab)| (*label, *tab == app.active_response_tab)),
+    );
+    f.render_widget(Paragraph::new(response_tab_line), response_sections[0]);
+
     let content = app.response_text.as_deref().unwrap_or("No response yet...");
 
-    // Apply syntax highlighting for JSON responses
-    let highlighted_content = if content != "No response yet..." && content != "Loading..." {
-        let highlighter = Highlighter::new();
-        let lines = highlighter.highlight_json(content);
-        Text::from(lines)
-    } else {
-        Text::raw(content)
+    let response_content = match (&app.active_response_tab, &app.response) {
+        _ if app.in_flight.is_some() => {
+            let elapsed = app
+                .in_flight
+                .as_ref()
+                .map(|r| r.started.elapsed())
+                .unwrap_or_default();
+            let frame = SPINNER[(elapsed.as_millis() / 100) as usize % SPINNER.len()];
+            Text::from(vec![
+                Line::from(Span::styled(
+                    format!("{} Sending request... {:.1}s", frame, elapsed.as_secs_f64()),
+                    Style::default().fg(Color::Yellow),
+                )),
+                Line::from(""),
+                Line::from(Span::styled(
+                    "Press Esc to cancel",
+                    Style::default().fg(Color::DarkGray),
+                )),
+            ])
+        }
+        (ResponseTab::Body, _) | (_, None) => {
+            // Apply syntax highlighting for JSON responses
+            if app.response.is_some() {
+                let highlighter = Highlighter::new();
+                let lines = highlighter.highlight_json(content);
+                Text::from(lines)
+            } else {
+                Text::raw(content)
+            }
+        }
+        (ResponseTab::Headers, Some(resp)) => response_headers_text(resp),
+        (ResponseTab::Cookies, Some(resp)) => response_cookies_text(resp),
+        (ResponseTab::Timing, Some(resp)) => response_timing_text(resp, response_sections[1].width),
+        (ResponseTab::Tests, Some(_)) => response_tests_text(&app.test_results, &app.script_log),
     };
 
-    let response_p = Paragraph::new(highlighted_content)
-        .block(response_block)
+    let response_p = Paragraph::new(response_content)
         .wrap(Wrap { trim: false })
         .scroll((app.response_scroll, 0));
-    f.render_widget(response_p, response_area);
+    f.render_widget(response_p, response_sections[1]);
 
     // --- Footer Section ---
     let help_msg = match app.input_mode {
+        InputMode::Normal if app.focused_pane == FocusedPane::Collections => {
+            " [Enter] Open | [s] Save Request | [n] New Folder | [r] Rename | [c] Duplicate | [d] Delete "
+        }
+        InputMode::Normal if app.focuses_auth() => {
+            " [t] Auth Type | [↑/↓] Field | [i] Edit | [Enter] Send "
+        }
+        InputMode::Normal if app.focuses_scripts() => " [↑/↓] Script | [i] Edit | [Enter] Send ",
+        InputMode::Normal if app.focused_pane == FocusedPane::Body => {
+            match app.body_mode {
+                BodyMode::Text => " [i] Edit | [m] Body Type | [t] Content Type | [Enter] Send ",
+                _ => " [i] Edit | [m] Body Type | [Enter] Send ",
+            }
+        }
         InputMode::Normal => {
-            " [Tab] Next Pane | [Space] Cycle Method | [i] Edit | [Enter] Send | [q] Quit "
+            " [Tab] Next Pane | [Space] Cycle Method | [i] Edit | [Enter] Send | [s] Save | [e] Env | [p] Paste curl | [y] Export | [k] Cookies | [j] JWT | [q] Quit "
         }
+        InputMode::Editing if app.collection_prompt.is_some() => " [Enter] Confirm | [Esc] Cancel ",
+        InputMode::Editing if app.method_input.is_some() => {
+            " Type any method, e.g. PURGE | [Enter] Apply | [Esc] Cancel "
+        }
+        InputMode::Editing if app.focuses_auth() => " [Tab] Next Field | [Esc] Finish Editing ",
         InputMode::Editing => " [Esc] Finish Editing ",
     };
-    let footer =
-        Paragraph::new(help_msg).block(Block::default().borders(Borders::ALL).title("Controls"));
+    let mut footer_block = Block::default().borders(Borders::ALL).title("Controls");
+    if let Some(status) = &app.status_message {
+        footer_block = footer_block.title(
+            Line::from(Span::styled(
+                format!(" {} ", status),
+                Style::default().fg(Color::Red),
+            ))
+            .right_aligned(),
+        );
+    }
+    let footer = Paragraph::new(help_msg).block(footer_block);
     f.render_widget(footer, footer_area);
+
+    if let Some(export) = &app.export {
+        let area = centered_rect(80, 70, f.area());
+        let mut tab_spans = Vec::new();
+        for format in SnippetFormat::ALL {
+            let style = if format == export.format {
+                Style::default()
+                    .fg(Color::Blue)
+                    .add_modifier(Modifier::BOLD)
+            } else {
+                Style::default().fg(Color::DarkGray)
+            };
+            tab_spans.push(Span::styled(format!("[{}]", format.label()), style));
+            tab_spans.push(Span::raw(" "));
+        }
+        let mut lines = vec![Line::from(tab_spans), Line::raw("")];
+        lines.extend(export.text.lines().map(|l| Line::raw(l.to_string())));
+
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .title("Export request")
+            .title(
+                Line::from(format!(
+                    " [←/→] Format | [w] Write {} | [Esc] Close ",
+                    export.format.file_name()
+                ))
+                .right_aligned(),
+            )
+            .style(Style::default().fg(Color::Yellow));
+        let snippet = Paragraph::new(lines)
+            .block(block)
+            .scroll((export.scroll, 0));
+        f.render_widget(Clear, area);
+        f.render_widget(snippet, area);
+    }
+
+    if let Some(view) = &app.cookie_view {
+        render_cookies(f, app, view);
+    }
+
+    if let Some(view) = &app.jwt_view {
+        render_jwt(f, view);
+    }
+
+    if let Some(editor) = &app.script_editor {
+        let area = centered_rect(80, 70, f.area());
+        let mut textarea = editor.textarea.clone();
+        textarea.set_block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(format!("{} script", editor.stage.label()))
+                .title(Line::from(" [Esc] Done ").right_aligned())
+                .style(Style::default().fg(Color::Yellow)),
+        );
+        f.render_widget(Clear, area);
+        f.render_widget(&textarea, area);
+    }
+
+    if let Some(textarea) = &app.curl_import {
+        let area = centered_rect(70, 50, f.area());
+        let mut textarea = textarea.clone();
+        textarea.set_block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("Import curl command")
+                .title(Line::from(" [Enter] Import | [Esc] Cancel ").right_aligned())
+                .style(Style::default().fg(Color::Yellow)),
+        );
+        f.render_widget(Clear, area);
+        f.render_widget(&textarea, area);
+    }
+}
+
+/// The cookie jar popup for the active environment
+fn render_cookies(f: &mut Frame, app: &App, view: &CookieView) {
+    let area = centered_rect(80, 60, f.area());
+    let now = unix_now();
+    let jar = app
+        .cookie_jars
+        .get(&app.cookie_jar_name())
+        .map(|jar| jar.lock().clone())
+        .unwrap_or_default();
+
+    let mut lines = Vec::new();
+    if jar.cookies.is_empty() {
+        lines.push(Line::styled(
+            "No cookies yet. Cookies set by responses appear here.",
+            Style::default().fg(Color::DarkGray),
+        ));
+    }
+    for (idx, cookie) in jar.cookies.iter().enumerate() {
+        let expires = match cookie.expires {
+            Some(expires) => format!("expires in {}s", expires.saturating_sub(now)),
+            None => "session".to_string(),
+        };
+        let mut flags = Vec::new();
+        if cookie.host_only {
+            flags.push("host-only");
+        }
+        if cookie.secure {
+            flags.push("Secure");
+        }
+        if cookie.http_only {
+            flags.push("HttpOnly");
+        }
+        let style = if idx == view.selected {
+            Style::default().fg(Color::Black).bg(Color::Yellow)
+        } else {
+            Style::default()
+        };
+        lines.push(Line::from(vec![
+            Span::styled(format!("{}={}", cookie.name, cookie.value), style),
+            Span::styled(
+                format!(
+                    "  {}{}  {}  {}",
+                    cookie.domain,
+                    cookie.path,
+                    expires,
+                    flags.join(" ")
+                ),
+                Style::default().fg(Color::DarkGray),
+            ),
+        ]));
+    }
+    if let Some(edit) = &view.edit {
+        let label = if edit.target.is_some() {
+            "Value"
+        } else {
+            "New cookie"
+        };
+        lines.push(Line::raw(""));
+        lines.push(field_line(label, format!("{}_", edit.input)));
+    }
+
+    let help = if view.edit.is_some() {
+        " [Enter] Save | [Esc] Cancel "
+    } else {
+        " [a] Add | [Enter] Edit Value | [d] Delete | [D] Clear | [Esc] Close "
+    };
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(format!("Cookies ({})", app.cookie_jar_name()))
+        .title(Line::from(help).right_aligned())
+        .style(Style::default().fg(Color::Yellow));
+    f.render_widget(Clear, area);
+    f.render_widget(
+        Paragraph::new(lines)
+            .block(block)
+            .wrap(Wrap { trim: false }),
+        area,
+    );
+}
+
+/// The JWT decoder popup: header and claims of the selected token, with
+/// its time claims as dates
+fn render_jwt(f: &mut Frame, view: &JwtView) {
+    let area = centered_rect(80, 70, f.area());
+    let Some(token) = view.tokens.get(view.selected) else {
+        return;
+    };
+    let now = unix_now();
+    let validity_style = if token.is_expired(now) {
+        Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)
+    } else {
+        Style::default().fg(Color::Green)
+    };
+
+    let pretty = |value: &serde_json::Value| {
+        serde_json::to_string_pretty(value).unwrap_or_else(|_| value.to_string())
+    };
+    let (header, claims) = (pretty(&token.header), pretty(&token.claims));
+    let highlighter = Highlighter::new();
+    let mut lines = vec![
+        field_line(
+            "Token",
+            format!(
+                "{}/{} from {}",
+                view.selected + 1,
+                view.tokens.len(),
+                token.source
+            ),
+        ),
+        Line::styled(token.validity(now), validity_style),
+        Line::raw(""),
+        Line::styled("Header", Style::default().add_modifier(Modifier::BOLD)),
+    ];
+    lines.extend(highlighter.highlight_json(&header));
+    lines.push(Line::raw(""));
+    lines.push(Line::styled(
+        "Claims",
+        Style::default().add_modifier(Modifier::BOLD),
+    ));
+    lines.extend(highlighter.highlight_json(&claims));
+    let dates: Vec<_> = TIME_CLAIMS
+        .iter()
+        .filter_map(|name| {
+            crate::jwt::claim_date(&token.claims[*name]).map(|date| field_line(name, date))
+        })
+        .collect();
+    if !dates.is_empty() {
+        lines.push(Line::raw(""));
+        lines.extend(dates);
+    }
+    lines.push(Line::raw(""));
+    lines.push(Line::styled(
+        "Signature not verified",
+        Style::default().fg(Color::DarkGray),
+    ));
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title("JWT")
+        .title(Line::from(" [←/→] Token | [↑/↓] Scroll | [Esc] Close ").right_aligned())
+        .style(Style::default().fg(Color::Yellow));
+    f.render_widget(Clear, area);
+    f.render_widget(
+        Paragraph::new(lines)
+            .block(block)
+            .wrap(Wrap { trim: false })
+            .scroll((view.scroll, 0)),
+        area,
+    );
+}
+
+/// The key-value table of every request tab but Auth
+fn render_key_values(f: &mut Frame, app: &App, area: Rect, is_editing: bool) {
+    // Render key-value widget for active tab
+    let active_entries = match app.active_request_tab {
+        RequestTab::Headers => &app.headers,
+        RequestTab::Params => &app.params,
+        RequestTab::Settings => &app.settings,
+        RequestTab::Tests => &app.tests,
+        RequestTab::Captures => &app.captures,
+        RequestTab::Authorization | RequestTab::Scripts => return,
+    };
+
+    let kv_widget = KeyValueWidget::new(active_entries)
+        .focused(app.focused_pane == FocusedPane::RequestDetails)
+        .editing(is_editing);
+
+    let hint = match app.active_request_tab {
+        RequestTab::Settings => format!("Keys: {}", SETTING_KEYS.join(", ")),
+        RequestTab::Tests => assertions::HINT.to_string(),
+        RequestTab::Captures => captures::HINT.to_string(),
+        _ => String::new(),
+    };
+    // Rough wrapped height, with a spare line for word breaks
+    let hint_height = (hint.len() as u16).div_ceil(area.width.max(1)) + 1;
+    if !hint.is_empty() && area.height >= hint_height + 3 {
+        let sections = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(0), Constraint::Length(hint_height)])
+            .split(area);
+        kv_widget.render(f, sections[0]);
+        let hint = Paragraph::new(hint)
+            .wrap(Wrap { trim: true })
+            .style(Style::default().fg(Color::DarkGray));
+        f.render_widget(hint, sections[1]);
+    } else {
+        kv_widget.render(f, area);
+    }
+}
+
+/// The Scripts tab: the start of each script, and what scripts can use
+fn render_scripts(f: &mut Frame, app: &App, area: Rect) {
+    const PREVIEW_LINES: usize = 3;
+    let focused = app.focused_pane == FocusedPane::RequestDetails;
+    let mut lines = Vec::new();
+    for stage in [ScriptStage::PreRequest, ScriptStage::PostResponse] {
+        let label_style = if focused && stage == app.script_stage {
+            Style::default().fg(Color::Black).bg(Color::Yellow)
+        } else {
+            Style::default().fg(Color::Cyan)
+        };
+        let script = app.script(stage);
+        let count = script.lines().count();
+        lines.push(Line::from(vec![
+            Span::styled(stage.label(), label_style),
+            Span::styled(
+                match count {
+                    0 => "  (empty)".to_string(),
+                    1 => "  1 line".to_string(),
+                    n => format!("  {} lines", n),
+                },
+                Style::default().fg(Color::DarkGray),
+            ),
+        ]));
+        for line in script.lines().take(PREVIEW_LINES) {
+            lines.push(Line::raw(format!("  {}", line)));
+        }
+        if count > PREVIEW_LINES {
+            lines.push(Line::styled("  …", Style::default().fg(Color::DarkGray)));
+        }
+    }
+    lines.push(Line::raw(""));
+    lines.push(Line::styled(
+        scripts::HINT,
+        Style::default().fg(Color::DarkGray),
+    ));
+    f.render_widget(Paragraph::new(lines).wrap(Wrap { trim: false }), area);
+}
+
+/// The Auth tab: the auth type, then the fields it uses
+fn render_auth(f: &mut Frame, app: &App, area: Rect, is_editing: bool) {
+    let focused = app.focused_pane == FocusedPane::RequestDetails;
+    let mut lines = vec![Line::from(vec![
+        Span::styled("Type: ", Style::default().fg(Color::Cyan)),
+        Span::styled(
+            app.auth.kind.label(),
+            Style::default().add_modifier(Modifier::BOLD),
+        ),
+        Span::styled("  [t] change", Style::default().fg(Color::DarkGray)),
+    ])];
+    let width = app
+        .auth
+        .fields()
+        .iter()
+        .map(|field| field.label().len())
+        .max()
+        .unwrap_or(0);
+    for (idx, &field) in app.auth.fields().iter().enumerate() {
+        let is_focused = focused && idx == app.auth_field;
+        let value = app.auth.value(field);
+        let mut value = if field.is_secret() && !(is_focused && is_editing) {
+            "•".repeat(value.chars().count())
+        } else {
+            value.to_string()
+        };
+        if is_focused && is_editing {
+            value.push('_');
+        }
+        let label_style = if is_focused {
+            Style::default().fg(Color::Black).bg(Color::Yellow)
+        } else {
+            Style::default().fg(Color::Cyan)
+        };
+        lines.push(Line::from(vec![
+            Span::styled(format!("{:<width$}", field.label()), label_style),
+            Span::raw(" "),
+            Span::raw(value),
+        ]));
+    }
+    let hint = match app.auth.kind {
+        AuthKind::None => Some("No credentials are sent".to_string()),
+        AuthKind::Digest => Some("Sent in reply to the server's 401 challenge".to_string()),
+        AuthKind::ApiKey => {
+            Some("[i] on \"Send in\" switches header / query parameter".to_string())
+        }
+        AuthKind::AwsSigV4 => {
+            Some("Signed just before sending; API Gateway's service is execute-api".to_string())
+        }
+        AuthKind::Hmac => Some(format!(
+            "Placeholders: {}",
+            crate::signing::PLACEHOLDERS
+                .map(|p| format!("{{{}}}", p))
+                .join(" ")
+        )),
+        AuthKind::Jwt if app.auth.jwt.algorithm.uses_key_file() => {
+            Some("Key file: PEM, PKCS#8 (or PKCS#1 for RS256); sent as a Bearer token".to_string())
+        }
+        AuthKind::Jwt => Some("Signed on every send and sent as a Bearer token".to_string()),
+        AuthKind::OAuth2 => Some(format!(
+            "{}  [x] clear token",
+            app.oauth_token_status()
+                .unwrap_or_else(|| "Token fetched on send".to_string())
+        )),
+        _ => None,
+    };
+    if let Some(hint) = hint {
+        lines.push(Line::raw(""));
+        lines.push(Line::styled(hint, Style::default().fg(Color::DarkGray)));
+    }
+    f.render_widget(Paragraph::new(lines).wrap(Wrap { trim: false }), area);
+}
+
+/// A rectangle of the given percentage size centered in `area`
+fn centered_rect(percent_x: u16, percent_y: u16, area: Rect) -> Rect {
+    let vertical = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Percentage((100 - percent_y) / 2),
+            Constraint::Percentage(percent_y),
+            Constraint::Percentage((100 - percent_y) / 2),
+        ])
+        .split(area);
+    Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Percentage((100 - percent_x) / 2),
+            Constraint::Percentage(percent_x),
+            Constraint::Percentage((100 - percent_x) / 2),
+        ])
+        .split(vertical[1])[1]
+}
+
+/// Render the saved-request tree, with the name prompt at the bottom when active
+fn render_collections(f: &mut Frame, app: &App, area: Rect) {
+    let is_focused = app.focused_pane == FocusedPane::Collections;
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title("Collections")
+        .style(if is_focused {
+            Style::default().fg(Color::Yellow)
+        } else {
+            Style::default()
+        });
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let prompt_height = u16::from(app.collection_prompt.is_some());
+    let sections = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(0), Constraint::Length(prompt_height)])
+        .split(inner);
+
+    let rows = app.collection_rows();
+    let mut lines = Vec::new();
+    for (idx, row) in rows.iter().enumerate() {
+        let indent = "  ".repeat(row.depth);
+        let mut spans = vec![Span::raw(indent)];
+        match &row.method {
+            Some(method) => {
+                spans.push(Span::styled(
+                    format!("{:<7} ", method.to_string()),
+                    Style::default().fg(Color::Green),
+                ));
+                spans.push(Span::raw(row.name.clone()));
+            }
+            None => spans.push(Span::styled(
+                format!("▾ {}/", row.name),
+                Style::default().fg(Color::Cyan),
+            )),
+        }
+
+        let mut line = Line::from(spans);
+        if is_focused && idx == app.collection_selected {
+            line = line.style(Style::default().bg(Color::DarkGray));
+        }
+        lines.push(line);
+    }
+    if lines.is_empty() {
+        lines.push(Line::from(Span::styled(
+            "(empty - press s to save)",
+            Style::default().fg(Color::DarkGray),
+        )));
+    }
+
+    // Keep the selection visible
+    let visible = sections[0].height as usize;
+    let offset = (app.collection_selected + 1).saturating_sub(visible);
+    f.render_widget(
+        Paragraph::new(lines).scroll((offset as u16, 0)),
+        sections[0],
+    );
+
+    if let Some(prompt) = &app.collection_prompt {
+        let label = match prompt.action {
+            CollectionAction::SaveRequest => "Save as: ",
+            CollectionAction::NewFolder => "Folder: ",
+            CollectionAction::Rename => "Rename: ",
+            CollectionAction::ConfirmDelete => "Delete? (y/n) ",
+        };
+        let prompt_line = Line::from(vec![
+            Span::styled(label, Style::default().fg(Color::Cyan)),
+            Span::raw(format!("{}_", prompt.input)),
+        ]);
+        f.render_widget(Paragraph::new(prompt_line), sections[1]);
+    }
+}
+
+/// A row of `[Label]` tabs with the active one highlighted
+fn tab_bar<'a>(tabs: impl Iterator<Item = (&'a str, bool)>) -> Line<'a> {
+    let mut tab_spans = Vec::new();
+    for (i, (label, is_active)) in tabs.enumerate() {
+        if i > 0 {
+            tab_spans.push(Span::raw(" "));
+        }
+
+        let style = if is_active {
+            Style::default()
+                .fg(Color::Blue)
+                .add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(Color::DarkGray)
+        };
+
+        tab_spans.push(Span::styled(format!("[{}]", label), style));
+    }
+    Line::from(tab_spans)
+}
+
+/// A `label: value` line with the label highlighted
+fn field_line(label: &str, value: String) -> Line<'static> {
+    Line::from(vec![
+        Span::styled(format!("{}: ", label), Style::default().fg(Color::Cyan)),
+        Span::raw(value),
+    ])
+}
+
+fn response_headers_text(resp: &ApiResponse) -> Text<'static> {
+    if resp.headers.is_empty() {
+        return Text::raw("No headers");
+    }
+    Text::from(
+        resp.headers
+            .iter()
+            .map(|(name, value)| field_line(name, value.clone()))
+            .collect::<Vec<_>>(),
+    )
+}
+
+fn response_tests_text(results: &[TestResult], log: &[String]) -> Text<'static> {
+    if results.is_empty() && log.is_empty() {
+        return Text::raw("No tests. Add assertions in the request's Tests tab.");
+    }
+
+    let mut lines = Vec::new();
+    for result in results {
+        let (mark, color) = if result.passed {
+            ("✓", Color::Green)
+        } else {
+            ("✗", Color::Red)
+        };
+        lines.push(Line::from(vec![
+            Span::styled(format!("{} ", mark), Style::default().fg(color)),
+            Span::raw(result.name.clone()),
+            Span::styled(
+                format!("  {}", result.message),
+                Style::default().fg(Color::DarkGray),
+            ),
+        ]));
+    }
+    let failed = results.iter().filter(|r| !r.passed).count();
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled(
+        format!("{} passed, {} failed", results.len() - failed, failed),
+        Style::default().fg(if failed == 0 {
+            Color::Green
+        } else {
+            Color::Red
+        }),
+    )));
+    if !log.is_empty() {
+        lines.push(Line::from(""));
+        lines.push(Line::styled(
+            "Script output",
+            Style::default().fg(Color::Cyan),
+        ));
+        lines.extend(log.iter().map(|line| Line::raw(line.clone())));
+    }
+    Text::from(lines)
+}
+
+fn response_cookies_text(resp: &ApiResponse) -> Text<'static> {
+    let cookies = resp.cookies();
+    if cookies.is_empty() {
+        return Text::raw("No cookies set by this response");
+    }
+
+    let mut lines = Vec::new();
+    for cookie in cookies {
+        lines.push(field_line(&cookie.name, cookie.value.clone()));
+        let mut attrs = Vec::new();
+        if let Some(domain) = &cookie.domain {
+            attrs.push(format!("Domain={}", domain));
+        }
+        if let Some(path) = &cookie.path {
+            attrs.push(format!("Path={}", path));
+        }
+        if let Some(expires) = &cookie.expires {
+            attrs.push(format!("Expires={}", expires));
+        }
+        if let Some(max_age) = cookie.max_age {
+            attrs.push(format!("Max-Age={}", max_age));
+        }
+        if let Some(same_site) = &cookie.same_site {
+            attrs.push(format!("SameSite={}", same_site));
+        }
+        if cookie.secure {
+            attrs.push("Secure".to_string());
+        }
+        if cookie.http_only {
+            attrs.push("HttpOnly".to_string());
+        }
+        if !attrs.is_empty() {
+            lines.push(Line::from(Span::styled(
+                format!("  {}", attrs.join("; ")),
+                Style::default().fg(Color::DarkGray),
+            )));
+        }
+    }
+    Text::from(lines)
+}
+
+fn response_timing_text(resp: &ApiResponse, width: u16) -> Text<'static> {
+    let mut lines = vec![
+        field_line("Status", resp.status.to_string()),
+        field_line("HTTP version", resp.version.clone()),
+        field_line("Final URL", resp.url.clone()),
+        field_line("Size", format_bytes(resp.size)),
+        field_line("Time", format_duration(resp.timing.total)),
+        Line::raw(""),
+    ];
+    lines.extend(timing_waterfall(&resp.timing, width));
+    Text::from(lines)
+}
+
+/// One bar per phase, offset by when the phase started
+fn timing_waterfall(timing: &Timing, width: u16) -> Vec<Line<'static>> {
+    const LABEL_WIDTH: usize = 14;
+    const DURATION_WIDTH: usize = 10;
+    let bar_width = (width as usize)
+        .saturating_sub(LABEL_WIDTH + DURATION_WIDTH + 2)
+        .max(10);
+    let total = timing.total.as_secs_f64().max(f64::EPSILON);
+    let colors = [
+        Color::Cyan,
+        Color::Yellow,
+        Color::Magenta,
+        Color::Green,
+        Color::Blue,
+    ];
+
+    let mut lines = Vec::new();
+    for ((label, start, duration), color) in timing.phases().into_iter().zip(colors) {
+        let offset = ((start.as_secs_f64() / total) * bar_width as f64).round() as usize;
+        let mut len = ((duration.as_secs_f64() / total) * bar_width as f64).round() as usize;
+        if !duration.is_zero() {
+            len = len.max(1);
+        }
+        let offset = offset.min(bar_width.saturating_sub(len));
+        lines.push(Line::from(vec![
+            Span::styled(
+                format!("{:<width$}", label, width = LABEL_WIDTH),
+                Style::default().fg(Color::Cyan),
+            ),
+            Span::raw(" ".repeat(offset)),
+            Span::styled("█".repeat(len), Style::default().fg(color)),
+            Span::raw(" ".repeat(bar_width - offset - len + 1)),
+            Span::raw(format!(
+                "{:>width$}",
+                format_duration(duration),
+                width = DURATION_WIDTH
+            )),
+        ]));
+    }
+    if timing.dns.is_zero() && timing.connect.is_zero() {
+        lines.push(Line::styled(
+            "Reused an open connection; set fresh_connection = true to time a cold start",
+            Style::default().fg(Color::DarkGray),
+        ));
+    }
+    lines
+}
+
+fn format_bytes(bytes: usize) -> String {
+    match bytes {
+        b if b < 1024 => format!("{} B", b),
+        b if b < 1024 * 1024 => format!("{:.1} KB", b as f64 / 1024.0),
+        b => format!("{:.1} MB", b as f64 / (1024.0 * 1024.0)),
+    }
+}
+
+fn format_duration(duration: Duration) -> String {
+    let millis = duration.as_secs_f64() * 1000.0;
+    if millis < 10.0 {
+        format!("{:.1} ms", millis)
+    } else if millis < 1000.0 {
+        format!("{:.0} ms", millis)
+    } else {
+        format!("{:.2} s", millis / 1000.0)
+    }
 }
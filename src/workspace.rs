@@ -0,0 +1,125 @@
+use anyhow::Result;
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use crate::config::Config;
+use crate::cookies::CookieJars;
+use crate::environment::EnvironmentStore;
+use crate::network::{make_request, ApiResponse, ClientPool, HttpClient};
+use crate::request::RequestSpec;
+use crate::scripts::{self, ResponseChecks};
+use crate::settings::effective_settings;
+
+/// The workspace files a headless `send` or `run` reads: config defaults,
+/// environments and cookie jars
+pub struct Workspace {
+    request_defaults: BTreeMap<String, String>,
+    pub environments: EnvironmentStore,
+    cookie_jars: CookieJars,
+    clients: ClientPool,
+}
+
+impl Workspace {
+    /// Load the workspace with the environment called `env` active
+    pub fn load(env: Option<&str>) -> Result<Self> {
+        let config = Config::load()?;
+        let mut environments = EnvironmentStore::load(EnvironmentStore::default_path())?;
+        if let Some(name) = env {
+            environments.select(name)?;
+        }
+        let cookie_jars = if config.persist_cookies {
+            CookieJars::load(CookieJars::default_path())?
+        } else {
+            CookieJars::in_memory()
+        };
+        Ok(Self {
+            request_defaults: config.request,
+            environments,
+            cookie_jars,
+            clients: ClientPool::default(),
+        })
+    }
+
+    /// No config file, environments or saved cookies
+    #[cfg(test)]
+    pub fn detached() -> Self {
+        Self {
+            request_defaults: BTreeMap::new(),
+            environments: EnvironmentStore::detached(),
+            cookie_jars: CookieJars::in_memory(),
+            clients: ClientPool::default(),
+        }
+    }
+
+    /// The request after its pre-request script, with variables
+    /// substituted, and the client to send it with, chosen the same way as
+    /// in the UI
+    pub fn prepare(&mut self, request: &RequestSpec) -> Result<(RequestSpec, HttpClient)> {
+        let mut request = request.clone();
+        for line in scripts::pre_request(&mut request, &mut self.environments)? {
+            eprintln!("{}", line);
+        }
+        let request = self.environments.resolve(&request)?;
+        let environment = self.environments.active().map(|env| &env.settings);
+        let settings = effective_settings(&self.request_defaults, environment, &request)?;
+        let jar_name = self.environments.cookie_jar_name();
+        let jar = self.cookie_jars.jar(&jar_name);
+        let client = self.clients.client(&settings, Some((&jar_name, jar)))?;
+        Ok((request, client))
+    }
+
+    /// Send `request` with `client`, as `prepare` returned them for
+    /// `original`, and check the response as the UI does. For as long as
+    /// the post-response script asks for a retry, `original` is prepared
+    /// and sent again, so the pre-request script reruns and variables set
+    /// by the last response are used.
+    pub async fn exchange(
+        &mut self,
+        original: &RequestSpec,
+        mut request: RequestSpec,
+        mut client: HttpClient,
+    ) -> Result<Exchange> {
+        let mut attempt = 1;
+        let mut time = Duration::ZERO;
+        loop {
+            let response = make_request(&client, &request).await?;
+            time += response.timing.total;
+            let mut checks = scripts::after_response(&request, &response, &mut self.environments);
+            for line in &checks.log {
+                eprintln!("{}", line);
+            }
+            match checks.retry {
+                Some(delay) if attempt < scripts::MAX_ATTEMPTS => {
+                    attempt += 1;
+                    tokio::time::sleep(delay).await;
+                    (request, client) = self.prepare(original)?;
+                    continue;
+                }
+                Some(_) => checks.tests.push(scripts::retries_exhausted()),
+                None => {}
+            }
+            return Ok(Exchange {
+                request,
+                response,
+                checks,
+                time,
+            });
+        }
+    }
+
+    /// Save cookies received so far, if they are persisted
+    pub fn save_cookies(&self) -> Result<()> {
+        self.cookie_jars.save()
+    }
+}
+
+/// The last response to a request, once the post-response script stopped
+/// asking for retries
+pub struct Exchange {
+    /// The request as sent the last time
+    pub request: RequestSpec,
+    pub response: ApiResponse,
+    pub checks: ResponseChecks,
+    /// Time spent sending, summed over every attempt
+    pub time: Duration,
+}